@@ -6,14 +6,18 @@ use syn::{parse_macro_input, AttributeArgs};
 #[derive(Debug, FromMeta)]
 struct PlcProgramArgs {
     #[darling(rename = "loop")]
-    lp: String,
+    lp: Option<String>,
     #[darling()]
     shift: Option<String>,
+    #[darling()]
+    trigger: Option<String>,
 }
 
+/// Either "loop" (interval) or "trigger" (comma-separated trigger names) must be specified
+///
 /// # Panics
 ///
-/// Will panic if function name is more than 14 symbols
+/// Will panic if function name is more than 14 symbols or the arguments are invalid
 #[proc_macro_attribute]
 pub fn plc_program(args: TokenStream, input: TokenStream) -> TokenStream {
     let attr_args = parse_macro_input!(args as AttributeArgs);
@@ -24,7 +28,6 @@ pub fn plc_program(args: TokenStream, input: TokenStream) -> TokenStream {
         }
     };
     let item: syn::Item = syn::parse(input).expect("Invalid input");
-    let int = parse_interval(&args.lp).unwrap();
     let shift: u64 = args
         .shift
         .map(|v| parse_interval(&v).unwrap())
@@ -49,17 +52,66 @@ pub fn plc_program(args: TokenStream, input: TokenStream) -> TokenStream {
         );
         let spawner_name = format_ident!("{}_spawn", name);
         let prgname = name.to_string();
+        let program_name = format_ident!("{}_program", name);
+        let (spawner, period) = match (args.lp, args.trigger) {
+            (Some(lp), None) => {
+                let int = parse_interval(&lp).unwrap();
+                (
+                    quote! {
+                        ::rplc::tasks::spawn_program_loop(#prgname,
+                            #name,
+                            ::std::time::Duration::from_nanos(#int),
+                            ::std::time::Duration::from_nanos(#shift)
+                            );
+                    },
+                    quote! {
+                        ::rplc::testing::ProgramPeriod::Loop {
+                            interval: ::std::time::Duration::from_nanos(#int),
+                            shift: ::std::time::Duration::from_nanos(#shift),
+                        }
+                    },
+                )
+            }
+            (None, Some(trigger)) => {
+                assert!(shift == 0, "shift can not be used with trigger ({})", name);
+                let triggers: Vec<&str> = trigger
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .collect();
+                assert!(!triggers.is_empty(), "trigger can not be empty ({})", name);
+                (
+                    quote! {
+                        ::rplc::tasks::spawn_program(#prgname,
+                            #name,
+                            ::rplc::tasks::Period::Trigger(
+                                ::rplc::trigger::Listener::new(&[#(#triggers),*])
+                                )
+                            );
+                    },
+                    quote! {
+                        ::rplc::testing::ProgramPeriod::Trigger(&[#(#triggers),*])
+                    },
+                )
+            }
+            _ => panic!("either loop or trigger must be specified ({})", name),
+        };
         let f = quote! {
             fn #spawner_name() {
-                ::rplc::tasks::spawn_program_loop(#prgname,
-                    #name,
-                    ::std::time::Duration::from_nanos(#int),
-                    ::std::time::Duration::from_nanos(#shift)
-                    );
+                #spawner
             }
             fn #name() {
                 #block
             }
+            /// program definition for ::rplc::testing::Harness
+            #[allow(dead_code)]
+            fn #program_name() -> ::rplc::testing::Program {
+                ::rplc::testing::Program {
+                    name: #prgname,
+                    f: #name,
+                    period: #period,
+                }
+            }
         };
         f.into_token_stream().into()
     } else {
@@ -4,7 +4,6 @@ mod plc;
 mod plc_types;
 
 use std::time::Duration;
-use std::time::Instant;
 
 #[plc_program(loop = "500ms")]
 fn p1() {
@@ -18,17 +17,33 @@ fn p1() {
         ctx.fan2 = true;
     }
     info!("fan1: {}, fan2: {}", ctx.fan, ctx.fan2);
+    let fan = ctx.fan;
+    if ctx.fan_edge.call(fan) {
+        info!("fan1 turned on");
+    }
+    ctx.fan_delay.pt = Duration::from_secs(5);
+    if ctx.fan_delay.call(fan) {
+        info!("fan1 has been on for 5 seconds");
+    }
     ctx.data.subfield.a += 1_000_000_000;
     ctx.data.subfield.b += 10;
     let temp = ctx.temperature;
     ctx.modbus.set_holding(20, (temp * 100.0) as u16).unwrap();
-    ctx.timers.t1 = Some(Instant::now());
+    ctx.timers.t1 = Some(rplc::clock::now());
     if let Some(info) = rplc::tasks::controller_stats().lock().current_thread_info() {
         ctx.modbus.set_inputs_from_u32(100, info.iters).unwrap();
-        ctx.modbus.set_input(102, info.jitter_max).unwrap();
-        ctx.modbus.set_input(103, info.jitter_min).unwrap();
-        ctx.modbus.set_input(104, info.jitter_avg).unwrap();
-        ctx.modbus.set_input(105, info.jitter_last).unwrap();
+        ctx.modbus
+            .set_input(102, u16::try_from(info.jitter_max).unwrap_or(u16::MAX))
+            .unwrap();
+        ctx.modbus
+            .set_input(103, u16::try_from(info.jitter_min).unwrap_or(u16::MAX))
+            .unwrap();
+        ctx.modbus
+            .set_input(104, u16::try_from(info.jitter_avg).unwrap_or(u16::MAX))
+            .unwrap();
+        ctx.modbus
+            .set_input(105, u16::try_from(info.jitter_last).unwrap_or(u16::MAX))
+            .unwrap();
     }
 }
 
@@ -49,6 +64,11 @@ fn p2() {
     }
 }
 
+#[plc_program(trigger = "ctx.temperature")]
+fn p3() {
+    info!("temperature changed: {}", plc_context!().temperature);
+}
+
 fn get_if_status(
     oid: &[u32],
     session: &mut snmp::SyncSession,
@@ -108,24 +128,14 @@ fn shutdown() {
     warn!("shutdown program completed");
 }
 
-use std::fs;
-
 fn main() {
     init_plc!();
     rplc::tasks::on_shutdown(shutdown);
-    if let Ok(data) = fs::read("plc.dat") {
-        info!("loading context");
-        *plc_context_mut!() = rmp_serde::from_slice(&data).unwrap();
-    }
     p1_spawn();
     p2_spawn();
+    p3_spawn();
     spawn_check_router();
     spawn_relays();
     rplc::tasks::spawn_stats_log(Duration::from_secs(5));
     run_plc!();
-    fs::write(
-        "plc.dat",
-        rmp_serde::to_vec_named(&*plc_context!()).unwrap(),
-    )
-    .unwrap();
 }
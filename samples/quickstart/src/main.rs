@@ -17,3 +17,22 @@ fn main() {
     tempmon_spawn();
     run_plc!();
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rplc::testing::Harness;
+    use std::time::Duration;
+
+    #[test]
+    fn test_tempmon() {
+        let mut h = Harness::new();
+        h.add(tempmon_program());
+        plc_context_mut!().temperature = 31.0;
+        h.run_for(Duration::from_secs(1));
+        assert!(plc_context!().fan);
+        plc_context_mut!().temperature = 20.0;
+        h.step();
+        assert!(!plc_context!().fan);
+    }
+}
@@ -0,0 +1,566 @@
+use crate::interval::Loop;
+use crate::{clock, tasks};
+use eva_common::{EResult, Error};
+use log::{error, info, warn};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+static ALARMS: OnceCell<&'static Alarms> = OnceCell::new();
+static STATES: Mutex<Vec<AlarmState>> = Mutex::new(Vec::new());
+
+/// Alarms, generated as crate::plc::ALARMS from the "alarms" section of plc.yml
+pub struct Alarms {
+    /// evaluation interval
+    pub interval: Duration,
+    pub alarms: &'static [Alarm],
+}
+
+pub struct Alarm {
+    pub id: &'static str,
+    pub condition: Condition,
+    /// the condition must be true for the delay to activate the alarm
+    pub delay: Duration,
+    pub severity: Severity,
+    /// a latched alarm stays active after the condition is cleared until acknowledged
+    pub latch: bool,
+    pub description: &'static str,
+    /// EVA ICS item OID to publish the alarm state to
+    pub oid: Option<&'static str>,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info = 1,
+    #[default]
+    Warning = 2,
+    Critical = 3,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Severity::Info => "info",
+                Severity::Warning => "warning",
+                Severity::Critical => "critical",
+            }
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+}
+
+impl Op {
+    fn as_str(self) -> &'static str {
+        match self {
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Eq => "==",
+            Op::Ne => "!=",
+        }
+    }
+    #[allow(clippy::float_cmp)]
+    fn eval(self, a: f64, b: f64) -> bool {
+        match self {
+            Op::Gt => a > b,
+            Op::Ge => a >= b,
+            Op::Lt => a < b,
+            Op::Le => a <= b,
+            Op::Eq => a == b,
+            Op::Ne => a != b,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Operand {
+    Number(f64),
+    Path(&'static str),
+}
+
+/// Alarm condition: "PATH OP VALUE", "PATH OP PATH", "PATH" (not zero) or "!PATH" (zero)
+///
+/// Context field values are compared as numbers, booleans are converted to 0/1
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Condition {
+    pub path: &'static str,
+    pub op: Op,
+    pub operand: Operand,
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ", self.path, self.op.as_str())?;
+        match self.operand {
+            Operand::Number(v) => write!(f, "{}", v),
+            Operand::Path(p) => write!(f, "{}", p),
+        }
+    }
+}
+
+/// Parsed alarm condition with owned paths, used by the builder
+#[derive(Debug)]
+pub struct ConditionDef {
+    pub path: String,
+    pub op: Op,
+    pub operand: OperandDef,
+}
+
+#[derive(Debug)]
+pub enum OperandDef {
+    Number(f64),
+    Path(String),
+}
+
+fn parse_path(s: &str) -> EResult<String> {
+    crate::context::split_path(s)?;
+    if s.contains(char::is_whitespace) {
+        return Err(Error::invalid_params(format!("invalid path: {}", s)));
+    }
+    Ok(s.to_owned())
+}
+
+impl FromStr for ConditionDef {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        for (op_s, op) in [
+            (">=", Op::Ge),
+            ("<=", Op::Le),
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            (">", Op::Gt),
+            ("<", Op::Lt),
+        ] {
+            if let Some((path, operand)) = s.split_once(op_s) {
+                let operand = operand.trim();
+                let operand = match operand {
+                    "true" => OperandDef::Number(1.0),
+                    "false" => OperandDef::Number(0.0),
+                    _ => {
+                        if let Ok(v) = operand.parse::<f64>() {
+                            if !v.is_finite() {
+                                return Err(Error::invalid_params(format!(
+                                    "invalid condition value: {}",
+                                    operand
+                                )));
+                            }
+                            OperandDef::Number(v)
+                        } else {
+                            OperandDef::Path(parse_path(operand)?)
+                        }
+                    }
+                };
+                return Ok(Self {
+                    path: parse_path(path.trim())?,
+                    op,
+                    operand,
+                });
+            }
+        }
+        if let Some(path) = s.strip_prefix('!') {
+            Ok(Self {
+                path: parse_path(path.trim())?,
+                op: Op::Eq,
+                operand: OperandDef::Number(0.0),
+            })
+        } else {
+            Ok(Self {
+                path: parse_path(s)?,
+                op: Op::Ne,
+                operand: OperandDef::Number(0.0),
+            })
+        }
+    }
+}
+
+impl ConditionDef {
+    /// Context paths used by the condition
+    pub fn paths(&self) -> Vec<&str> {
+        let mut result = vec![self.path.as_str()];
+        if let OperandDef::Path(ref p) = self.operand {
+            result.push(p);
+        }
+        result
+    }
+    /// Generates the condition code
+    pub fn to_code(&self) -> String {
+        format!(
+            "::rplc::alarm::Condition {{ path: {:?}, op: ::rplc::alarm::Op::{:?}, operand: ::rplc::alarm::Operand::{} }}",
+            self.path,
+            self.op,
+            match self.operand {
+                OperandDef::Number(v) => format!("Number({:?})", v),
+                OperandDef::Path(ref p) => format!("Path({:?})", p),
+            }
+        )
+    }
+}
+
+#[derive(Default)]
+struct AlarmState {
+    condition: bool,
+    condition_since: Option<Instant>,
+    active: bool,
+    acked: bool,
+    activated: Option<f64>,
+    cleared: Option<f64>,
+    failed: bool,
+}
+
+impl AlarmState {
+    /// the EVA ICS item value: 0 - normal, 1 - active, 2 - active and acknowledged
+    #[cfg(feature = "eva")]
+    fn item_value(&self) -> u8 {
+        if self.active {
+            if self.acked {
+                2
+            } else {
+                1
+            }
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AlarmInfo {
+    pub id: String,
+    pub severity: Severity,
+    pub condition: String,
+    pub description: String,
+    pub latch: bool,
+    /// the alarm condition is currently true
+    pub condition_active: bool,
+    pub active: bool,
+    pub acked: bool,
+    /// last activation timestamp
+    pub activated: Option<f64>,
+    /// last clear timestamp
+    pub cleared: Option<f64>,
+}
+
+fn timestamp() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+/// use init_plc!() macro to register alarms
+///
+/// # Panics
+///
+/// Will panic if called twice
+pub fn register(alarms: Option<&'static Alarms>) {
+    if let Some(a) = alarms {
+        assert!(ALARMS.set(a).is_ok(), "alarms are already registered");
+        STATES
+            .lock()
+            .resize_with(a.alarms.len(), AlarmState::default);
+    }
+}
+
+/// Spawns the alarm evaluation task, called by the controller after I/O is launched
+pub(crate) fn spawn() {
+    if let Some(alarms) = ALARMS.get().copied() {
+        info!("alarms: {}", alarms.alarms.len());
+        tasks::spawn_service("alarms", move || {
+            let mut int = Loop::prepare0(alarms.interval);
+            loop {
+                int.tick();
+                evaluate(alarms);
+                #[cfg(feature = "eva")]
+                publish(alarms);
+            }
+        });
+    }
+}
+
+fn eval_condition(c: &Condition) -> EResult<bool> {
+    let value = f64::try_from(crate::context::get(c.path)?)?;
+    let operand = match c.operand {
+        Operand::Number(v) => v,
+        Operand::Path(p) => f64::try_from(crate::context::get(p)?)?,
+    };
+    Ok(c.op.eval(value, operand))
+}
+
+fn evaluate(alarms: &Alarms) {
+    let now = clock::now();
+    let mut states = STATES.lock();
+    for (alarm, state) in alarms.alarms.iter().zip(states.iter_mut()) {
+        match eval_condition(&alarm.condition) {
+            Ok(condition) => {
+                state.failed = false;
+                update(alarm, state, condition, now);
+            }
+            Err(e) => {
+                // the alarm state is kept until the condition can be evaluated again
+                if !state.failed {
+                    error!("alarm {} condition error: {}", alarm.id, e);
+                    state.failed = true;
+                }
+            }
+        }
+    }
+}
+
+fn update(alarm: &Alarm, state: &mut AlarmState, condition: bool, now: Instant) {
+    state.condition = condition;
+    if condition {
+        let since = *state.condition_since.get_or_insert(now);
+        if !state.active && now.saturating_duration_since(since) >= alarm.delay {
+            state.active = true;
+            state.acked = false;
+            state.activated = Some(timestamp());
+            state.cleared = None;
+            warn!(
+                "alarm {} ({}) activated: {}",
+                alarm.id, alarm.severity, alarm.condition
+            );
+        }
+    } else {
+        state.condition_since = None;
+        if state.active && (!alarm.latch || state.acked) {
+            clear(alarm, state);
+        }
+    }
+}
+
+fn clear(alarm: &Alarm, state: &mut AlarmState) {
+    state.active = false;
+    state.cleared = Some(timestamp());
+    info!("alarm {} cleared", alarm.id);
+}
+
+/// Publishes changed alarm states to EVA ICS
+#[cfg(feature = "eva")]
+fn publish(alarms: &Alarms) {
+    use eva_common::events::RawStateEventOwned;
+    use eva_common::value::Value;
+    use eva_common::OID;
+    use eva_sdk::controller::RawStateEventPreparedOwned;
+    use std::collections::HashMap;
+    static LAST: Mutex<Vec<Option<u8>>> = Mutex::new(Vec::new());
+    static OIDS: OnceCell<Vec<Option<OID>>> = OnceCell::new();
+    let oids = OIDS.get_or_init(|| {
+        alarms
+            .alarms
+            .iter()
+            .map(|a| a.oid.map(|oid| oid.parse().unwrap()))
+            .collect()
+    });
+    let states = STATES.lock();
+    let mut last = LAST.lock();
+    last.resize(states.len(), None);
+    let mut result: HashMap<&OID, RawStateEventPreparedOwned> = HashMap::new();
+    for ((oid, state), last_value) in oids.iter().zip(states.iter()).zip(last.iter_mut()) {
+        if let Some(oid) = oid {
+            let value = state.item_value();
+            if *last_value != Some(value) {
+                *last_value = Some(value);
+                result.insert(
+                    oid,
+                    RawStateEventPreparedOwned::from_rse_owned(
+                        RawStateEventOwned::new(1, Value::U8(value)),
+                        None,
+                    ),
+                );
+            }
+        }
+    }
+    drop(states);
+    if let Err(e) = crate::eapi::notify(result) {
+        // force re-publishing when EAPI is back
+        last.clear();
+        log::debug!("unable to publish alarm states: {}", e);
+    }
+}
+
+pub fn list() -> Vec<AlarmInfo> {
+    if let Some(alarms) = ALARMS.get() {
+        alarms
+            .alarms
+            .iter()
+            .zip(STATES.lock().iter())
+            .map(|(alarm, state)| AlarmInfo {
+                id: alarm.id.to_owned(),
+                severity: alarm.severity,
+                condition: alarm.condition.to_string(),
+                description: alarm.description.to_owned(),
+                latch: alarm.latch,
+                condition_active: state.condition,
+                active: state.active,
+                acked: state.acked,
+                activated: state.activated,
+                cleared: state.cleared,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Acknowledges an active alarm. Latched alarms with the condition cleared are cleared as well
+pub fn ack(id: &str) -> EResult<()> {
+    let alarms = ALARMS
+        .get()
+        .ok_or_else(|| Error::not_found(format!("alarm {} not found", id)))?;
+    let pos = alarms
+        .alarms
+        .iter()
+        .position(|a| a.id == id)
+        .ok_or_else(|| Error::not_found(format!("alarm {} not found", id)))?;
+    let mut states = STATES.lock();
+    let state = &mut states[pos];
+    if !state.active {
+        return Err(Error::failed(format!("alarm {} is not active", id)));
+    }
+    ack_alarm(&alarms.alarms[pos], state);
+    Ok(())
+}
+
+/// Acknowledges all active alarms
+pub fn ack_all() {
+    if let Some(alarms) = ALARMS.get() {
+        let mut states = STATES.lock();
+        for (alarm, state) in alarms.alarms.iter().zip(states.iter_mut()) {
+            if state.active && !state.acked {
+                ack_alarm(alarm, state);
+            }
+        }
+    }
+}
+
+fn ack_alarm(alarm: &Alarm, state: &mut AlarmState) {
+    state.acked = true;
+    info!("alarm {} acknowledged", alarm.id);
+    if !state.condition {
+        clear(alarm, state);
+    }
+}
+
+/// Returns the number of active alarms
+pub fn active_count() -> usize {
+    STATES.lock().iter().filter(|s| s.active).count()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_condition() {
+        let c: ConditionDef = "temperature > 30".parse().unwrap();
+        assert_eq!(c.path, "temperature");
+        assert_eq!(c.op, Op::Gt);
+        assert!(matches!(c.operand, OperandDef::Number(v) if v == 30.0));
+        let c: ConditionDef = "data.a>=connector[1].voltage".parse().unwrap();
+        assert_eq!(c.op, Op::Ge);
+        assert!(matches!(c.operand, OperandDef::Path(ref p) if p == "connector[1].voltage"));
+        let c: ConditionDef = "!fan".parse().unwrap();
+        assert_eq!((c.path.as_str(), c.op), ("fan", Op::Eq));
+        let c: ConditionDef = "fan == true".parse().unwrap();
+        assert!(matches!(c.operand, OperandDef::Number(v) if v == 1.0));
+        assert!("temperature >".parse::<ConditionDef>().is_err());
+        assert!("temp erature > 1".parse::<ConditionDef>().is_err());
+        assert!("x > inf".parse::<ConditionDef>().is_err());
+    }
+
+    fn alarm(latch: bool) -> Alarm {
+        Alarm {
+            id: "test",
+            condition: Condition {
+                path: "alarm_test_missing",
+                op: Op::Gt,
+                operand: Operand::Number(30.0),
+            },
+            delay: Duration::from_secs(1),
+            severity: Severity::Warning,
+            latch,
+            description: "",
+            oid: None,
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        clock::set_virtual(Some(Instant::now()));
+        // the condition must be true for the delay
+        let a = alarm(false);
+        let mut state = AlarmState::default();
+        update(&a, &mut state, true, clock::now());
+        clock::advance(Duration::from_millis(600));
+        update(&a, &mut state, true, clock::now());
+        assert!(!state.active);
+        update(&a, &mut state, false, clock::now());
+        update(&a, &mut state, true, clock::now());
+        clock::advance(Duration::from_millis(600));
+        update(&a, &mut state, true, clock::now());
+        assert!(!state.active);
+        clock::advance(Duration::from_millis(400));
+        update(&a, &mut state, true, clock::now());
+        assert!(state.active && state.activated.is_some());
+        // ack keeps the alarm active while the condition is true
+        ack_alarm(&a, &mut state);
+        assert!(state.active && state.acked);
+        // a non-latched alarm is cleared with the condition
+        update(&a, &mut state, false, clock::now());
+        assert!(!state.active && state.cleared.is_some());
+        // a latched alarm stays active until acknowledged
+        let a = alarm(true);
+        let mut state = AlarmState::default();
+        update(&a, &mut state, true, clock::now());
+        clock::advance(Duration::from_secs(1));
+        update(&a, &mut state, true, clock::now());
+        update(&a, &mut state, false, clock::now());
+        assert!(state.active && !state.acked);
+        ack_alarm(&a, &mut state);
+        assert!(!state.active);
+        // re-activated after the delay, the ack is reset
+        update(&a, &mut state, true, clock::now());
+        clock::advance(Duration::from_secs(2));
+        update(&a, &mut state, true, clock::now());
+        assert!(state.active && !state.acked);
+        clock::set_virtual(None);
+    }
+
+    #[test]
+    fn test_condition_error() {
+        let alarms = Alarms {
+            interval: Duration::from_secs(1),
+            alarms: Box::leak(Box::new([alarm(false)])),
+        };
+        *STATES.lock() = vec![AlarmState {
+            condition: true,
+            condition_since: Some(clock::now()),
+            active: true,
+            ..AlarmState::default()
+        }];
+        // the condition path can not be read, the alarm state is kept
+        evaluate(&alarms);
+        let states = STATES.lock();
+        assert!(states[0].failed);
+        assert!(states[0].active && states[0].condition);
+        assert!(states[0].condition_since.is_some());
+    }
+}
@@ -2,7 +2,8 @@ use crate::tasks;
 use eva_common::payload::{pack, unpack};
 use eva_common::value::{to_value, Value};
 use eva_common::{EResult, Error};
-use log::error;
+use log::{debug, error};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fs;
@@ -10,9 +11,16 @@ use std::io;
 use std::io::{Read, Write};
 use std::os::unix;
 use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 const JSON_RPC: &str = "2.0";
 const MAX_API_CONN: usize = 10;
+// watch streams occupy API connection threads, keep the rest for regular calls
+const MAX_WATCHERS: usize = MAX_API_CONN / 2;
+const MIN_WATCH_INTERVAL: Duration = Duration::from_millis(10);
+const WATCH_HEARTBEAT: Duration = Duration::from_secs(1);
+
+static WATCHERS: Mutex<usize> = Mutex::new(0);
 
 #[derive(Serialize, Deserialize)]
 pub struct Request {
@@ -140,17 +148,266 @@ fn handle_api_stream(mut stream: unix::net::UnixStream) -> Result<(), Error> {
         stream.read_exact(&mut buf)?;
         let req: Request = unpack(&buf)?;
         req.check()?;
+        if req.method == "context.watch" {
+            // the connection is switched to the streaming mode until closed by the client
+            return handle_watch(&mut stream, req.params);
+        }
         let response: Response = handle_api_call(&req.method, req.params).into();
-        let packed = pack(&response)?;
-        let mut buf = Vec::with_capacity(packed.len() + 5);
-        buf.push(0u8);
-        buf.extend(u32::try_from(packed.len())?.to_le_bytes());
-        buf.extend(packed);
-        stream.write_all(&buf)?;
+        write_response(&mut stream, &response)?;
     }
     Ok(())
 }
 
+fn write_response(stream: &mut unix::net::UnixStream, response: &Response) -> Result<(), Error> {
+    let packed = pack(response)?;
+    let mut buf = Vec::with_capacity(packed.len() + 5);
+    buf.push(0u8);
+    buf.extend(u32::try_from(packed.len())?.to_le_bytes());
+    buf.extend(packed);
+    stream.write_all(&buf)?;
+    Ok(())
+}
+
+struct WatcherSlot {}
+
+impl WatcherSlot {
+    fn acquire() -> EResult<Self> {
+        let mut watchers = WATCHERS.lock();
+        if *watchers >= MAX_WATCHERS {
+            return Err(Error::busy("too many watchers"));
+        }
+        *watchers += 1;
+        Ok(Self {})
+    }
+}
+
+impl Drop for WatcherSlot {
+    fn drop(&mut self) {
+        *WATCHERS.lock() -= 1;
+    }
+}
+
+fn prepare_watch(params: Option<Value>) -> EResult<(Vec<String>, Duration, WatcherSlot)> {
+    let p = WatchParams::deserialize(params.ok_or_else(|| Error::invalid_params("no params"))?)?;
+    let interval = Duration::from_nanos(crate::interval::parse_interval(&p.interval)?);
+    if interval < MIN_WATCH_INTERVAL {
+        return Err(Error::invalid_params(format!(
+            "watch interval can not be less than {:?}",
+            MIN_WATCH_INTERVAL
+        )));
+    }
+    if p.paths.is_empty() {
+        return Err(Error::invalid_params("no paths specified"));
+    }
+    let paths = expand_paths(p.paths)?;
+    crate::context::get_many(paths.iter().map(String::as_str))?;
+    Ok((paths, interval, WatcherSlot::acquire()?))
+}
+
+/// Expands field prefixes to the listed field paths, other paths are kept as-is
+fn expand_paths(prefixes: Vec<String>) -> EResult<Vec<String>> {
+    let mut paths = Vec::new();
+    for path in prefixes {
+        let listed = crate::context::list(Some(&path))?;
+        if listed.is_empty() {
+            // not a field prefix, checked by get_many
+            paths.push(path);
+        } else {
+            paths.extend(listed);
+        }
+    }
+    Ok(paths)
+}
+
+/// Gets values and units of the context fields, all fields if no paths are specified
+fn get_many(paths: Vec<String>) -> EResult<Vec<ContextValue>> {
+    let paths = if paths.is_empty() {
+        crate::context::list(None)?
+    } else {
+        expand_paths(paths)?
+    };
+    let values = crate::context::get_many(paths.iter().map(String::as_str))?;
+    Ok(paths
+        .into_iter()
+        .zip(values)
+        .map(|(path, value)| {
+            let unit = crate::context::meta(&path)
+                .and_then(|m| m.unit)
+                .map(ToOwned::to_owned);
+            ContextValue { path, value, unit }
+        })
+        .collect())
+}
+
+/// Streams context field changes. The first frame contains all watched fields, the following
+/// ones changed fields only. Empty frames are sent as heartbeats
+fn handle_watch(stream: &mut unix::net::UnixStream, params: Option<Value>) -> Result<(), Error> {
+    let (paths, interval, _slot) = match prepare_watch(params) {
+        Ok(v) => v,
+        Err(e) => {
+            write_response(stream, &Response::err(e))?;
+            return Ok(());
+        }
+    };
+    debug!("API watch started: {}", paths.join(", "));
+    let mut last: Vec<Option<Value>> = vec![None; paths.len()];
+    let mut next = Instant::now();
+    let mut last_sent: Option<Instant> = None;
+    loop {
+        let values = match crate::context::get_many(paths.iter().map(String::as_str)) {
+            Ok(v) => v,
+            Err(e) => {
+                write_response(stream, &Response::err(e))?;
+                break;
+            }
+        };
+        let mut changes = BTreeMap::new();
+        for ((path, value), prev) in paths.iter().zip(values).zip(last.iter_mut()) {
+            if prev.as_ref() != Some(&value) {
+                changes.insert(path.clone(), value.clone());
+                prev.replace(value);
+            }
+        }
+        let heartbeat = match last_sent {
+            Some(t) => t.elapsed() >= WATCH_HEARTBEAT,
+            None => true,
+        };
+        if !changes.is_empty() || heartbeat {
+            let frame = WatchFrame {
+                t: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs_f64(),
+                changes,
+            };
+            if write_response(stream, &Response::result(to_value(frame)?)).is_err() {
+                // the client has disconnected
+                break;
+            }
+            last_sent.replace(Instant::now());
+        }
+        next += interval;
+        let now = Instant::now();
+        if next > now {
+            std::thread::sleep(next - now);
+        } else {
+            next = now;
+        }
+    }
+    debug!("API watch finished");
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskParams {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskIntervalParams {
+    pub name: String,
+    /// interval string, e.g. "100ms"
+    pub interval: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContextPathParams {
+    pub path: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ContextPathsParams {
+    /// field paths or prefixes, all fields if empty
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ContextValue {
+    pub path: String,
+    pub value: Value,
+    pub unit: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContextSetParams {
+    pub path: String,
+    pub value: Value,
+}
+
+#[inline]
+fn default_watch_interval() -> String {
+    "500ms".to_owned()
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchParams {
+    /// context field paths or prefixes
+    pub paths: Vec<String>,
+    /// sampling interval string, e.g. "100ms"
+    #[serde(default = "default_watch_interval")]
+    pub interval: String,
+}
+
+/// Context watch stream frame
+#[derive(Serialize, Deserialize)]
+pub struct WatchFrame {
+    /// sampling timestamp
+    pub t: f64,
+    /// changed fields
+    pub changes: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ForceSetParams {
+    pub path: String,
+    pub value: Value,
+    #[serde(default)]
+    pub persistent: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ForceClearParams {
+    /// if not set, all forces are cleared
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AlarmAckParams {
+    /// if not set, all active alarms are acknowledged
+    #[serde(default)]
+    pub id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrendGetParams {
+    pub id: String,
+    /// return samples after the timestamp only
+    #[serde(default)]
+    pub since: Option<f64>,
+    /// return the last N samples only
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ContextListParams {
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
 fn handle_api_call(method: &str, params: Option<Value>) -> Result<Value, Error> {
     macro_rules! ok {
         () => {
@@ -178,11 +435,25 @@ fn handle_api_call(method: &str, params: Option<Value>) -> Result<Value, Error>
             }
         }
         "thread_stats.get" => {
+            if params.is_none() {
+                to_value(tasks::thread_infos()).map_err(Into::into)
+            } else {
+                invalid_params!()
+            }
+        }
+        "io.status" => {
+            if params.is_none() {
+                to_value(crate::io::status_info()).map_err(Into::into)
+            } else {
+                invalid_params!()
+            }
+        }
+        "thread_stats.histogram" => {
             if params.is_none() {
                 let mut result = BTreeMap::new();
                 let thread_stats = &tasks::controller_stats().lock().thread_stats;
                 for (name, st) in thread_stats {
-                    result.insert(name, st.info());
+                    result.insert(name, st.histogram());
                 }
                 to_value(result).map_err(Into::into)
             } else {
@@ -197,6 +468,130 @@ fn handle_api_call(method: &str, params: Option<Value>) -> Result<Value, Error>
                 invalid_params!()
             }
         }
+        "task.pause" => {
+            let p =
+                TaskParams::deserialize(params.ok_or_else(|| Error::invalid_params("no params"))?)?;
+            tasks::pause_task(&p.name)?;
+            ok!()
+        }
+        "task.resume" => {
+            let p =
+                TaskParams::deserialize(params.ok_or_else(|| Error::invalid_params("no params"))?)?;
+            tasks::resume_task(&p.name)?;
+            ok!()
+        }
+        "task.set_interval" => {
+            let p = TaskIntervalParams::deserialize(
+                params.ok_or_else(|| Error::invalid_params("no params"))?,
+            )?;
+            let interval = crate::interval::parse_interval(&p.interval)?;
+            tasks::set_task_interval(&p.name, Duration::from_nanos(interval))?;
+            ok!()
+        }
+        "context.get" => {
+            let p = ContextPathParams::deserialize(
+                params.ok_or_else(|| Error::invalid_params("no params"))?,
+            )?;
+            crate::context::get(&p.path)
+        }
+        "context.get_many" => {
+            let p = if let Some(v) = params {
+                ContextPathsParams::deserialize(v)?
+            } else {
+                ContextPathsParams::default()
+            };
+            to_value(get_many(p.paths)?).map_err(Into::into)
+        }
+        "context.set" => {
+            let p = ContextSetParams::deserialize(
+                params.ok_or_else(|| Error::invalid_params("no params"))?,
+            )?;
+            crate::context::set(&p.path, p.value)?;
+            ok!()
+        }
+        "context.list" => {
+            let p = if let Some(v) = params {
+                ContextListParams::deserialize(v)?
+            } else {
+                ContextListParams::default()
+            };
+            to_value(crate::context::list(p.prefix.as_deref())?).map_err(Into::into)
+        }
+        "context.meta" => {
+            let p = if let Some(v) = params {
+                ContextListParams::deserialize(v)?
+            } else {
+                ContextListParams::default()
+            };
+            to_value(crate::context::list_meta(p.prefix.as_deref())).map_err(Into::into)
+        }
+        "force.set" => {
+            let p = ForceSetParams::deserialize(
+                params.ok_or_else(|| Error::invalid_params("no params"))?,
+            )?;
+            crate::force::set(&p.path, p.value, p.persistent)?;
+            ok!()
+        }
+        "force.clear" => {
+            let p = if let Some(v) = params {
+                ForceClearParams::deserialize(v)?
+            } else {
+                ForceClearParams::default()
+            };
+            if let Some(path) = p.path {
+                crate::force::clear(&path)?;
+            } else {
+                crate::force::clear_all()?;
+            }
+            ok!()
+        }
+        "force.list" => {
+            if params.is_none() {
+                to_value(crate::force::list()).map_err(Into::into)
+            } else {
+                invalid_params!()
+            }
+        }
+        "trend.list" => {
+            if params.is_none() {
+                to_value(crate::trend::list()).map_err(Into::into)
+            } else {
+                invalid_params!()
+            }
+        }
+        "trend.get" => {
+            let p = TrendGetParams::deserialize(
+                params.ok_or_else(|| Error::invalid_params("no params"))?,
+            )?;
+            to_value(crate::trend::get(&p.id, p.since, p.limit)?).map_err(Into::into)
+        }
+        "alarm.list" => {
+            if params.is_none() {
+                to_value(crate::alarm::list()).map_err(Into::into)
+            } else {
+                invalid_params!()
+            }
+        }
+        "alarm.ack" => {
+            let p = if let Some(v) = params {
+                AlarmAckParams::deserialize(v)?
+            } else {
+                AlarmAckParams::default()
+            };
+            if let Some(id) = p.id {
+                crate::alarm::ack(&id)?;
+            } else {
+                crate::alarm::ack_all();
+            }
+            ok!()
+        }
+        "sfc.list" => {
+            if params.is_none() {
+                to_value(crate::sfc::list()).map_err(Into::into)
+            } else {
+                invalid_params!()
+            }
+        }
         v => Err(Error::not_implemented(v)),
     }
 }
@@ -3,6 +3,7 @@ use eva_common::value::Value;
 use indexmap::IndexMap;
 use inflector::Inflector;
 use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::fmt::Write as _;
 use std::fs;
@@ -23,8 +24,22 @@ pub struct Config {
     io: Vec<Io>,
     #[serde(default)]
     server: Vec<ServerConfig>,
+    #[serde(default)]
+    pub(crate) chains: Vec<ChainConfig>,
+    #[serde(default)]
+    pub(crate) trends: Vec<TrendConfig>,
+    #[serde(default)]
+    pub(crate) alarms: AlarmsConfig,
+    #[serde(default)]
+    pub(crate) sfc: Vec<SfcConfig>,
+    #[serde(default)]
+    types: IndexMap<String, TypeConfig>,
 }
 
+const IO_STATUS_FIELD: &str = "io_status";
+const QUALITY_FIELD: &str = "quality";
+const SFC_FIELD: &str = "sfc";
+
 fn default_stop_timeout() -> f64 {
     crate::DEFAULT_STOP_TIMEOUT
 }
@@ -36,6 +51,29 @@ pub(crate) struct CoreConfig {
     pub(crate) stop_timeout: f64,
     #[serde(default)]
     pub(crate) stack_size: Option<usize>,
+    #[serde(default)]
+    pub(crate) watchdog: Option<WatchdogConfig>,
+    #[serde(default)]
+    pub(crate) mlockall: bool,
+    #[serde(default)]
+    pub(crate) prefault_stack: Option<usize>,
+    #[serde(default)]
+    pub(crate) tasks: BTreeMap<String, TaskRtConfig>,
+    #[serde(default)]
+    pub(crate) panic: PanicConfig,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PanicConfig {
+    #[serde(default)]
+    pub(crate) input: crate::tasks::PanicAction,
+    #[serde(default)]
+    pub(crate) program: crate::tasks::PanicAction,
+    #[serde(default)]
+    pub(crate) output: crate::tasks::PanicAction,
+    #[serde(default)]
+    pub(crate) chain: crate::tasks::PanicAction,
 }
 
 impl Default for CoreConfig {
@@ -43,10 +81,225 @@ impl Default for CoreConfig {
         Self {
             stop_timeout: default_stop_timeout(),
             stack_size: None,
+            watchdog: None,
+            mlockall: false,
+            prefault_stack: None,
+            tasks: <_>::default(),
+            panic: <_>::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TaskRtConfig {
+    #[serde(default)]
+    pub(crate) cpu: Option<usize>,
+    #[serde(default)]
+    pub(crate) policy: Option<crate::tasks::SchedPolicy>,
+    #[serde(default)]
+    pub(crate) priority: Option<i32>,
+    #[serde(default)]
+    pub(crate) nice: Option<i32>,
+}
+
+#[inline]
+fn default_watchdog_missed() -> u32 {
+    3
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct WatchdogConfig {
+    #[serde(default = "default_watchdog_missed")]
+    pub(crate) missed: u32,
+    #[serde(default)]
+    pub(crate) action: crate::tasks::WatchdogAction,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ChainConfig {
+    pub(crate) id: String,
+    #[serde(deserialize_with = "crate::interval::deserialize_interval_as_nanos")]
+    pub(crate) interval: u64,
+    #[serde(
+        default,
+        deserialize_with = "crate::interval::deserialize_opt_interval_as_nanos"
+    )]
+    pub(crate) shift: Option<u64>,
+    pub(crate) steps: Vec<ChainStep>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(try_from = "ChainStepMap")]
+pub(crate) enum ChainStep {
+    Input(String),
+    Program(String),
+    Output(String),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChainStepMap {
+    input: Option<String>,
+    program: Option<String>,
+    output: Option<String>,
+}
+
+impl TryFrom<ChainStepMap> for ChainStep {
+    type Error = eva_common::Error;
+    fn try_from(m: ChainStepMap) -> Result<Self, Self::Error> {
+        match (m.input, m.program, m.output) {
+            (Some(v), None, None) => Ok(ChainStep::Input(v)),
+            (None, Some(v), None) => Ok(ChainStep::Program(v)),
+            (None, None, Some(v)) => Ok(ChainStep::Output(v)),
+            _ => Err(eva_common::Error::invalid_params(
+                "chain step must have exactly one of input, program or output",
+            )),
+        }
+    }
+}
+
+impl ChainStep {
+    pub(crate) fn kind(&self) -> crate::tasks::Kind {
+        match self {
+            ChainStep::Input(_) => crate::tasks::Kind::Input,
+            ChainStep::Program(_) => crate::tasks::Kind::Program,
+            ChainStep::Output(_) => crate::tasks::Kind::Output,
+        }
+    }
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            ChainStep::Input(v) | ChainStep::Program(v) | ChainStep::Output(v) => v,
+        }
+    }
+}
+
+#[inline]
+fn default_trend_size() -> usize {
+    1000
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TrendConfig {
+    pub(crate) id: String,
+    #[serde(deserialize_with = "crate::interval::deserialize_interval_as_nanos")]
+    pub(crate) interval: u64,
+    pub(crate) paths: Vec<String>,
+    #[serde(default = "default_trend_size")]
+    pub(crate) size: usize,
+    #[serde(default)]
+    pub(crate) csv: Option<TrendCsvConfig>,
+}
+
+#[inline]
+fn default_trend_csv_max_size() -> u64 {
+    10_000_000
+}
+
+#[inline]
+fn default_trend_csv_keep() -> usize {
+    5
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TrendCsvConfig {
+    #[serde(default = "default_trend_csv_max_size")]
+    pub(crate) max_size: u64,
+    #[serde(default = "default_trend_csv_keep")]
+    pub(crate) keep: usize,
+}
+
+#[inline]
+fn default_alarm_interval() -> u64 {
+    1_000_000_000
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct AlarmsConfig {
+    #[serde(
+        default = "default_alarm_interval",
+        deserialize_with = "crate::interval::deserialize_interval_as_nanos"
+    )]
+    pub(crate) interval: u64,
+    #[serde(default)]
+    pub(crate) list: Vec<AlarmConfig>,
+}
+
+impl Default for AlarmsConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_alarm_interval(),
+            list: <_>::default(),
         }
     }
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct AlarmConfig {
+    pub(crate) id: String,
+    pub(crate) condition: String,
+    #[serde(
+        default,
+        deserialize_with = "crate::interval::deserialize_opt_interval_as_nanos"
+    )]
+    pub(crate) delay: Option<u64>,
+    #[serde(default)]
+    pub(crate) severity: crate::alarm::Severity,
+    #[serde(default)]
+    pub(crate) latch: bool,
+    #[serde(default)]
+    pub(crate) description: String,
+    #[serde(default)]
+    pub(crate) oid: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SfcConfig {
+    pub(crate) id: String,
+    #[serde(deserialize_with = "crate::interval::deserialize_interval_as_nanos")]
+    pub(crate) interval: u64,
+    #[serde(
+        default,
+        deserialize_with = "crate::interval::deserialize_opt_interval_as_nanos"
+    )]
+    pub(crate) shift: Option<u64>,
+    #[serde(default)]
+    pub(crate) retain: bool,
+    pub(crate) steps: Vec<SfcStepConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SfcStepConfig {
+    pub(crate) name: String,
+    /// executed on the first scan of the step
+    #[serde(default)]
+    pub(crate) entry: Option<String>,
+    /// executed on each scan of the step
+    #[serde(default)]
+    pub(crate) action: Option<String>,
+    /// executed when a transition from the step fires
+    #[serde(default)]
+    pub(crate) exit: Option<String>,
+    #[serde(default)]
+    pub(crate) transitions: Vec<SfcTransitionConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SfcTransitionConfig {
+    pub(crate) to: String,
+    /// Rust expression, "ctx" (the context) and "t" (the step time in seconds) are available
+    pub(crate) condition: String,
+}
+
 #[cfg(feature = "eva")]
 #[inline]
 fn default_eapi_action_pool_size() -> usize {
@@ -86,9 +339,34 @@ struct ContextConfig {
     #[serde(default)]
     modbus: Option<ModbusConfig>,
     #[serde(default)]
+    retain: RetainConfig,
+    #[serde(default)]
     fields: IndexMap<String, ContextField>,
 }
 
+#[inline]
+fn default_retain_interval() -> Option<u64> {
+    Some(10_000_000_000)
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RetainConfig {
+    #[serde(
+        default = "default_retain_interval",
+        deserialize_with = "crate::interval::deserialize_opt_interval_as_nanos"
+    )]
+    pub(crate) interval: Option<u64>,
+}
+
+impl Default for RetainConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_retain_interval(),
+        }
+    }
+}
+
 #[cfg(feature = "modbus")]
 #[derive(Deserialize, Default, Debug)]
 #[serde(deny_unknown_fields)]
@@ -126,13 +404,247 @@ struct Io {
     output: Vec<Value>,
 }
 
-#[derive(Deserialize, Debug)]
+impl Io {
+    /// The normalized id, used in generated code: ctx.io_status fields, functions and tasks
+    fn name(&self) -> String {
+        self.id.to_lowercase()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
 #[serde(untagged)]
 enum ContextField {
+    Field(FieldConfig),
     Map(IndexMap<String, ContextField>),
     Type(String),
 }
 
+/// Expanded field definition with an initial value and metadata
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct FieldConfig {
+    #[serde(rename = "type")]
+    tp: String,
+    #[serde(default)]
+    init: Option<Value>,
+    #[serde(default)]
+    min: Option<f64>,
+    #[serde(default)]
+    max: Option<f64>,
+    #[serde(default)]
+    unit: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+impl FieldConfig {
+    fn has_meta(&self) -> bool {
+        self.min.is_some()
+            || self.max.is_some()
+            || self.unit.is_some()
+            || self.description.is_some()
+    }
+}
+
+/// Context structure as seen by the builder, used to validate I/O mappings
+#[cfg_attr(
+    not(any(feature = "modbus", feature = "opcua", feature = "eva")),
+    allow(dead_code)
+)]
+pub(crate) enum FieldSchema {
+    Struct(IndexMap<String, FieldSchema>),
+    Array(usize, Box<FieldSchema>),
+    /// a built-in or an IEC type
+    Value(String),
+    /// a user-defined enumeration and its raw type
+    Enum(String, String),
+    /// a type which is not known by the builder, its fields are not checked
+    Custom(String),
+}
+
+#[cfg_attr(
+    not(any(feature = "modbus", feature = "opcua", feature = "eva")),
+    allow(dead_code)
+)]
+impl FieldSchema {
+    fn from_fields<'a>(
+        fields: &'a IndexMap<String, ContextField>,
+        types: &'a IndexMap<String, TypeConfig>,
+        parents: &mut Vec<&'a str>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut result = IndexMap::new();
+        for (k, v) in fields {
+            match v {
+                ContextField::Type(t) | ContextField::Field(FieldConfig { tp: t, .. }) => {
+                    result.insert(k.clone(), Self::from_type(t, types, parents)?);
+                }
+                ContextField::Map(m) => {
+                    let (name, array) = parse_struct_name(k)?;
+                    let sub = Self::from_fields(m, types, parents)?;
+                    result.insert(
+                        name.to_owned(),
+                        if let Some(a) = array {
+                            Self::Array(a.size, Box::new(sub))
+                        } else {
+                            sub
+                        },
+                    );
+                }
+            }
+        }
+        Ok(Self::Struct(result))
+    }
+    fn from_type<'a>(
+        t: &str,
+        types: &'a IndexMap<String, TypeConfig>,
+        parents: &mut Vec<&'a str>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let t = parse_field_type(t).0;
+        let (base_tp, dims) = parse_base_type(t);
+        let mut result = match types.get_key_value(base_tp.as_str()) {
+            Some((
+                name,
+                TypeConfig {
+                    fields: Some(sub), ..
+                },
+            )) => {
+                if parents.contains(&name.as_str()) {
+                    return Err(eva_common::Error::invalid_params(format!(
+                        "type {} contains itself",
+                        name
+                    ))
+                    .into());
+                }
+                parents.push(name);
+                let result = Self::from_fields(sub, types, parents)?;
+                parents.pop();
+                result
+            }
+            Some((name, tp)) => {
+                if let Some((raw, _, _)) = enum_raw_type(tp.base.as_deref().unwrap_or("INT")) {
+                    Self::Enum(name.clone(), raw.to_owned())
+                } else {
+                    Self::Custom(name.clone())
+                }
+            }
+            None => {
+                if let Some(fields) = crate::fb::default_fields(&base_tp) {
+                    Self::Struct(
+                        fields
+                            .into_iter()
+                            .map(|(name, value)| {
+                                let node = match value {
+                                    Value::Bool(_) => Self::Value("bool".to_owned()),
+                                    Value::U16(_) => Self::Value("u16".to_owned()),
+                                    Value::I32(_) => Self::Value("i32".to_owned()),
+                                    Value::F32(_) => Self::Value("f32".to_owned()),
+                                    // durations
+                                    _ => Self::Custom("::std::time::Duration".to_owned()),
+                                };
+                                (name, node)
+                            })
+                            .collect(),
+                    )
+                } else if base_val(&base_tp).is_some() || base_tp.starts_with("::rplc::") {
+                    Self::Value(base_tp)
+                } else {
+                    Self::Custom(base_tp)
+                }
+            }
+        };
+        for a in parse_dims(t, dims)? {
+            result = Self::Array(a.size, Box::new(result));
+        }
+        Ok(result)
+    }
+    /// Resolves a context path, returns None if the path points inside a custom type
+    pub(crate) fn resolve(&self, path: &str) -> Result<Option<&Self>, String> {
+        let mut node = self;
+        let mut path = path.trim();
+        while !path.is_empty() {
+            let (name, idx, rest) = crate::context::split_path(path)
+                .map_err(|e| e.message().unwrap_or_default().to_owned())?;
+            let name = name.trim();
+            node = match node {
+                Self::Struct(fields) => fields
+                    .get(name)
+                    .ok_or_else(|| format!("no such context field: {}", name))?,
+                Self::Custom(_) => return Ok(None),
+                // rplc types (function blocks, SFC states etc.) implement PathAccess
+                Self::Value(t) if t.starts_with("::rplc::") => return Ok(None),
+                _ => return Err(format!("{} is not a structure", node.type_name())),
+            };
+            for i in idx {
+                node = match node {
+                    Self::Array(size, sub) => {
+                        if i >= *size {
+                            return Err(format!(
+                                "{}: index {} is out of range (size {})",
+                                name, i, size
+                            ));
+                        }
+                        sub
+                    }
+                    Self::Custom(_) => return Ok(None),
+                    _ => return Err(format!("{} is not an array", name)),
+                };
+            }
+            path = rest;
+        }
+        Ok(Some(node))
+    }
+    /// Resolves a context path of an I/O mapping
+    pub(crate) fn resolve_mapping(
+        &self,
+        location: &str,
+        path: &str,
+    ) -> Result<Option<&Self>, Box<dyn Error>> {
+        if path.trim().is_empty() {
+            return Err(mapping_error(location, path, "context field path is empty"));
+        }
+        self.resolve(path)
+            .map_err(|e| mapping_error(location, path, e))
+    }
+    /// Resolves a context path which must point to a number, a boolean or an enumeration
+    pub(crate) fn resolve_number(&self, path: &str) -> Result<(), String> {
+        match self.resolve(path)? {
+            None | Some(Self::Enum(..)) => Ok(()),
+            Some(Self::Value(t)) if base_val(t).is_some() => Ok(()),
+            Some(node) => Err(format!("{}: {} is not a number", path, node.type_name())),
+        }
+    }
+    pub(crate) fn type_name(&self) -> String {
+        match self {
+            Self::Struct(_) => "structure".to_owned(),
+            Self::Array(size, sub) => format!("[{}; {}]", sub.type_name(), size),
+            Self::Value(t) | Self::Enum(t, _) | Self::Custom(t) => t.clone(),
+        }
+    }
+}
+
+/// Creates an error of an I/O mapping, the location is the mapping path in plc.yml
+pub(crate) fn mapping_error(
+    location: &str,
+    path: &str,
+    e: impl std::fmt::Display,
+) -> Box<dyn Error> {
+    eva_common::Error::invalid_params(format!("plc.yml, {} \"{}\": {}", location, path.trim(), e))
+        .into()
+}
+
+/// User-defined type, either a structure or an enumeration
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct TypeConfig {
+    #[serde(default, rename = "struct")]
+    fields: Option<IndexMap<String, ContextField>>,
+    #[serde(default, rename = "enum")]
+    values: Option<IndexMap<String, i64>>,
+    /// enumeration base type, INT if not specified
+    #[serde(default, rename = "type")]
+    base: Option<String>,
+}
+
 impl Config {
     pub fn load<P: AsRef<Path>>(path: P, context: &tera::Context) -> Result<Self, Box<dyn Error>> {
         let config_tpl = fs::read_to_string(path)?;
@@ -143,8 +655,67 @@ impl Config {
         }
         Ok(config)
     }
+    /// Names of the I/O tasks of the given kind, as they are spawned by the generated code
+    pub(crate) fn io_tasks(&self, kind: crate::tasks::Kind) -> Vec<String> {
+        let mut result = Vec::new();
+        for i in &self.io {
+            let count = match kind {
+                crate::tasks::Kind::Input => match i.kind {
+                    // EVA ICS inputs are action handlers, not tasks
+                    #[cfg(feature = "eva")]
+                    Kind::Eapi => 0,
+                    #[allow(unreachable_patterns)]
+                    _ => i.input.len(),
+                },
+                crate::tasks::Kind::Output => i.output.len(),
+                _ => 0,
+            };
+            result.extend((1..=count).map(|n| format!("{}_{}", i.name(), n)));
+        }
+        result
+    }
+    /// Resolves the mapped context paths and checks the mappings of all I/O
+    #[allow(unused_variables)]
+    fn validate_io(&self) -> Result<(), Box<dyn Error>> {
+        let mut names = BTreeSet::new();
+        for i in &self.io {
+            if !is_valid_identifier(&i.id) {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "invalid io id: {} (must be a valid identifier)",
+                    i.id
+                ))
+                .into());
+            }
+            if !names.insert(i.name()) {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "duplicate io: {} (ids are case-insensitive)",
+                    i.id
+                ))
+                .into());
+            }
+        }
+        let schema = FieldSchema::from_fields(&self.context.fields, &self.types, &mut Vec::new())?;
+        for i in &self.io {
+            match i.kind {
+                #[cfg(feature = "modbus")]
+                Kind::Modbus => {
+                    crate::io::modbus::validate(&i.id, &i.input, &i.output, &schema)?;
+                }
+                #[cfg(feature = "opcua")]
+                Kind::OpcUa => {
+                    crate::io::opcua::validate(&i.id, &i.input, &i.output, &schema)?;
+                }
+                #[cfg(feature = "eva")]
+                Kind::Eapi => {
+                    crate::io::eapi::validate(&i.id, &i.input, &i.output, &schema)?;
+                }
+            }
+        }
+        Ok(())
+    }
     #[allow(unreachable_code)]
     pub fn generate_io<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        self.validate_io()?;
         let mut m = codegen::Scope::new();
         m.raw(crate::builder::AUTO_GENERATED);
         m.raw("#[allow(unused_imports)]");
@@ -157,26 +728,26 @@ impl Config {
                 #[cfg(feature = "modbus")]
                 Kind::Modbus => {
                     m.raw(
-                        crate::io::modbus::generate_io(&i.id, &i.config, &i.input, &i.output)?
+                        crate::io::modbus::generate_io(&i.name(), &i.config, &i.input, &i.output)?
                             .to_string(),
                     );
                 }
                 #[cfg(feature = "opcua")]
                 Kind::OpcUa => {
                     m.raw(
-                        crate::io::opcua::generate_io(&i.id, &i.config, &i.input, &i.output)?
+                        crate::io::opcua::generate_io(&i.name(), &i.config, &i.input, &i.output)?
                             .to_string(),
                     );
                 }
                 #[cfg(feature = "eva")]
                 Kind::Eapi => {
                     m.raw(
-                        crate::io::eapi::generate_io(&i.id, &i.config, &i.input, &i.output)?
+                        crate::io::eapi::generate_io(&i.name(), &i.config, &i.input, &i.output)?
                             .to_string(),
                     );
                 }
             }
-            funcs.push(format!("launch_datasync_{}", i.id.to_lowercase()));
+            funcs.push(format!("launch_datasync_{}", i.name()));
         }
         let f_launch_datasync = m.new_fn("launch_datasync").vis("pub");
         for function in funcs {
@@ -203,39 +774,906 @@ impl Config {
         super::write(path, m.to_string())?;
         Ok(())
     }
+    #[allow(clippy::unnecessary_wraps)]
+    fn quality_fields(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        #[allow(unused_mut)]
+        let mut result = Vec::new();
+        for i in &self.io {
+            match i.kind {
+                #[cfg(feature = "modbus")]
+                Kind::Modbus => result.extend(crate::io::modbus::quality_fields(&i.input)?),
+                #[cfg(feature = "opcua")]
+                Kind::OpcUa => result.extend(crate::io::opcua::quality_fields(&i.input)?),
+                #[allow(unreachable_patterns)]
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+    /// Returns context paths of the fields marked as RETAIN
+    pub(crate) fn retain_fields(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut result = Vec::new();
+        collect_retain("", &self.context.fields, &mut result)?;
+        for chart in &self.sfc {
+            if chart.retain {
+                result.push(format!("{}.{}", SFC_FIELD, chart.id));
+            }
+        }
+        Ok(result)
+    }
+    pub(crate) fn retain_interval(&self) -> Option<u64> {
+        self.context.retain.interval
+    }
+    /// Context fields, including the reserved ones, generated by the builder
+    fn context_fields(&self) -> Result<IndexMap<String, ContextField>, Box<dyn Error>> {
+        let mut fields = self.context.fields.clone();
+        if !self.io.is_empty() {
+            if fields.contains_key(IO_STATUS_FIELD) {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "context field name {} is reserved",
+                    IO_STATUS_FIELD
+                ))
+                .into());
+            }
+            let io_status = self
+                .io
+                .iter()
+                .map(|i| {
+                    (
+                        i.name(),
+                        ContextField::Type("::rplc::io::IoStatus".to_owned()),
+                    )
+                })
+                .collect();
+            fields.insert(IO_STATUS_FIELD.to_owned(), ContextField::Map(io_status));
+        }
+        let quality = self.quality_fields()?;
+        if !quality.is_empty() {
+            if fields.contains_key(QUALITY_FIELD) {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "context field name {} is reserved",
+                    QUALITY_FIELD
+                ))
+                .into());
+            }
+            let mut quality_map = IndexMap::new();
+            for field in quality {
+                if quality_map
+                    .insert(
+                        field.clone(),
+                        ContextField::Type("::rplc::io::Quality".to_owned()),
+                    )
+                    .is_some()
+                {
+                    return Err(eva_common::Error::invalid_params(format!(
+                        "duplicate quality field ctx.{}.{}",
+                        QUALITY_FIELD, field
+                    ))
+                    .into());
+                }
+            }
+            fields.insert(QUALITY_FIELD.to_owned(), ContextField::Map(quality_map));
+        }
+        if !self.sfc.is_empty() {
+            if fields.contains_key(SFC_FIELD) {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "context field name {} is reserved",
+                    SFC_FIELD
+                ))
+                .into());
+            }
+            let sfc_map = self
+                .sfc
+                .iter()
+                .map(|chart| {
+                    (
+                        chart.id.clone(),
+                        ContextField::Type("::rplc::sfc::SfcState".to_owned()),
+                    )
+                })
+                .collect();
+            fields.insert(SFC_FIELD.to_owned(), ContextField::Map(sfc_map));
+        }
+        Ok(fields)
+    }
+    /// Schema of the generated context
+    pub(crate) fn context_schema(&self) -> Result<FieldSchema, Box<dyn Error>> {
+        FieldSchema::from_fields(&self.context_fields()?, &self.types, &mut Vec::new())
+    }
     pub fn generate_context<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
-        let mut b = path.as_ref().to_path_buf();
-        b.pop();
-        b.pop();
-        let mut bm = b.clone();
-        b.push("plc_types.rs");
-        bm.push("plc_types");
-        bm.push("mod.rs");
         let mut m = codegen::Scope::new();
         m.raw(crate::builder::AUTO_GENERATED);
-        if b.exists() || bm.exists() {
-            m.raw("#[allow(clippy::wildcard_imports)]");
-            m.raw("use crate::plc_types::*;");
+        import_plc_types(path.as_ref(), &mut m);
+        if !self.types.is_empty() {
+            m.raw(format!(
+                "#[allow(unused_imports)] use super::types::{{{}}};",
+                self.types
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ));
         }
         m.import("::rplc::export::parking_lot", "RwLock");
         m.import("::rplc::export::once_cell::sync", "Lazy");
-        if self.context.serialize {
-            m.import("::rplc::export::serde", "Serialize");
-            m.import("::rplc::export::serde", "Deserialize");
-            m.import("::rplc::export::serde", "self");
-        }
         m.raw("#[allow(dead_code)] pub(crate) static CONTEXT: Lazy<RwLock<Context>> = Lazy::new(<_>::default);");
+        let fields = self.context_fields()?;
+        let retain = self.retain_fields()?;
+        if !retain.is_empty() {
+            generate_retain(&retain, &mut m);
+        }
         generate_structs(
             "Context",
-            &self.context.fields,
+            &fields,
             &mut m,
             #[cfg(feature = "modbus")]
             self.context.modbus.as_ref(),
             self.context.serialize,
+            &self.types,
         )?;
+        let mut meta = Vec::new();
+        collect_meta(
+            "",
+            &self.context.fields,
+            &self.types,
+            &mut Vec::new(),
+            &mut meta,
+        )?;
+        generate_meta(&meta, &mut m)?;
         super::write(path, m.to_string())?;
         Ok(())
     }
+    /// Validates and generates user-defined types
+    pub fn generate_types<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let mut m = codegen::Scope::new();
+        m.raw(crate::builder::AUTO_GENERATED);
+        import_plc_types(path.as_ref(), &mut m);
+        for (name, tp) in &self.types {
+            if !is_valid_type_name(name) {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "invalid type name: {} (must be a valid identifier, not an IEC type name, not starting with Context)",
+                    name
+                ))
+                .into());
+            }
+            match (&tp.fields, &tp.values) {
+                (Some(fields), None) if tp.base.is_none() => {
+                    let mut retain = Vec::new();
+                    collect_retain("", fields, &mut retain)?;
+                    if !retain.is_empty() {
+                        return Err(eva_common::Error::invalid_params(format!(
+                            "type {}: RETAIN is not allowed for structure fields ({})",
+                            name,
+                            retain.join(", ")
+                        ))
+                        .into());
+                    }
+                    generate_structs(
+                        name,
+                        fields,
+                        &mut m,
+                        #[cfg(feature = "modbus")]
+                        None,
+                        // user types may be retained or nested in a serialized context
+                        true,
+                        &self.types,
+                    )?;
+                }
+                (None, Some(values)) => {
+                    generate_enum(name, values, tp.base.as_deref().unwrap_or("INT"), &mut m)?;
+                }
+                _ => {
+                    return Err(eva_common::Error::invalid_params(format!(
+                        "type {}: either struct or enum (with an optional base type) must be specified",
+                        name
+                    ))
+                    .into());
+                }
+            }
+        }
+        super::write(path, m.to_string())?;
+        Ok(())
+    }
+    /// Validates the charts and generates their programs
+    pub fn generate_sfc<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let mut m = codegen::Scope::new();
+        m.raw(crate::builder::AUTO_GENERATED);
+        m.raw("#[allow(unused_imports)]");
+        m.raw("use crate::plc::context::{Context, CONTEXT};");
+        let mut chart_ids = BTreeSet::new();
+        for chart in &self.sfc {
+            if !is_valid_program_name(&chart.id) {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "invalid sfc id: {} (must be a valid identifier, 1-14 characters)",
+                    chart.id
+                ))
+                .into());
+            }
+            if !chart_ids.insert(&chart.id) {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "duplicate sfc: {}",
+                    chart.id
+                ))
+                .into());
+            }
+            generate_chart(chart, &mut m)?;
+        }
+        super::write(path, m.to_string())?;
+        Ok(())
+    }
+}
+
+fn is_valid_program_name(name: &str) -> bool {
+    is_valid_identifier(name) && name.len() < 15
+}
+
+fn generate_chart(chart: &SfcConfig, scope: &mut codegen::Scope) -> Result<(), Box<dyn Error>> {
+    let id = &chart.id;
+    if chart.interval == 0 {
+        return Err(eva_common::Error::invalid_params(format!(
+            "sfc {}: interval can not be zero",
+            id
+        ))
+        .into());
+    }
+    if chart.steps.is_empty() || chart.steps.len() > usize::from(u16::MAX) {
+        return Err(eva_common::Error::invalid_params(format!(
+            "sfc {}: invalid number of steps",
+            id
+        ))
+        .into());
+    }
+    let mut step_ids = BTreeMap::new();
+    for (i, step) in chart.steps.iter().enumerate() {
+        if step.name.is_empty() || step.name.contains(char::is_control) {
+            return Err(eva_common::Error::invalid_params(format!(
+                "sfc {}: invalid step name {:?}",
+                id, step.name
+            ))
+            .into());
+        }
+        for f in [&step.entry, &step.action, &step.exit]
+            .into_iter()
+            .flatten()
+        {
+            if !f.split("::").all(is_valid_identifier) {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "sfc {}: step {}: invalid function name {}",
+                    id, step.name, f
+                ))
+                .into());
+            }
+        }
+        if step_ids.insert(step.name.as_str(), i).is_some() {
+            return Err(eva_common::Error::invalid_params(format!(
+                "sfc {}: duplicate step {}",
+                id, step.name
+            ))
+            .into());
+        }
+    }
+    let state = format!("ctx.{}.{}", SFC_FIELD, id);
+    let f = scope
+        .new_fn(id)
+        .vis("pub")
+        .allow("unused_variables, clippy::match_single_binding");
+    f.line("let mut ctx = CONTEXT.write();");
+    f.line(format!(
+        "let entry = {}.scan({});",
+        state,
+        chart.steps.len()
+    ));
+    f.line(format!("let t = {}.t.as_secs_f64();", state));
+    let mut m = codegen::Block::new(&format!("match {}.step", state));
+    for (i, step) in chart.steps.iter().enumerate() {
+        let mut b = codegen::Block::new(&format!("{} =>", i));
+        b.line(format!("// {}", step.name));
+        if let Some(ref entry) = step.entry {
+            b.line(format!("if entry {{ crate::{}(&mut ctx); }}", entry));
+        }
+        if let Some(ref action) = step.action {
+            b.line(format!("crate::{}(&mut ctx);", action));
+        }
+        for (n, tr) in step.transitions.iter().enumerate() {
+            let to = step_ids.get(tr.to.as_str()).ok_or_else(|| {
+                eva_common::Error::invalid_params(format!(
+                    "sfc {}: step {} transition to unknown step {}",
+                    id, step.name, tr.to
+                ))
+            })?;
+            let mut tb = codegen::Block::new(&format!(
+                "{}if {}",
+                if n == 0 { "" } else { "else " },
+                tr.condition
+            ));
+            if let Some(ref exit) = step.exit {
+                tb.line(format!("crate::{}(&mut ctx);", exit));
+            }
+            tb.line(format!("{}.go({});", state, to));
+            b.push_block(tb);
+        }
+        m.push_block(b);
+    }
+    let mut b = codegen::Block::new("_ =>");
+    b.line(format!(
+        "::rplc::export::log::error!(\"sfc {}: invalid step {{}}, reset to the initial one\", {}.step);",
+        id, state
+    ));
+    b.line(format!("{}.go(0);", state));
+    m.push_block(b);
+    f.push_block(m);
+    scope
+        .new_fn(&format!("{}_state", id))
+        .vis("pub")
+        .ret("::rplc::sfc::SfcState")
+        .line(format!("CONTEXT.read().{}.{}.clone()", SFC_FIELD, id));
+    Ok(())
+}
+
+/// Imports custom types from plc_types.rs or plc_types/mod.rs if exist
+fn import_plc_types(path: &Path, scope: &mut codegen::Scope) {
+    let mut b = path.to_path_buf();
+    b.pop();
+    b.pop();
+    let mut bm = b.clone();
+    b.push("plc_types.rs");
+    bm.push("plc_types");
+    bm.push("mod.rs");
+    if b.exists() || bm.exists() {
+        scope.raw("#[allow(unused_imports, clippy::wildcard_imports)]");
+        scope.raw("use crate::plc_types::*;");
+    }
+}
+
+fn is_valid_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn is_valid_type_name(name: &str) -> bool {
+    is_valid_identifier(name)
+        && !name.starts_with("Context")
+        && parse_iec_type(name) == name
+        && string_type(name, None).is_none()
+}
+
+fn enum_raw_type(base: &str) -> Option<(&str, i128, i128)> {
+    let tp = parse_iec_type(base);
+    let (min, max) = match tp {
+        "u8" => (0, u8::MAX.into()),
+        "u16" => (0, u16::MAX.into()),
+        "u32" => (0, u32::MAX.into()),
+        "u64" => (0, u64::MAX.into()),
+        "i8" => (i8::MIN.into(), i8::MAX.into()),
+        "i16" => (i16::MIN.into(), i16::MAX.into()),
+        "i32" => (i32::MIN.into(), i32::MAX.into()),
+        "i64" => (i64::MIN.into(), i64::MAX.into()),
+        _ => return None,
+    };
+    Some((tp, min, max))
+}
+
+#[allow(clippy::too_many_lines)]
+fn generate_enum(
+    name: &str,
+    values: &IndexMap<String, i64>,
+    base: &str,
+    scope: &mut codegen::Scope,
+) -> Result<(), Box<dyn Error>> {
+    let (raw, min, max) = enum_raw_type(base).ok_or_else(|| {
+        eva_common::Error::invalid_params(format!(
+            "type {}: invalid enum base type {} (an integer type expected)",
+            name, base
+        ))
+    })?;
+    if values.is_empty() {
+        return Err(eva_common::Error::invalid_params(format!("type {}: no values", name)).into());
+    }
+    let mut variants: Vec<(String, &str, i64)> = Vec::with_capacity(values.len());
+    for (k, &v) in values {
+        let variant = k.to_pascal_case();
+        if !is_valid_identifier(&variant) || !(min..=max).contains(&i128::from(v)) {
+            return Err(eva_common::Error::invalid_params(format!(
+                "type {}: invalid enum value {} = {}",
+                name, k, v
+            ))
+            .into());
+        }
+        if let Some((_, other, _)) = variants
+            .iter()
+            .find(|(var, _, val)| *var == variant || *val == v)
+        {
+            return Err(eva_common::Error::invalid_params(format!(
+                "type {}: enum values {} and {} conflict",
+                name, other, k
+            ))
+            .into());
+        }
+        variants.push((variant, k, v));
+    }
+    let err = "::rplc::export::eva_common::Error";
+    let value = "::rplc::export::eva_common::value::Value";
+    let mut en = codegen::Enum::new(name);
+    en.vis("pub")
+        .derive("Copy")
+        .derive("Clone")
+        .derive("Debug")
+        .derive("Eq")
+        .derive("PartialEq")
+        .derive("Hash")
+        .repr(raw);
+    for (variant, _, v) in &variants {
+        en.new_variant(format!("{} = {}", variant, v));
+    }
+    scope.push_enum(en);
+    scope.raw(format!(
+        "#[allow(clippy::derivable_impls)] impl Default for {name} {{ fn default() -> Self {{ Self::{} }} }}",
+        variants[0].0
+    ));
+    scope.raw(format!(
+        "impl From<{name}> for {raw} {{ fn from(v: {name}) -> Self {{ v as {raw} }} }}"
+    ));
+    let mut code = format!(
+        "impl TryFrom<{raw}> for {name} {{ type Error = {err}; fn try_from(v: {raw}) -> Result<Self, Self::Error> {{ match v {{"
+    );
+    for (variant, _, v) in &variants {
+        write!(code, "{v} => Ok(Self::{variant}),")?;
+    }
+    write!(
+        code,
+        "_ => Err({err}::invalid_data(format!(\"invalid {name} value: {{}}\", v))), }} }} }}"
+    )?;
+    scope.raw(code);
+    let mut code = format!(
+        "impl ::std::str::FromStr for {name} {{ type Err = {err}; fn from_str(s: &str) -> Result<Self, Self::Err> {{ match s {{"
+    );
+    for (variant, k, _) in &variants {
+        write!(code, "{k:?} => Ok(Self::{variant}),")?;
+    }
+    write!(
+        code,
+        "_ => s.parse::<{raw}>().map_err(|_| {err}::invalid_data(format!(\"invalid {name} value: {{}}\", s)))?.try_into(), }} }} }}"
+    )?;
+    scope.raw(code);
+    let mut code = format!(
+        "impl ::std::fmt::Display for {name} {{ fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {{ f.write_str(match self {{"
+    );
+    for (variant, k, _) in &variants {
+        write!(code, "Self::{variant} => {k:?},")?;
+    }
+    code.push_str("}) } }");
+    scope.raw(code);
+    scope.raw(format!(
+        "impl TryFrom<{value}> for {name} {{ type Error = {err}; fn try_from(v: {value}) -> Result<Self, Self::Error> {{ if let {value}::String(s) = v {{ s.parse() }} else {{ {raw}::try_from(v)?.try_into() }} }} }}"
+    ));
+    scope.raw(format!(
+        "impl From<{name}> for {value} {{ fn from(v: {name}) -> Self {{ {value}::from({raw}::from(v)) }} }}"
+    ));
+    scope.raw(format!(
+        "impl ::rplc::export::serde::Serialize for {name} {{ fn serialize<S: ::rplc::export::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {{ serializer.serialize_{raw}({raw}::from(*self)) }} }}"
+    ));
+    scope.raw(format!(
+        "impl<'de> ::rplc::export::serde::Deserialize<'de> for {name} {{ fn deserialize<D: ::rplc::export::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {{ <{value} as ::rplc::export::serde::Deserialize>::deserialize(deserializer)?.try_into().map_err(::rplc::export::serde::de::Error::custom) }} }}"
+    ));
+    scope.raw(format!(
+        "impl ::rplc::context::PathAccess for {name} {{
+    fn get_value(&self, idx: &[usize], path: &str) -> ::rplc::export::eva_common::EResult<{value}> {{
+        ::rplc::context::check_value_path(idx, path)?;
+        Ok({value}::from(*self))
+    }}
+    fn set_value(&mut self, idx: &[usize], path: &str, value: {value}) -> ::rplc::export::eva_common::EResult<()> {{
+        ::rplc::context::check_value_path(idx, path)?;
+        *self = value.try_into()?;
+        Ok(())
+    }}
+    fn list_paths(&self, prefix: &str, result: &mut Vec<String>) {{
+        result.push(prefix.to_owned());
+    }}
+}}"
+    ));
+    #[cfg(feature = "modbus")]
+    {
+        // 8-bit enums are mapped to single registers
+        let reg = match raw {
+            "u8" => "u16",
+            "i8" => "i16",
+            v => v,
+        };
+        let (conv_from, conv_into) = if reg == raw {
+            (format!("{raw}::from(*v)"), String::new())
+        } else {
+            (
+                format!("{reg}::from({raw}::from(*v))"),
+                format!(".try_into().map_err({err}::invalid_data)?"),
+            )
+        };
+        scope.raw(format!(
+            "impl From<&{name}> for ::rplc::io::modbus::Registers {{ fn from(v: &{name}) -> Self {{ Self::from(&{conv_from}) }} }}"
+        ));
+        scope.raw(format!(
+            "impl<'a> TryFrom<::rplc::io::modbus::RegisterSlice<'a>> for {name} {{ type Error = {err}; fn try_from(s: ::rplc::io::modbus::RegisterSlice<'a>) -> Result<Self, Self::Error> {{ let v: {raw} = {reg}::try_from(s)?{conv_into}; v.try_into() }} }}"
+        ));
+        let size = match reg {
+            "u64" | "i64" => 4,
+            "u32" | "i32" => 2,
+            _ => 1,
+        };
+        scope.raw(format!(
+            "impl ::rplc::io::modbus::RegisterEnum for {name} {{ const SIZE: usize = {size}; \
+                fn to_registers(&self) -> ::rplc::io::modbus::Registers {{ self.into() }} \
+                fn from_registers(s: ::rplc::io::modbus::RegisterSlice) -> ::rplc::export::eva_common::EResult<Self> {{ s.try_into() }} }}"
+        ));
+    }
+    #[cfg(feature = "opcua")]
+    {
+        let variant = "::rplc::export::opcua::types::Variant";
+        scope.raw(format!(
+            "impl From<{name}> for {variant} {{ fn from(v: {name}) -> Self {{ Self::from({raw}::from(v)) }} }}"
+        ));
+        scope.raw(format!(
+            "impl TryFrom<{variant}> for {name} {{ type Error = {err}; fn try_from(v: {variant}) -> Result<Self, Self::Error> {{ {raw}::try_from(v).map_err(|_| {err}::invalid_data(\"invalid {name} OPC-UA value\"))?.try_into() }} }}"
+        ));
+    }
+    Ok(())
+}
+
+/// Splits a field type into the type and the RETAIN flag
+fn parse_field_type(t: &str) -> (&str, bool) {
+    let tp = t.trim();
+    if let Some(base) = tp.strip_suffix("RETAIN") {
+        if base.ends_with(char::is_whitespace) {
+            return (base.trim_end(), true);
+        }
+    }
+    (tp, false)
+}
+
+struct ArraySize {
+    size: usize,
+    boxed: bool,
+}
+
+/// Parses a struct name, returns the field name and the array size, if the struct is an array
+fn parse_struct_name(k: &str) -> Result<(&str, Option<ArraySize>), Box<dyn Error>> {
+    if k.ends_with(']') {
+        if let Some(pos) = k.rfind('[') {
+            let mut size_s = k[pos + 1..k.len() - 1].trim().replace('_', "");
+            let boxed = if size_s.ends_with('!') {
+                size_s = size_s[..size_s.len() - 1].to_owned();
+                true
+            } else {
+                false
+            };
+            let size = size_s.parse::<usize>().map_err(|e| {
+                eva_common::Error::invalid_params(format!("invalid struct name: {} ({})", k, e))
+            })?;
+            Ok((&k[..pos], Some(ArraySize { size, boxed })))
+        } else {
+            Err(eva_common::Error::invalid_params(format!("invalid struct name: {}", k)).into())
+        }
+    } else {
+        Ok((k, None))
+    }
+}
+
+fn collect_retain(
+    prefix: &str,
+    fields: &IndexMap<String, ContextField>,
+    result: &mut Vec<String>,
+) -> Result<(), Box<dyn Error>> {
+    for (k, v) in fields {
+        match v {
+            ContextField::Type(t) | ContextField::Field(FieldConfig { tp: t, .. }) => {
+                if parse_field_type(t).1 {
+                    result.push(format!("{}{}", prefix, k));
+                }
+            }
+            ContextField::Map(m) => {
+                let (name, array) = parse_struct_name(k)?;
+                if let Some(a) = array {
+                    for i in 0..a.size {
+                        collect_retain(&format!("{}{}[{}].", prefix, name, i), m, result)?;
+                    }
+                } else {
+                    collect_retain(&format!("{}{}.", prefix, name), m, result)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collects metadata of the expanded fields, including fields of user-defined structures
+///
+/// The paths are without array indexes, the metadata is common for all array elements
+fn collect_meta<'a>(
+    prefix: &str,
+    fields: &'a IndexMap<String, ContextField>,
+    types: &'a IndexMap<String, TypeConfig>,
+    parents: &mut Vec<&'a str>,
+    result: &mut Vec<(String, &'a FieldConfig)>,
+) -> Result<(), Box<dyn Error>> {
+    for (k, v) in fields {
+        match v {
+            ContextField::Type(t) | ContextField::Field(FieldConfig { tp: t, .. }) => {
+                if let ContextField::Field(config) = v {
+                    if config.has_meta() {
+                        result.push((format!("{}{}", prefix, k), config));
+                    }
+                }
+                let (base_tp, _) = parse_base_type(parse_field_type(t).0);
+                if let Some((
+                    name,
+                    TypeConfig {
+                        fields: Some(sub), ..
+                    },
+                )) = types.get_key_value(base_tp.as_str())
+                {
+                    if parents.contains(&name.as_str()) {
+                        return Err(eva_common::Error::invalid_params(format!(
+                            "type {} contains itself",
+                            name
+                        ))
+                        .into());
+                    }
+                    parents.push(name);
+                    collect_meta(&format!("{}{}.", prefix, k), sub, types, parents, result)?;
+                    parents.pop();
+                }
+            }
+            ContextField::Map(m) => {
+                let (name, _) = parse_struct_name(k)?;
+                collect_meta(&format!("{}{}.", prefix, name), m, types, parents, result)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn generate_meta(
+    meta: &[(String, &FieldConfig)],
+    scope: &mut codegen::Scope,
+) -> Result<(), Box<dyn Error>> {
+    let mut s = "pub(crate) static FIELD_META: &[::rplc::context::FieldMeta] = &[".to_owned();
+    for (path, config) in meta {
+        write!(
+            s,
+            "::rplc::context::FieldMeta {{ path: {:?}, min: {:?}, max: {:?}, unit: {:?}, description: {:?} }},",
+            path,
+            config.min,
+            config.max,
+            config.unit.as_deref(),
+            config.description.as_deref()
+        )?;
+    }
+    s.push_str("];");
+    scope.raw(s);
+    Ok(())
+}
+
+/// Checks the value range of an expanded field
+fn check_field_config(config: &FieldConfig) -> Result<(), Box<dyn Error>> {
+    if config.min.is_none() && config.max.is_none() {
+        return Ok(());
+    }
+    let (base_tp, _) = parse_base_type(parse_field_type(&config.tp).0);
+    let integer = match base_val(&base_tp) {
+        Some("0") => true,
+        Some("0.0") => false,
+        _ => {
+            return Err(eva_common::Error::invalid_params(format!(
+                "min/max are supported for numeric types only, {} given",
+                config.tp
+            ))
+            .into())
+        }
+    };
+    for bound in [config.min, config.max].into_iter().flatten() {
+        if !bound.is_finite() || (integer && bound.fract() != 0.0) {
+            return Err(eva_common::Error::invalid_params(format!(
+                "invalid range bound: {}",
+                bound
+            ))
+            .into());
+        }
+    }
+    if let (Some(min), Some(max)) = (config.min, config.max) {
+        if min > max {
+            return Err(eva_common::Error::invalid_params(format!(
+                "invalid range: min {} is greater than max {}",
+                min, max
+            ))
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Generates the initial value of a field, a scalar init value of an array is set to all its
+/// elements
+fn generate_init(
+    t: &str,
+    value: &Value,
+    config: &FieldConfig,
+    types: &IndexMap<String, TypeConfig>,
+) -> Result<String, Box<dyn Error>> {
+    let (base_tp, dims) = parse_base_type(t.trim());
+    generate_init_value(&base_tp, &parse_dims(t, dims)?, value, config, types)
+}
+
+/// Parses array dimensions, the first one is the inner
+fn parse_dims(t: &str, dims: &str) -> Result<Vec<ArraySize>, Box<dyn Error>> {
+    let mut sizes = Vec::new();
+    for d in dims.split('[').skip(1) {
+        let size_s = d[0..d.len() - 1].trim().replace('_', "");
+        let boxed = size_s.ends_with('!');
+        let size = size_s
+            .trim_end_matches('!')
+            .parse::<usize>()
+            .map_err(|e| eva_common::Error::invalid_params(format!("{}: {}", t, e)))?;
+        sizes.push(ArraySize { size, boxed });
+    }
+    Ok(sizes)
+}
+
+fn generate_init_value(
+    base_tp: &str,
+    sizes: &[ArraySize],
+    value: &Value,
+    config: &FieldConfig,
+    types: &IndexMap<String, TypeConfig>,
+) -> Result<String, Box<dyn Error>> {
+    // the last dimension is the outer one
+    if let Some((a, inner)) = sizes.split_last() {
+        let result = if let Value::Seq(values) = value {
+            if values.len() != a.size {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "init array size mismatch: {} expected",
+                    a.size
+                ))
+                .into());
+            }
+            let mut r = "[".to_owned();
+            for v in values {
+                write!(
+                    r,
+                    "{},",
+                    generate_init_value(base_tp, inner, v, config, types)?
+                )
+                .unwrap();
+            }
+            r.push(']');
+            r
+        } else {
+            format!(
+                "::std::array::from_fn(|_| {})",
+                generate_init_value(base_tp, inner, value, config, types)?
+            )
+        };
+        return Ok(if a.boxed {
+            format!("Box::new({})", result)
+        } else {
+            result
+        });
+    }
+    if base_val(base_tp).is_some_and(|v| v != "false") {
+        let n = f64::try_from(value.clone())?;
+        if !n.is_finite()
+            || config.min.is_some_and(|min| n < min)
+            || config.max.is_some_and(|max| n > max)
+        {
+            return Err(eva_common::Error::invalid_params(format!(
+                "init value {} is out of range",
+                value
+            ))
+            .into());
+        }
+    }
+    let v = value.clone();
+    let result = match base_tp {
+        "bool" => bool::try_from(v)?.to_string(),
+        "u8" => u8::try_from(v)?.to_string(),
+        "u16" => u16::try_from(v)?.to_string(),
+        "u32" => u32::try_from(v)?.to_string(),
+        "u64" => u64::try_from(v)?.to_string(),
+        "i8" => i8::try_from(v)?.to_string(),
+        "i16" => i16::try_from(v)?.to_string(),
+        "i32" => i32::try_from(v)?.to_string(),
+        "i64" => i64::try_from(v)?.to_string(),
+        "f32" => format!("{:?}", f32::try_from(v)?),
+        "f64" => format!("{:?}", f64::try_from(v)?),
+        "::rplc::iec::Time" => format!("{}({})", base_tp, crate::iec::Time::try_from(v)?.0),
+        "::rplc::iec::Date" => format!("{}({})", base_tp, crate::iec::Date::try_from(v)?.0),
+        "::rplc::iec::Tod" => format!("{}({})", base_tp, crate::iec::Tod::try_from(v)?.0),
+        "::rplc::iec::Dt" => format!("{}({})", base_tp, crate::iec::Dt::try_from(v)?.0),
+        _ => {
+            if let Some(capacity) = base_tp
+                .strip_prefix("::rplc::iec::IecString<")
+                .or_else(|| base_tp.strip_prefix("::rplc::iec::IecWString<"))
+                .and_then(|c| c.strip_suffix('>'))
+            {
+                let s = if let Value::String(s) = v {
+                    s
+                } else {
+                    return Err(eva_common::Error::invalid_params("init string expected").into());
+                };
+                let len = if base_tp.starts_with("::rplc::iec::IecString<") {
+                    s.len()
+                } else {
+                    s.encode_utf16().count()
+                };
+                if len > capacity.parse::<usize>()? {
+                    return Err(eva_common::Error::invalid_params(format!(
+                        "init string is too long, max {}",
+                        capacity
+                    ))
+                    .into());
+                }
+                format!(
+                    "{{ let mut s = {}::new(); s.set({:?}); s }}",
+                    base_tp.replacen('<', "::<", 1),
+                    s
+                )
+            } else if let Some(TypeConfig {
+                values: Some(values),
+                ..
+            }) = types.get(base_tp)
+            {
+                let variant = if let Value::String(ref s) = v {
+                    values.get_key_value(s).map(|(k, _)| k)
+                } else {
+                    let n = i64::try_from(v)?;
+                    values.iter().find(|(_, &val)| val == n).map(|(k, _)| k)
+                };
+                let variant = variant.ok_or_else(|| {
+                    eva_common::Error::invalid_params(format!(
+                        "invalid init value {} for {}",
+                        value, base_tp
+                    ))
+                })?;
+                format!("{}::{}", base_tp, variant.to_pascal_case())
+            } else {
+                return Err(eva_common::Error::unsupported(format!(
+                    "init values are not supported for {}",
+                    base_tp
+                ))
+                .into());
+            }
+        }
+    };
+    Ok(result)
+}
+
+fn generate_retain(fields: &[String], scope: &mut codegen::Scope) {
+    let f_dump = scope
+        .new_fn("retain_dump")
+        .vis("pub(crate)")
+        .ret("::rplc::export::eva_common::EResult<::std::collections::BTreeMap<&'static str, ::rplc::export::eva_common::value::Value>>");
+    f_dump.line("let ctx = CONTEXT.read();");
+    f_dump.line("let mut data = ::std::collections::BTreeMap::new();");
+    for field in fields {
+        f_dump.line(format!(
+            "data.insert(\"{field}\", ::rplc::retain::to_value(&ctx.{field})?);"
+        ));
+    }
+    f_dump.line("Ok(data)");
+    let f_load = scope
+        .new_fn("retain_load")
+        .vis("pub(crate)")
+        .arg("path", "&str")
+        .arg("value", "::rplc::export::eva_common::value::Value")
+        .ret("::rplc::export::eva_common::EResult<()>");
+    f_load.line("let mut ctx = CONTEXT.write();");
+    let mut m = codegen::Block::new("match path");
+    for field in fields {
+        m.line(format!(
+            "\"{field}\" => ctx.{field} = ::rplc::retain::from_value(value)?,"
+        ));
+    }
+    m.line("_ => return Err(::rplc::export::eva_common::Error::not_found(path)),");
+    f_load.push_block(m);
+    f_load.line("Ok(())");
 }
 
 fn parse_iec_type(tp: &str) -> &str {
@@ -251,7 +1689,49 @@ fn parse_iec_type(tp: &str) -> &str {
         "LINT" => "i64",
         "REAL" => "f32",
         "LREAL" => "f64",
-        _ => tp,
+        "TIME" => "::rplc::iec::Time",
+        "DATE" => "::rplc::iec::Date",
+        "TOD" | "TIME_OF_DAY" => "::rplc::iec::Tod",
+        "DT" | "DATE_AND_TIME" => "::rplc::iec::Dt",
+        _ => crate::fb::IEC_TYPES
+            .iter()
+            .find(|(iec, _)| *iec == tp)
+            .map_or(tp, |(_, t)| t),
+    }
+}
+
+/// Splits a field type into the base Rust type and array dimensions, e.g. "[2][3]"
+///
+/// For STRING/WSTRING, the first dimension is the string capacity
+fn parse_base_type(tp: &str) -> (String, &str) {
+    if tp.starts_with('[') || !tp.ends_with(']') {
+        let base_tp = string_type(tp, None).unwrap_or_else(|| parse_iec_type(tp).to_owned());
+        return (base_tp, "");
+    }
+    let pos = tp.find('[').unwrap();
+    let (name, dims) = (tp[..pos].trim(), &tp[pos..]);
+    if let Some(len_end) = dims.find(']') {
+        let len_s = dims[1..len_end].trim().replace('_', "");
+        if let Some(base_tp) = string_type(
+            name,
+            Some(
+                len_s
+                    .parse::<usize>()
+                    .unwrap_or_else(|e| panic!("{e}: {len_s}")),
+            ),
+        ) {
+            return (base_tp, &dims[len_end + 1..]);
+        }
+    }
+    (parse_iec_type(name).to_owned(), dims)
+}
+
+fn string_type(tp: &str, len: Option<usize>) -> Option<String> {
+    let len = len.unwrap_or(crate::iec::DEFAULT_STRING_LEN);
+    match tp {
+        "STRING" => Some(format!("::rplc::iec::IecString<{}>", len)),
+        "WSTRING" => Some(format!("::rplc::iec::IecWString<{}>", len)),
+        _ => None,
     }
 }
 
@@ -267,89 +1747,149 @@ fn base_val(tp: &str) -> Option<&str> {
 }
 
 fn parse_type(t: &str) -> String {
-    let tp = t.trim();
-    if tp.ends_with(']') && !tp.starts_with('[') {
-        let mut sp = tp.split('[');
-        let mut result = String::new();
-        let base_tp = parse_iec_type(sp.next().unwrap());
-        for d in sp {
-            let mut size_s = d[0..d.len() - 1].trim().replace('_', "");
-            let boxed = if size_s.ends_with('!') {
-                size_s = size_s[..size_s.len() - 1].to_owned();
-                true
-            } else {
-                false
-            };
-            let size = size_s
-                .parse::<usize>()
-                .unwrap_or_else(|e| panic!("{e}: {size_s}"));
-            if result.is_empty() {
-                if boxed {
-                    write!(result, "Box<[{}; {}]>", base_tp, size).unwrap();
-                } else {
-                    write!(result, "[{}; {}]", base_tp, size).unwrap();
-                }
-            } else if boxed {
-                result = format!("Box<[{}; {}]>", result, size);
-            } else {
-                result = format!("[{}; {}]", result, size);
-            }
+    let (base_tp, dims) = parse_base_type(t.trim());
+    let mut result = base_tp;
+    for d in dims.split('[').skip(1) {
+        let mut size_s = d[0..d.len() - 1].trim().replace('_', "");
+        let boxed = if size_s.ends_with('!') {
+            size_s = size_s[..size_s.len() - 1].to_owned();
+            true
+        } else {
+            false
+        };
+        let size = size_s
+            .parse::<usize>()
+            .unwrap_or_else(|e| panic!("{e}: {size_s}"));
+        if boxed {
+            result = format!("Box<[{}; {}]>", result, size);
+        } else {
+            result = format!("[{}; {}]", result, size);
         }
-        result
-    } else {
-        parse_iec_type(tp).to_owned()
     }
+    result
 }
 
 fn generate_default(t: &str) -> String {
-    let tp = t.trim();
-    if tp.ends_with(']') && !tp.starts_with('[') {
-        let mut sp = tp.split('[');
-        let mut result = String::new();
-        let base_tp = parse_iec_type(sp.next().unwrap());
-        for d in sp {
-            let mut size_s = d[0..d.len() - 1].trim().replace('_', "");
-            let boxed = if size_s.ends_with('!') {
-                size_s = size_s[..size_s.len() - 1].to_owned();
-                true
+    let (base_tp, dims) = parse_base_type(t.trim());
+    if dims.is_empty() {
+        return "<_>::default()".to_owned();
+    }
+    let mut result = String::new();
+    for d in dims.split('[').skip(1) {
+        let mut size_s = d[0..d.len() - 1].trim().replace('_', "");
+        let boxed = if size_s.ends_with('!') {
+            size_s = size_s[..size_s.len() - 1].to_owned();
+            true
+        } else {
+            false
+        };
+        let size = size_s
+            .parse::<usize>()
+            .unwrap_or_else(|e| panic!("{e}: {size_s}"));
+        if result.is_empty() {
+            if boxed {
+                write!(result, "Box::new(").unwrap();
+            }
+            if let Some(val) = base_val(&base_tp) {
+                write!(result, "[{};{}]", val, size).unwrap();
             } else {
-                false
-            };
-            let size = size_s
-                .parse::<usize>()
-                .unwrap_or_else(|e| panic!("{e}: {size_s}"));
-            if result.is_empty() {
-                if boxed {
-                    write!(result, "Box::new(").unwrap();
-                }
-                if let Some(val) = base_val(base_tp) {
-                    write!(result, "[{};{}]", val, size).unwrap();
-                } else {
-                    write!(result, "::std::array::from_fn(|_| <_>::default())").unwrap();
-                }
-                if boxed {
-                    write!(result, ")").unwrap();
-                }
+                write!(result, "::std::array::from_fn(|_| <_>::default())").unwrap();
+            }
+            if boxed {
+                write!(result, ")").unwrap();
+            }
+        } else {
+            let mut r = if boxed {
+                "Box::new([".to_owned()
             } else {
-                let mut r = if boxed {
-                    "Box::new([".to_owned()
-                } else {
-                    "[".to_owned()
-                };
-                for _ in 0..size {
-                    write!(r, "{},", result).unwrap();
-                }
-                write!(r, "]").unwrap();
-                if boxed {
-                    write!(r, ")").unwrap();
-                }
-                result = r;
+                "[".to_owned()
+            };
+            for _ in 0..size {
+                write!(r, "{},", result).unwrap();
+            }
+            write!(r, "]").unwrap();
+            if boxed {
+                write!(r, ")").unwrap();
             }
+            result = r;
         }
-        result
-    } else {
-        "<_>::default()".to_owned()
     }
+    result
+}
+
+/// Returns true if the field type implements rplc::context::PathAccess
+fn is_path_accessible(t: &str, types: &IndexMap<String, TypeConfig>) -> bool {
+    let tp = t.trim();
+    if tp.starts_with('[') {
+        return false;
+    }
+    let (base_tp, _) = parse_base_type(tp);
+    base_val(&base_tp).is_some()
+        || types.contains_key(base_tp.as_str())
+        || base_tp.starts_with("::rplc::fb::")
+        || base_tp.starts_with("::rplc::iec::")
+        || base_tp == "::rplc::sfc::SfcState"
+}
+
+fn generate_path_access(name: &str, fields: &[&str]) -> codegen::Impl {
+    let mut im = codegen::Impl::new(name);
+    im.impl_trait("::rplc::context::PathAccess");
+    let f_get = im
+        .new_fn("get_value")
+        .allow("unused_mut, unused_variables, clippy::match_single_binding")
+        .arg_ref_self()
+        .arg("idx", "&[usize]")
+        .arg("path", "&str")
+        .ret("::rplc::export::eva_common::EResult<::rplc::export::eva_common::value::Value>");
+    f_get.line("::rplc::context::check_struct_path(idx)?;");
+    let mut b_all = codegen::Block::new("if path.is_empty()");
+    b_all.line("let mut result = ::std::collections::BTreeMap::new();");
+    for field in fields {
+        b_all.line(format!(
+            "result.insert(\"{field}\".into(), ::rplc::context::PathAccess::get_value(&self.{field}, &[], \"\")?);"
+        ));
+    }
+    b_all.line("return Ok(::rplc::export::eva_common::value::Value::Map(result));");
+    f_get.push_block(b_all);
+    f_get.line("let (field, idx, rest) = ::rplc::context::split_path(path)?;");
+    let mut m = codegen::Block::new("match field");
+    for field in fields {
+        m.line(format!(
+            "\"{field}\" => ::rplc::context::PathAccess::get_value(&self.{field}, &idx, rest),"
+        ));
+    }
+    m.line("_ => Err(::rplc::export::eva_common::Error::not_found(field)),");
+    f_get.push_block(m);
+    let f_set = im
+        .new_fn("set_value")
+        .allow("unused_variables, clippy::match_single_binding")
+        .arg_mut_self()
+        .arg("idx", "&[usize]")
+        .arg("path", "&str")
+        .arg("value", "::rplc::export::eva_common::value::Value")
+        .ret("::rplc::export::eva_common::EResult<()>");
+    f_set.line("::rplc::context::check_struct_path(idx)?;");
+    f_set.line("let (field, idx, rest) = ::rplc::context::split_path(path)?;");
+    let mut m = codegen::Block::new("match field");
+    for field in fields {
+        m.line(format!(
+            "\"{field}\" => ::rplc::context::PathAccess::set_value(&mut self.{field}, &idx, rest, value),"
+        ));
+    }
+    m.line("_ => Err(::rplc::export::eva_common::Error::not_found(field)),");
+    f_set.push_block(m);
+    let f_list = im
+        .new_fn("list_paths")
+        .allow("unused_variables")
+        .arg_ref_self()
+        .arg("prefix", "&str")
+        .arg("result", "&mut Vec<String>");
+    for field in fields {
+        f_list.line(format!(
+            "::rplc::context::PathAccess::list_paths(&self.{field}, &::rplc::context::join_path(prefix, \"{field}\"), result);"
+        ));
+    }
+    im
 }
 
 #[allow(clippy::too_many_lines)]
@@ -359,6 +1899,7 @@ fn generate_structs(
     scope: &mut codegen::Scope,
     #[cfg(feature = "modbus")] modbus_config: Option<&ModbusConfig>,
     serialize: bool,
+    types: &IndexMap<String, TypeConfig>,
 ) -> Result<(), Box<dyn Error>> {
     let mut st: codegen::Struct = codegen::Struct::new(name);
     let mut st_impl: codegen::Impl = codegen::Impl::new(name);
@@ -371,54 +1912,54 @@ fn generate_structs(
         .repr("C")
         .vis("pub");
     if serialize {
-        st.derive("Serialize").derive("Deserialize");
-        st.attr("serde(crate = \"self::serde\")");
+        st.derive("::rplc::export::serde::Serialize")
+            .derive("::rplc::export::serde::Deserialize");
+        st.attr("serde(crate = \"::rplc::export::serde\")");
     }
+    let mut accessible = Vec::new();
     for (k, v) in fields {
         match v {
-            ContextField::Type(t) => {
+            ContextField::Type(t) | ContextField::Field(FieldConfig { tp: t, .. }) => {
+                let (t, _) = parse_field_type(t);
                 let mut field = codegen::Field::new(k, parse_type(t));
                 field.vis("pub");
                 if serialize {
                     field.annotation.push("#[serde(default)]".to_owned());
                 }
-                default.line(format!("{}: {},", field.name, generate_default(t)));
+                let init = if let ContextField::Field(config) = v {
+                    check_field_config(config)
+                        .and_then(|()| {
+                            config.init.as_ref().map_or_else(
+                                || Ok(generate_default(t)),
+                                |value| generate_init(t, value, config, types),
+                            )
+                        })
+                        .map_err(|e| {
+                            eva_common::Error::invalid_params(format!("{}.{}: {}", name, k, e))
+                        })?
+                } else {
+                    generate_default(t)
+                };
+                default.line(format!("{}: {},", field.name, init));
                 st.push_field(field);
+                if is_path_accessible(t, types) {
+                    accessible.push(k.as_str());
+                }
             }
             ContextField::Map(m) => {
-                let (mut field, sub_name) = if k.ends_with(']') {
-                    let (number, field_name, boxed) = if let Some(pos) = k.rfind('[') {
-                        let mut size_s = k[pos + 1..k.len() - 1].trim().replace('_', "");
-                        let boxed = if size_s.ends_with('!') {
-                            size_s = size_s[..size_s.len() - 1].to_owned();
-                            true
-                        } else {
-                            false
-                        };
-                        let field = size_s.parse::<usize>().map_err(|e| {
-                            eva_common::Error::invalid_params(format!(
-                                "invalid struct name: {} ({})",
-                                k, e
-                            ))
-                        })?;
-                        (field, &k[..pos], boxed)
-                    } else {
-                        return Err(eva_common::Error::invalid_params(format!(
-                            "invalid struct name: {}",
-                            k
-                        ))
-                        .into());
-                    };
-                    let sub_name = format!("{}{}", name, field_name.to_title_case());
-                    let field_value = if boxed {
-                        format!("Box<[{}; {}]>", sub_name, number)
+                let (field_name, array) = parse_struct_name(k)?;
+                accessible.push(field_name);
+                // e.g. io_status of Context is generated as ContextIoStatus
+                let sub_name = format!("{}{}", name, field_name.to_pascal_case());
+                let mut field = if let Some(a) = array {
+                    let field_value = if a.boxed {
+                        format!("Box<[{}; {}]>", sub_name, a.size)
                     } else {
-                        format!("[{}; {}]", sub_name, number)
+                        format!("[{}; {}]", sub_name, a.size)
                     };
-                    (codegen::Field::new(field_name, field_value), sub_name)
+                    codegen::Field::new(field_name, field_value)
                 } else {
-                    let sub_name = format!("{}{}", name, k.to_title_case());
-                    (codegen::Field::new(k, &sub_name), sub_name)
+                    codegen::Field::new(k, &sub_name)
                 };
                 field.vis("pub");
                 if serialize {
@@ -433,6 +1974,7 @@ fn generate_structs(
                     #[cfg(feature = "modbus")]
                     None,
                     serialize,
+                    types,
                 )?;
             }
         }
@@ -457,6 +1999,7 @@ fn generate_structs(
     scope.push_struct(st);
     scope.raw("#[allow(clippy::derivable_impls)]");
     scope.push_impl(st_impl);
+    scope.push_impl(generate_path_access(name, &accessible));
     #[cfg(feature = "modbus")]
     if let Some(c) = modbus_config {
         let im = scope.new_impl(&format!(
@@ -488,3 +2031,144 @@ fn generate_structs(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_field_schema() {
+        let fields: IndexMap<String, ContextField> = serde_yaml::from_str(
+            r"
+temp: REAL
+flags: UINT[4]
+connector[2]:
+  voltage:
+    type: LREAL
+    min: 0
+  valve: Valve
+custom: MyType
+",
+        )
+        .unwrap();
+        let types: IndexMap<String, TypeConfig> =
+            serde_yaml::from_str("Valve: { struct: { pos: REAL } }").unwrap();
+        let schema = FieldSchema::from_fields(&fields, &types, &mut Vec::new()).unwrap();
+        let type_name = |path| schema.resolve(path).map(|v| v.map(FieldSchema::type_name));
+        assert_eq!(type_name("temp").unwrap().unwrap(), "f32");
+        assert_eq!(type_name("flags").unwrap().unwrap(), "[u16; 4]");
+        assert_eq!(type_name("flags[3]").unwrap().unwrap(), "u16");
+        assert_eq!(type_name("connector[1].voltage").unwrap().unwrap(), "f64");
+        assert_eq!(type_name("connector[0].valve.pos").unwrap().unwrap(), "f32");
+        assert!(type_name("custom.any[1].field").unwrap().is_none());
+        assert!(type_name("tmp").is_err());
+        assert!(type_name("flags[4]").is_err());
+        assert!(type_name("temp[0]").is_err());
+        assert!(type_name("connector.voltage").is_err());
+        assert!(type_name("connector[0].valve.position").is_err());
+        assert!(schema.resolve_number("connector[1].valve.pos").is_ok());
+        assert!(schema.resolve_number("custom.any").is_ok());
+        assert!(schema.resolve_number("connector[1].valve").is_err());
+        assert!(schema.resolve_number("flags").is_err());
+    }
+
+    #[cfg(feature = "modbus")]
+    #[test]
+    fn test_modbus_mapping() {
+        let fields: IndexMap<String, ContextField> = serde_yaml::from_str(
+            r"
+temp: REAL
+counter: ULINT
+flags: UINT[4]
+pid: PID
+timer: TON
+custom: MyType
+",
+        )
+        .unwrap();
+        let schema = FieldSchema::from_fields(&fields, &IndexMap::new(), &mut Vec::new()).unwrap();
+        let validate = |inputs: &str, outputs: &str| {
+            crate::io::modbus::validate(
+                "mb1",
+                &serde_yaml::from_str::<Vec<Value>>(inputs).unwrap(),
+                &serde_yaml::from_str::<Vec<Value>>(outputs).unwrap(),
+                &schema,
+            )
+            .map_err(|e| e.to_string())
+        };
+        let input = |map: &str| {
+            validate(
+                &format!(
+                    "[{{ reg: h0, number: 8, unit: 1, sync: 1s, map: {} }}]",
+                    map
+                ),
+                "[]",
+            )
+        };
+        // sizes
+        input("[{ target: temp }, { offset: 2, target: counter }, { offset: 6, target: temp }]")
+            .unwrap();
+        assert!(input("[{ offset: 6, target: counter }]")
+            .unwrap_err()
+            .contains("takes 4 holding registers at offset 6, the block has 8"));
+        assert!(input("[{ offset: 5, target: flags }]").is_err());
+        input("[{ offset: 4, target: flags }]").unwrap();
+        // overlaps
+        assert!(
+            input("[{ target: temp }, { offset: 1, target: \"flags[0]\" }]")
+                .unwrap_err()
+                .contains("overlap")
+        );
+        // function block fields
+        input("[{ target: pid.sp }, { offset: 2, target: pid.kp }]").unwrap();
+        assert!(input("[{ target: pid.sp }, { offset: 1, target: pid.kp }]").is_err());
+        assert!(input("[{ target: pid.manual }]").is_err());
+        assert!(input("[{ target: pid }]").is_err());
+        // custom types and durations are mapped with their own conversions
+        input("[{ target: custom }, { target: custom.field }, { target: timer.pt }]").unwrap();
+        // coils
+        assert!(validate(
+            "[{ reg: c0, number: 2, unit: 1, sync: 1s, map: [{ target: temp }] }]",
+            "[]"
+        )
+        .is_err());
+        // outputs of the same unit writing the same registers
+        let outputs = |second: &str| {
+            validate(
+                "[]",
+                &format!(
+                    "[{{ reg: h0, number: 4, unit: 1, sync: 1s, map: [{{ source: flags }}] }}, {}]",
+                    second
+                ),
+            )
+        };
+        assert!(
+            outputs("{ reg: h2, number: 2, unit: 1, sync: 1s, map: [{ source: temp }] }")
+                .unwrap_err()
+                .contains("overlap")
+        );
+        outputs("{ reg: h4, number: 2, unit: 1, sync: 1s, map: [{ source: temp }] }").unwrap();
+        outputs("{ reg: h2, number: 2, unit: 2, sync: 1s, map: [{ source: temp }] }").unwrap();
+    }
+
+    #[test]
+    fn test_chart_validation() {
+        let chart = |steps: &str| -> SfcConfig {
+            serde_yaml::from_str(&format!("{{ id: c1, interval: 1, steps: {} }}", steps)).unwrap()
+        };
+        let generate = |steps: &str| generate_chart(&chart(steps), &mut codegen::Scope::new());
+        generate("[{ name: idle, action: logic::idle }, { name: run, entry: start }]").unwrap();
+        assert!(generate("[{ name: idle, action: \"idle(); panic!()\" }]").is_err());
+        assert!(generate("[{ name: idle, exit: \"\" }]").is_err());
+        assert!(generate("[{ name: \"idle\\nfn x() {}\" }]").is_err());
+        assert!(generate("[{ name: \"\" }]").is_err());
+        assert!(generate_chart(
+            &serde_yaml::from_str("{ id: c1, interval: 0, steps: [{ name: idle }] }").unwrap(),
+            &mut codegen::Scope::new()
+        )
+        .is_err());
+        assert!(
+            generate("[{ name: idle, transitions: [{ to: run, condition: \"true\" }] }]").is_err()
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use serde::Serialize;
+use std::collections::BTreeSet;
 use std::env;
 use std::error::Error;
 use std::fmt;
+use std::fmt::Write as _;
 use std::fs;
 use std::io::Write;
 use std::path::Path;
@@ -37,7 +39,9 @@ impl<'a> Builder<'a> {
         prepare(&config)?;
         fs::create_dir_all("src/plc")?;
         config.generate_io("src/plc/io.rs")?;
+        config.generate_types("src/plc/types.rs")?;
         config.generate_context("src/plc/context.rs")?;
+        config.generate_sfc("src/plc/sfc.rs")?;
         Ok(())
     }
 }
@@ -49,6 +53,8 @@ fn prepare(config: &Config) -> Result<(), Box<dyn Error>> {
     plc_mod.raw("use ::std::time::Duration;");
     plc_mod.raw("pub mod context;");
     plc_mod.raw("pub mod io;");
+    plc_mod.raw("pub mod sfc;");
+    plc_mod.raw("pub mod types;");
     for c in &["VERSION", "NAME", "DESCRIPTION"] {
         plc_mod.raw(format!(
             "pub const {c}: &str = \"{}\";",
@@ -63,6 +69,34 @@ fn prepare(config: &Config) -> Result<(), Box<dyn Error>> {
             "None".to_owned()
         }
     ));
+    plc_mod.raw(format!(
+        "pub const WATCHDOG: Option<::rplc::tasks::Watchdog> = {};",
+        if let Some(ref wd) = config.core.watchdog {
+            if wd.missed == 0 {
+                return Err(
+                    eva_common::Error::invalid_params("watchdog missed intervals can not be zero")
+                        .into(),
+                );
+            }
+            format!(
+                "Some(::rplc::tasks::Watchdog {{ missed: {}, action: ::rplc::tasks::WatchdogAction::{:?} }})",
+                wd.missed, wd.action
+            )
+        } else {
+            "None".to_owned()
+        }
+    ));
+    plc_mod.raw(generate_retain(config)?);
+    plc_mod.raw(generate_chains(config)?);
+    plc_mod.raw(generate_trends(config)?);
+    plc_mod.raw(generate_alarms(config)?);
+    plc_mod.raw(generate_sfc(config)?);
+    plc_mod.raw(generate_rt_profile(config)?);
+    let panic = &config.core.panic;
+    plc_mod.raw(format!(
+        "pub const PANIC_POLICY: ::rplc::tasks::PanicPolicy = ::rplc::tasks::PanicPolicy {{ input: ::rplc::tasks::PanicAction::{:?}, program: ::rplc::tasks::PanicAction::{:?}, output: ::rplc::tasks::PanicAction::{:?}, chain: ::rplc::tasks::PanicAction::{:?} }};",
+        panic.input, panic.program, panic.output, panic.chain
+    ));
     #[allow(clippy::cast_possible_truncation)]
     #[allow(clippy::cast_sign_loss)]
     plc_mod.raw(format!(
@@ -80,6 +114,352 @@ fn prepare(config: &Config) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+fn generate_retain(config: &Config) -> Result<String, Box<dyn Error>> {
+    let fields = config.retain_fields()?;
+    if fields.is_empty() {
+        return Ok("pub const RETAIN: Option<&::rplc::retain::Retain> = None;".to_owned());
+    }
+    let mut code = "#[allow(clippy::unreadable_literal)] pub const RETAIN: Option<&::rplc::retain::Retain> = Some(&::rplc::retain::Retain { fields: &[".to_owned();
+    for field in &fields {
+        write!(code, "\"{}\",", field)?;
+    }
+    write!(
+        code,
+        "], interval: {}, dump: context::retain_dump, load: context::retain_load }});",
+        if let Some(interval) = config.retain_interval() {
+            format!("Some(Duration::from_nanos({}))", interval)
+        } else {
+            "None".to_owned()
+        }
+    )?;
+    Ok(code)
+}
+
+fn generate_chains(config: &Config) -> Result<String, Box<dyn Error>> {
+    let mut chain_ids = BTreeSet::new();
+    let mut chain_steps = BTreeSet::new();
+    let mut code =
+        "#[allow(clippy::unreadable_literal)] pub const CHAINS: &[::rplc::chain::Chain] = &["
+            .to_owned();
+    for chain in &config.chains {
+        if chain.id.is_empty() || chain.id.len() > 14 {
+            return Err(eva_common::Error::invalid_params(format!(
+                "invalid chain id: {} (must be 1-14 characters)",
+                chain.id
+            ))
+            .into());
+        }
+        if !chain_ids.insert(&chain.id) {
+            return Err(eva_common::Error::invalid_params(format!(
+                "duplicate chain: {}",
+                chain.id
+            ))
+            .into());
+        }
+        if chain.steps.is_empty() {
+            return Err(eva_common::Error::invalid_params(format!(
+                "chain {} has no steps",
+                chain.id
+            ))
+            .into());
+        }
+        if chain.interval == 0 {
+            return Err(eva_common::Error::invalid_params(format!(
+                "chain {} interval can not be zero",
+                chain.id
+            ))
+            .into());
+        }
+        write!(
+            code,
+            "::rplc::chain::Chain {{ name: \"{}\", interval: Duration::from_nanos({}), shift: Duration::from_nanos({}), steps: &[",
+            chain.id,
+            chain.interval,
+            chain.shift.unwrap_or_default()
+        )?;
+        for step in &chain.steps {
+            if !chain_steps.insert((step.kind(), step.name())) {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "{} {} is used in chains more than once",
+                    step.kind(),
+                    step.name()
+                ))
+                .into());
+            }
+            if step.kind() != crate::tasks::Kind::Program {
+                let io_tasks = config.io_tasks(step.kind());
+                if !io_tasks.iter().any(|t| t == step.name()) {
+                    return Err(eva_common::Error::invalid_params(format!(
+                        "chain {}: no such {} task: {} (available: {})",
+                        chain.id,
+                        step.kind(),
+                        step.name(),
+                        if io_tasks.is_empty() {
+                            "none".to_owned()
+                        } else {
+                            io_tasks.join(", ")
+                        }
+                    ))
+                    .into());
+                }
+            }
+            write!(
+                code,
+                "(::rplc::tasks::Kind::{:?}, \"{}\"),",
+                step.kind(),
+                step.name()
+            )?;
+        }
+        code.push_str("] },");
+    }
+    code.push_str("];");
+    Ok(code)
+}
+
+fn generate_trends(config: &Config) -> Result<String, Box<dyn Error>> {
+    let schema = config.context_schema()?;
+    let mut trend_ids = BTreeSet::new();
+    let mut code =
+        "#[allow(clippy::unreadable_literal)] pub const TRENDS: &[::rplc::trend::Trend] = &["
+            .to_owned();
+    for trend in &config.trends {
+        if trend.id.is_empty() || trend.id.len() > 11 {
+            return Err(eva_common::Error::invalid_params(format!(
+                "invalid trend id: {} (must be 1-11 characters)",
+                trend.id
+            ))
+            .into());
+        }
+        if !trend_ids.insert(&trend.id) {
+            return Err(eva_common::Error::invalid_params(format!(
+                "duplicate trend: {}",
+                trend.id
+            ))
+            .into());
+        }
+        if trend.paths.is_empty() {
+            return Err(eva_common::Error::invalid_params(format!(
+                "trend {} has no paths",
+                trend.id
+            ))
+            .into());
+        }
+        if trend.interval == 0 || trend.size == 0 {
+            return Err(eva_common::Error::invalid_params(format!(
+                "trend {} interval and size can not be zero",
+                trend.id
+            ))
+            .into());
+        }
+        write!(
+            code,
+            "::rplc::trend::Trend {{ id: \"{}\", interval: Duration::from_nanos({}), size: {}, paths: &[",
+            trend.id, trend.interval, trend.size
+        )?;
+        for path in &trend.paths {
+            if path.trim().is_empty() {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "trend {}: the path is empty",
+                    trend.id
+                ))
+                .into());
+            }
+            schema.resolve(path).map_err(|e| {
+                eva_common::Error::invalid_params(format!("trend {}: {}: {}", trend.id, path, e))
+            })?;
+            write!(code, "{:?},", path)?;
+        }
+        code.push_str("], csv: ");
+        if let Some(ref csv) = trend.csv {
+            write!(
+                code,
+                "Some(::rplc::trend::TrendCsv {{ max_size: {}, keep: {} }})",
+                csv.max_size, csv.keep
+            )?;
+        } else {
+            code.push_str("None");
+        }
+        code.push_str(" },");
+    }
+    code.push_str("];");
+    Ok(code)
+}
+
+fn generate_alarms(config: &Config) -> Result<String, Box<dyn Error>> {
+    let alarms = &config.alarms;
+    if alarms.list.is_empty() {
+        return Ok("pub const ALARMS: Option<&::rplc::alarm::Alarms> = None;".to_owned());
+    }
+    if alarms.interval == 0 {
+        return Err(eva_common::Error::invalid_params("alarm interval can not be zero").into());
+    }
+    let schema = config.context_schema()?;
+    let mut alarm_ids = BTreeSet::new();
+    let mut code = format!(
+        "#[allow(clippy::unreadable_literal)] pub const ALARMS: Option<&::rplc::alarm::Alarms> = Some(&::rplc::alarm::Alarms {{ interval: Duration::from_nanos({}), alarms: &[",
+        alarms.interval
+    );
+    for alarm in &alarms.list {
+        if alarm.id.is_empty() {
+            return Err(eva_common::Error::invalid_params("alarm id can not be empty").into());
+        }
+        if !alarm_ids.insert(&alarm.id) {
+            return Err(eva_common::Error::invalid_params(format!(
+                "duplicate alarm: {}",
+                alarm.id
+            ))
+            .into());
+        }
+        let condition: crate::alarm::ConditionDef = alarm.condition.parse().map_err(|e| {
+            eva_common::Error::invalid_params(format!("alarm {} condition: {}", alarm.id, e))
+        })?;
+        for path in condition.paths() {
+            schema.resolve_number(path).map_err(|e| {
+                eva_common::Error::invalid_params(format!("alarm {} condition: {}", alarm.id, e))
+            })?;
+        }
+        let oid = generate_alarm_oid(alarm)?;
+        write!(
+            code,
+            "::rplc::alarm::Alarm {{ id: {:?}, condition: {}, delay: Duration::from_nanos({}), severity: ::rplc::alarm::Severity::{:?}, latch: {}, description: {:?}, oid: {} }},",
+            alarm.id,
+            condition.to_code(),
+            alarm.delay.unwrap_or_default(),
+            alarm.severity,
+            alarm.latch,
+            alarm.description,
+            oid
+        )?;
+    }
+    code.push_str("] });");
+    Ok(code)
+}
+
+#[cfg(feature = "eva")]
+fn generate_alarm_oid(alarm: &config::AlarmConfig) -> Result<String, Box<dyn Error>> {
+    if let Some(ref oid) = alarm.oid {
+        oid.parse::<eva_common::OID>()?;
+        Ok(format!("Some({:?})", oid))
+    } else {
+        Ok("None".to_owned())
+    }
+}
+
+#[cfg(not(feature = "eva"))]
+fn generate_alarm_oid(alarm: &config::AlarmConfig) -> Result<String, Box<dyn Error>> {
+    if alarm.oid.is_some() {
+        Err(eva_common::Error::invalid_params(format!(
+            "alarm {} oid: EVA ICS support is not enabled",
+            alarm.id
+        ))
+        .into())
+    } else {
+        Ok("None".to_owned())
+    }
+}
+
+fn generate_sfc(config: &Config) -> Result<String, Box<dyn Error>> {
+    let mut code =
+        "#[allow(clippy::unreadable_literal)] pub const SFC: &[::rplc::sfc::Chart] = &[".to_owned();
+    for chart in &config.sfc {
+        write!(code, "::rplc::sfc::Chart {{ id: {:?}, steps: &[", chart.id)?;
+        for step in &chart.steps {
+            write!(code, "{:?},", step.name)?;
+        }
+        write!(
+            code,
+            "], interval: Duration::from_nanos({}), shift: Duration::from_nanos({}), program: sfc::{}, state: sfc::{}_state }},",
+            chart.interval,
+            chart.shift.unwrap_or_default(),
+            chart.id,
+            chart.id
+        )?;
+    }
+    code.push_str("];");
+    Ok(code)
+}
+
+// default Rust thread stack size, KB
+const DEFAULT_STACK_SIZE: usize = 2000;
+// stack reserve which is never prefaulted, KB
+const STACK_RESERVE: usize = 64;
+
+fn generate_rt_profile(config: &Config) -> Result<String, Box<dyn Error>> {
+    let core = &config.core;
+    let prefault_stack = if let Some(size) = core.prefault_stack {
+        let max = core.stack_size.unwrap_or(DEFAULT_STACK_SIZE);
+        if size + STACK_RESERVE > max {
+            return Err(eva_common::Error::invalid_params(format!(
+                "prefault_stack must be at least {} KB less than the stack size ({} KB)",
+                STACK_RESERVE, max
+            ))
+            .into());
+        }
+        format!("Some({})", size * 1000)
+    } else {
+        "None".to_owned()
+    };
+    let mut code = format!(
+        "pub const RT: ::rplc::tasks::RtProfile = ::rplc::tasks::RtProfile {{ mlockall: {}, prefault_stack: {}, tasks: &[",
+        core.mlockall, prefault_stack
+    );
+    for (name, task) in &core.tasks {
+        if name.len() < 2
+            || name.len() > 15
+            || !matches!(name.chars().next(), Some('I' | 'P' | 'O' | 'C' | 'S'))
+        {
+            return Err(eva_common::Error::invalid_params(format!(
+                "invalid task name: {} (must be a full thread name, e.g. Pmain)",
+                name
+            ))
+            .into());
+        }
+        if let Some(priority) = task.priority {
+            let valid = if task.policy == Some(crate::tasks::SchedPolicy::Other) {
+                priority == 0
+            } else {
+                (1..=99).contains(&priority)
+            };
+            if !valid {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "task {}: invalid scheduler priority: {}",
+                    name, priority
+                ))
+                .into());
+            }
+        } else if task.policy.is_some() && task.policy != Some(crate::tasks::SchedPolicy::Other) {
+            return Err(eva_common::Error::invalid_params(format!(
+                "task {}: no scheduler priority specified",
+                name
+            ))
+            .into());
+        }
+        if let Some(nice) = task.nice {
+            if !(-20..=19).contains(&nice) {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "task {}: invalid nice value: {}",
+                    name, nice
+                ))
+                .into());
+            }
+        }
+        write!(
+            code,
+            "::rplc::tasks::TaskRt {{ name: \"{}\", cpu: {:?}, policy: {}, priority: {:?}, nice: {:?} }},",
+            name,
+            task.cpu,
+            task.policy.map_or_else(
+                || "None".to_owned(),
+                |p| format!("Some(::rplc::tasks::SchedPolicy::{:?})", p)
+            ),
+            task.priority,
+            task.nice
+        )?;
+    }
+    code.push_str("] };");
+    Ok(code)
+}
+
 fn format_code(code: String) -> Result<String, Box<dyn Error>> {
     let mut child = Command::new("rustfmt")
         .arg("--edition=2021")
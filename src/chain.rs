@@ -0,0 +1,248 @@
+use crate::interval::Loop;
+use crate::tasks::{self, Kind, Status};
+use log::{debug, info};
+use once_cell::sync::Lazy;
+use parking_lot::{Condvar, Mutex};
+use std::collections::BTreeMap;
+use std::panic;
+use std::time::Duration;
+
+type StepFn = Box<dyn FnMut() + Send>;
+
+static CHAINS: Lazy<Mutex<BTreeMap<&'static str, ChainEntry>>> = Lazy::new(<_>::default);
+static STEPS: Lazy<Mutex<BTreeMap<(Kind, String), &'static str>>> = Lazy::new(<_>::default);
+// chains with inputs/programs which have not been stopped yet
+static CHAINS_RUNNING: Mutex<usize> = Mutex::new(0);
+static CHAINS_STOPPED: Condvar = Condvar::new();
+
+/// Task chain: generated I/O workers and programs, executed in the given order in a single
+/// thread
+///
+/// Chains are declared in the "chains" section of plc.yml
+pub struct Chain {
+    pub name: &'static str,
+    pub interval: Duration,
+    pub shift: Duration,
+    pub steps: &'static [(Kind, &'static str)],
+}
+
+struct ChainEntry {
+    chain: &'static Chain,
+    steps: BTreeMap<usize, StepFn>,
+}
+
+struct Step {
+    kind: Kind,
+    f: StepFn,
+    guard: tasks::Guard,
+}
+
+/// use init_plc!() macro to register chains
+///
+/// # Panics
+///
+/// Will panic if a chain or a step is already registered
+pub fn register(chains: &'static [Chain]) {
+    let mut registered = CHAINS.lock();
+    let mut steps = STEPS.lock();
+    for chain in chains {
+        assert!(
+            registered
+                .insert(
+                    chain.name,
+                    ChainEntry {
+                        chain,
+                        steps: <_>::default()
+                    }
+                )
+                .is_none(),
+            "chain {} is already registered",
+            chain.name
+        );
+        for (kind, name) in chain.steps {
+            assert!(
+                steps
+                    .insert((*kind, (*name).to_owned()), chain.name)
+                    .is_none(),
+                "{} {} is already in a chain",
+                kind,
+                name
+            );
+        }
+    }
+}
+
+/// Takes the task into its chain if it is a chain step. Returns the function back if not
+pub(crate) fn take_step<F>(kind: Kind, name: &str, f: F) -> Result<(), F>
+where
+    F: FnMut() + Send + 'static,
+{
+    let chain_name = if let Some(v) = STEPS.lock().get(&(kind, name.to_owned())) {
+        *v
+    } else {
+        return Err(f);
+    };
+    let mut chains = CHAINS.lock();
+    let entry = chains.get_mut(chain_name).unwrap();
+    let pos = entry
+        .chain
+        .steps
+        .iter()
+        .position(|(k, n)| *k == kind && *n == name)
+        .unwrap();
+    debug!("{} {} is taken into chain {}", kind, name, chain_name);
+    entry.steps.insert(pos, Box::new(f));
+    Ok(())
+}
+
+/// Spawns all registered chains, called by the controller after I/O is launched
+///
+/// # Panics
+///
+/// Will panic if a chain step has been not spawned. All chains are checked before any of them
+/// is spawned
+pub(crate) fn spawn_all() {
+    let chains = std::mem::take(&mut *CHAINS.lock());
+    let missing: Vec<String> = chains
+        .iter()
+        .flat_map(|(name, entry)| {
+            entry
+                .chain
+                .steps
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !entry.steps.contains_key(i))
+                .map(move |(_, (kind, step_name))| format!("chain {name}: {kind} {step_name}"))
+        })
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "chain steps not spawned before the PLC start: {}",
+        missing.join(", ")
+    );
+    for (_, mut entry) in chains {
+        let mut steps = Vec::with_capacity(entry.chain.steps.len());
+        for (i, (kind, step_name)) in entry.chain.steps.iter().enumerate() {
+            steps.push(Step {
+                kind: *kind,
+                f: entry.steps.remove(&i).unwrap(),
+                guard: tasks::Guard::step(*kind, step_name),
+            });
+        }
+        spawn_chain(entry.chain, steps);
+    }
+}
+
+fn spawn_chain(chain: &'static Chain, mut steps: Vec<Step>) {
+    let has_inputs = steps.iter().any(|s| s.kind == Kind::Input);
+    let has_programs = steps.iter().any(|s| s.kind == Kind::Program);
+    let has_outputs = steps.iter().any(|s| s.kind == Kind::Output);
+    if has_inputs || has_programs {
+        *CHAINS_RUNNING.lock() += 1;
+    }
+    tasks::register_chain_thread(chain.name, has_inputs, has_programs, has_outputs);
+    info!(
+        "chain {}: {}",
+        chain.name,
+        chain
+            .steps
+            .iter()
+            .map(|(kind, name)| format!("{kind} {name}"))
+            .collect::<Vec<String>>()
+            .join(" -> ")
+    );
+    tasks::spawn(chain.name, Kind::Chain, move || {
+        if has_inputs {
+            tasks::wait_can_run_input();
+        } else if has_programs {
+            tasks::wait_can_run_program();
+        } else {
+            tasks::wait_can_run_output();
+        }
+        let mut stop_reported = !(has_inputs || has_programs);
+        // a panic outside of the step guards stops the chain
+        if panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            run_loop(chain, &mut steps, &mut stop_reported);
+        }))
+        .is_err()
+        {
+            tasks::fault_task(None);
+            if !stop_reported {
+                report_stopped();
+            }
+        }
+        if has_outputs {
+            tasks::mark_output_thread_stopped();
+        }
+        debug!("chain {} finished", tasks::thread_name());
+    });
+}
+
+#[allow(clippy::cognitive_complexity)]
+fn run_loop(chain: &Chain, steps: &mut [Step], stop_reported: &mut bool) {
+    let has_inputs = steps.iter().any(|s| s.kind == Kind::Input);
+    let has_programs = steps.iter().any(|s| s.kind == Kind::Program);
+    let has_outputs = steps.iter().any(|s| s.kind == Kind::Output);
+    let mut int = Loop::prepare_reported(chain.interval, chain.shift);
+    let mut inputs_marked = false;
+    let mut programs_marked = false;
+    loop {
+        let status = tasks::status();
+        let stopping = status <= Status::Stopping;
+        let programs_enabled = tasks::programs_enabled();
+        let outputs_enabled = tasks::can_run_outputs();
+        let last_sync = status <= Status::StopSyncing;
+        let paused = int.is_paused();
+        for step in steps.iter_mut() {
+            match step.kind {
+                Kind::Input if !stopping && !paused => {
+                    step.guard.run(&mut step.f);
+                    crate::force::apply_after_input();
+                }
+                Kind::Program if !stopping && !paused && programs_enabled => {
+                    crate::force::apply();
+                    step.guard.run(&mut step.f);
+                }
+                Kind::Output if outputs_enabled && (!paused || last_sync) => {
+                    crate::force::apply();
+                    step.guard.run(&mut step.f);
+                }
+                _ => {}
+            }
+        }
+        if stopping {
+            if !*stop_reported {
+                report_stopped();
+                *stop_reported = true;
+            }
+            if last_sync || !has_outputs {
+                break;
+            }
+        } else {
+            if has_inputs && !inputs_marked {
+                tasks::mark_thread_ready(Kind::Input);
+                inputs_marked = true;
+            }
+            if has_programs && programs_enabled && !programs_marked {
+                tasks::mark_thread_ready(Kind::Program);
+                programs_marked = true;
+            }
+        }
+        int.tick();
+    }
+}
+
+fn report_stopped() {
+    let mut running = CHAINS_RUNNING.lock();
+    *running -= 1;
+    debug!("chain {} inputs and programs stopped", tasks::thread_name());
+    CHAINS_STOPPED.notify_all();
+}
+
+/// Waits until all chains have stopped their inputs and programs
+pub(crate) fn wait_stopped() {
+    let mut running = CHAINS_RUNNING.lock();
+    while *running > 0 {
+        CHAINS_STOPPED.wait(&mut running);
+    }
+}
@@ -1,8 +1,12 @@
 use clap::Parser;
 use colored::Colorize;
-use eva_common::EResult;
+use eva_common::value::Value;
+use eva_common::{EResult, ErrorKind};
 use prettytable::Row;
+use rplc::alarm::Severity;
+use rplc::api::ContextValue;
 use rplc::tasks::{Affinity, Status};
+use rplc::trend::csv_escape;
 use rplc::{client, eapi};
 use std::collections::BTreeMap;
 use std::path::Path;
@@ -41,10 +45,38 @@ enum Command {
     Test(PlcParams),
     #[clap(about = "PLC info")]
     Info(PlcParams),
+    #[clap(about = "PLC I/O link status")]
+    Io(PlcParams),
     #[clap(about = "PLC task (thread) stats")]
-    Stat(PlcParams),
+    Stat(PlcStatParams),
     #[clap(about = "reset PLC task (thread) stats")]
     Reset(PlcParams),
+    #[clap(about = "pause PLC task")]
+    Pause(PlcTaskParams),
+    #[clap(about = "resume paused PLC task")]
+    Resume(PlcTaskParams),
+    #[clap(about = "change PLC task loop interval")]
+    SetInterval(PlcTaskIntervalParams),
+    #[clap(about = "get PLC context field values")]
+    Get(PlcGetParams),
+    #[clap(about = "set PLC context field value")]
+    Set(PlcSetParams),
+    #[clap(about = "watch PLC context field values")]
+    Watch(PlcWatchParams),
+    #[clap(about = "force PLC context field value")]
+    Force(PlcForceParams),
+    #[clap(about = "clear PLC context field force (all forces if no path specified)")]
+    Unforce(PlcUnforceParams),
+    #[clap(about = "list forced PLC context fields")]
+    Forces(PlcParams),
+    #[clap(about = "list PLC alarms")]
+    Alarms(PlcAlarmsParams),
+    #[clap(about = "acknowledge PLC alarm (all active alarms if no id specified)")]
+    Ack(PlcAckParams),
+    #[clap(about = "list PLC trends or get recorded trend samples")]
+    Trend(PlcTrendParams),
+    #[clap(about = "PLC sequential function chart steps")]
+    Sfc(PlcParams),
     #[clap(about = "register PLC binary in systemd")]
     Register(PlcRegisterParams),
     #[clap(about = "unregister PLC binary from systemd (stop if running)")]
@@ -62,7 +94,7 @@ enum Command {
 #[derive(Parser)]
 struct PlcRegisterParams {
     plc_file_path: String,
-    #[clap(short = 'a', help = "thread affinity: NAME=CPU,PRIORITY")]
+    #[clap(short = 'a', help = "thread affinity: NAME=CPU,PRIORITY[,POLICY]")]
     thread_affinity: Vec<String>,
     #[clap(
         short = 'e',
@@ -86,6 +118,8 @@ impl StatusColored for Status {
     fn as_colored_string(&self) -> colored::ColoredString {
         if *self == Status::Active {
             self.to_string().green()
+        } else if *self == Status::Fault {
+            self.to_string().red()
         } else if *self <= Status::Stopping {
             self.to_string().yellow()
         } else {
@@ -105,6 +139,101 @@ struct PlcParams {
     name: String,
 }
 
+#[derive(Parser)]
+struct PlcTaskParams {
+    name: String,
+    task: String,
+}
+
+#[derive(Parser)]
+struct PlcTaskIntervalParams {
+    name: String,
+    task: String,
+    #[clap(help = "interval, e.g. 100ms")]
+    interval: String,
+}
+
+#[derive(Parser)]
+struct PlcGetParams {
+    name: String,
+    #[clap(help = "context field paths or prefixes, e.g. data.counter, connector[2]")]
+    path: Vec<String>,
+}
+
+#[derive(Parser)]
+struct PlcSetParams {
+    name: String,
+    #[clap(help = "context field path, e.g. data.counter")]
+    path: String,
+    #[clap(allow_hyphen_values = true)]
+    value: String,
+}
+
+#[derive(Parser)]
+struct PlcWatchParams {
+    name: String,
+    #[clap(required = true, help = "context field paths or prefixes")]
+    path: Vec<String>,
+    #[clap(short = 'i', long = "interval", default_value = "500ms")]
+    interval: String,
+}
+
+#[derive(Parser)]
+struct PlcForceParams {
+    name: String,
+    #[clap(help = "context field path, e.g. data.counter")]
+    path: String,
+    #[clap(allow_hyphen_values = true)]
+    value: String,
+    #[clap(
+        short = 'p',
+        long = "persistent",
+        help = "keep the force after PLC restart"
+    )]
+    persistent: bool,
+}
+
+#[derive(Parser)]
+struct PlcUnforceParams {
+    name: String,
+    path: Option<String>,
+}
+
+#[derive(Parser)]
+struct PlcAlarmsParams {
+    name: String,
+    #[clap(short = 'a', long = "active", help = "active alarms only")]
+    active: bool,
+}
+
+#[derive(Parser)]
+struct PlcAckParams {
+    name: String,
+    id: Option<String>,
+}
+
+#[derive(Parser)]
+struct PlcTrendParams {
+    name: String,
+    #[clap(help = "trend id, list trends if not specified")]
+    id: Option<String>,
+    #[clap(short = 'n', long = "limit", help = "last N samples only")]
+    limit: Option<usize>,
+    #[clap(long = "csv", help = "output samples as CSV")]
+    csv: bool,
+}
+
+#[derive(Parser)]
+struct PlcStatParams {
+    name: String,
+    #[clap(
+        short = 'g',
+        long = "histogram",
+        help = "jitter and execution time percentiles"
+    )]
+    histogram: bool,
+}
+
 fn ctable(titles: &[&str]) -> prettytable::Table {
     let mut table = prettytable::Table::new();
     let format = prettytable::format::FormatBuilder::new()
@@ -166,10 +295,52 @@ async fn handle_list(p: ListParams, var_dir: &Path) -> EResult<()> {
     Ok(())
 }
 
-async fn handle_stat(p: PlcParams, var_dir: &Path) -> EResult<()> {
+fn jitter_colored(jitter: u32) -> colored::ColoredString {
+    if jitter < 150 {
+        jitter.to_string().normal()
+    } else if jitter < 250 {
+        jitter.to_string().yellow()
+    } else {
+        jitter.to_string().red()
+    }
+}
+
+async fn handle_stat_histogram(p: PlcStatParams, var_dir: &Path) -> EResult<()> {
+    let histograms = client::stat_histogram(&p.name, var_dir).await?;
+    let mut table = ctable(&[
+        "task", "iters", "j50", "j90", "j99", "j99.9", "jmax", "e50", "e90", "e99", "e99.9", "emax",
+    ]);
+    for (name, h) in histograms {
+        if h.jitter.count == 0 {
+            continue;
+        }
+        table.add_row(row![
+            name,
+            h.jitter.count,
+            h.jitter.p50,
+            h.jitter.p90,
+            jitter_colored(h.jitter.p99),
+            jitter_colored(h.jitter.p999),
+            jitter_colored(h.jitter.max),
+            h.exec.p50,
+            h.exec.p90,
+            h.exec.p99,
+            h.exec.p999,
+            h.exec.max,
+        ]);
+    }
+    table.printstd();
+    Ok(())
+}
+
+async fn handle_stat(p: PlcStatParams, var_dir: &Path) -> EResult<()> {
+    if p.histogram {
+        return handle_stat_histogram(p, var_dir).await;
+    }
     let tasks = client::stat_extended(&p.name, var_dir).await?;
     let mut table = ctable(&[
-        "task", "spid", "cpu", "rt", "iters", "jmin", "jmax", "jlast", "javg",
+        "task", "spid", "cpu", "rt", "int", "iters", "jmin", "jmax", "jlast", "javg", "emin",
+        "emax", "elast", "eavg", "ovr", "pan",
     ]);
     for task in tasks {
         let mut cols = vec![
@@ -184,17 +355,34 @@ async fn handle_stat(p: PlcParams, var_dir: &Path) -> EResult<()> {
         ];
         if let Some(t) = task.thread_info {
             let cols_t = vec![
-                cell!(t.iters),
-                cell!(t.jitter_min),
-                cell!(if t.jitter_max < 150 {
-                    t.jitter_max.to_string().normal()
-                } else if t.jitter_max < 250 {
-                    t.jitter_max.to_string().yellow()
+                cell!(if t.paused {
+                    "PAUSED".yellow()
+                } else if t.interval > 0 {
+                    t.interval.to_string().normal()
                 } else {
-                    t.jitter_max.to_string().red()
+                    String::new().normal()
                 }),
+                cell!(t.iters),
+                cell!(t.jitter_min),
+                cell!(jitter_colored(t.jitter_max)),
                 cell!(t.jitter_last),
                 cell!(t.jitter_avg),
+                cell!(t.exec_min),
+                cell!(t.exec_max),
+                cell!(t.exec_last),
+                cell!(t.exec_avg),
+                cell!(if t.overruns > 0 {
+                    t.overruns.to_string().red()
+                } else {
+                    t.overruns.to_string().normal()
+                }),
+                cell!(if t.faulted {
+                    "FAULT".red().bold()
+                } else if t.panics > 0 {
+                    t.panics.to_string().red()
+                } else {
+                    t.panics.to_string().normal()
+                }),
             ];
             cols.extend(cols_t);
         }
@@ -217,10 +405,281 @@ async fn handle_info(p: PlcParams, var_dir: &Path) -> EResult<()> {
     table.add_row(row!["pid", result.pid]);
     table.add_row(row!["system_name", result.system_name]);
     table.add_row(row!["uptime", result.uptime.trunc()]);
+    if result.degraded {
+        table.add_row(row!["io", "DEGRADED".red().bold()]);
+    }
+    if result.forces > 0 {
+        table.add_row(row![
+            "forces",
+            format!("{} ACTIVE", result.forces).red().bold()
+        ]);
+    }
+    if result.alarms > 0 {
+        table.add_row(row![
+            "alarms",
+            format!("{} ACTIVE", result.alarms).red().bold()
+        ]);
+    }
+    table.printstd();
+    Ok(())
+}
+
+async fn handle_io(p: PlcParams, var_dir: &Path) -> EResult<()> {
+    let result = client::io_status(&p.name, var_dir).await?;
+    let mut table = ctable(&["io", "status", "errors", "last_success", "last_error"]);
+    for (id, s) in result {
+        table.add_row(row![
+            id,
+            if s.online {
+                "online".green()
+            } else if s.errors > 0 {
+                "offline".red()
+            } else {
+                "unknown".normal()
+            },
+            s.errors,
+            s.last_success
+                .map(|v| format!("{:.3}s ago", v))
+                .unwrap_or_default(),
+            s.last_error.unwrap_or_default()
+        ]);
+    }
+    table.printstd();
+    Ok(())
+}
+
+async fn handle_get(p: PlcGetParams, var_dir: &Path) -> EResult<()> {
+    let values = match client::context_get_many(&p.name, var_dir, &p.path).await {
+        Ok(v) => v,
+        Err(e) if e.kind() == ErrorKind::MethodNotImplemented => {
+            // PLCs built with older rplc versions have no context.get_many
+            get_values_compat(&p, var_dir).await?
+        }
+        Err(e) => return Err(e),
+    };
+    let mut table = ctable(&["path", "value", "unit"]);
+    for v in values {
+        table.add_row(row![
+            v.path,
+            v.value.to_string().cyan(),
+            v.unit.unwrap_or_default()
+        ]);
+    }
+    table.printstd();
+    Ok(())
+}
+
+async fn get_values_compat(p: &PlcGetParams, var_dir: &Path) -> EResult<Vec<ContextValue>> {
+    let mut paths = Vec::new();
+    if p.path.is_empty() {
+        paths = client::context_list(&p.name, var_dir, None).await?;
+    } else {
+        for prefix in &p.path {
+            let mut listed = client::context_list(&p.name, var_dir, Some(prefix)).await?;
+            if listed.is_empty() {
+                // not a listed field prefix, let the PLC report an error if the path is invalid
+                listed.push(prefix.clone());
+            }
+            paths.extend(listed);
+        }
+    }
+    let mut values = Vec::with_capacity(paths.len());
+    for path in paths {
+        let value = client::context_get(&p.name, var_dir, &path).await?;
+        values.push(ContextValue {
+            path,
+            value,
+            unit: None,
+        });
+    }
+    Ok(values)
+}
+
+async fn handle_watch(p: PlcWatchParams, var_dir: &Path) -> EResult<()> {
+    let mut watcher = client::context_watch(&p.name, var_dir, &p.path, &p.interval).await?;
+    // the first frame contains all the watched fields
+    let mut values: Vec<(String, Value, bool)> = Vec::new();
+    loop {
+        let frame = watcher.next().await?;
+        if values.is_empty() {
+            values = frame
+                .changes
+                .into_iter()
+                .map(|(path, value)| (path, value, false))
+                .collect();
+        } else {
+            if frame.changes.is_empty() && values.iter().all(|v| !v.2) {
+                continue;
+            }
+            let mut changes = frame.changes;
+            for (path, value, changed) in &mut values {
+                if let Some(v) = changes.remove(path) {
+                    *value = v;
+                    *changed = true;
+                } else {
+                    *changed = false;
+                }
+            }
+        }
+        let mut table = ctable(&["path", "value"]);
+        for (path, value, changed) in &values {
+            table.add_row(row![
+                path,
+                if *changed {
+                    value.to_string().yellow().bold()
+                } else {
+                    value.to_string().normal()
+                }
+            ]);
+        }
+        print!("\x1b[2J\x1b[H");
+        println!(
+            "{} {} (every {})",
+            p.name.bold(),
+            "watch".blue(),
+            p.interval
+        );
+        table.printstd();
+    }
+}
+
+async fn handle_forces(p: PlcParams, var_dir: &Path) -> EResult<()> {
+    let forces = client::force_list(&p.name, var_dir).await?;
+    let mut table = ctable(&["path", "value", "persistent"]);
+    for f in forces {
+        table.add_row(row![
+            f.path,
+            f.value.to_string().red(),
+            if f.persistent { "yes" } else { "" }
+        ]);
+    }
+    table.printstd();
+    Ok(())
+}
+
+fn format_ago(t: Option<f64>) -> String {
+    t.map(|v| {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        format!("{:.3}s ago", now - v)
+    })
+    .unwrap_or_default()
+}
+
+async fn handle_alarms(p: PlcAlarmsParams, var_dir: &Path) -> EResult<()> {
+    let alarms = client::alarm_list(&p.name, var_dir).await?;
+    let mut table = ctable(&[
+        "id",
+        "severity",
+        "state",
+        "condition",
+        "activated",
+        "cleared",
+        "description",
+    ]);
+    for a in alarms {
+        if p.active && !a.active {
+            continue;
+        }
+        table.add_row(row![
+            a.id,
+            match a.severity {
+                Severity::Info => a.severity.to_string().normal(),
+                Severity::Warning => a.severity.to_string().yellow(),
+                Severity::Critical => a.severity.to_string().red(),
+            },
+            if a.active && !a.acked {
+                "ACTIVE".red().bold()
+            } else if a.active {
+                "ACKED".yellow()
+            } else {
+                "normal".normal()
+            },
+            if a.condition_active {
+                a.condition.red()
+            } else {
+                a.condition.normal()
+            },
+            format_ago(a.activated),
+            format_ago(a.cleared),
+            a.description
+        ]);
+    }
     table.printstd();
     Ok(())
 }
 
+async fn handle_sfc(p: PlcParams, var_dir: &Path) -> EResult<()> {
+    let charts = client::sfc_list(&p.name, var_dir).await?;
+    let mut table = ctable(&["sfc", "step", "", "t", "last duration"]);
+    for chart in charts {
+        for step in chart.steps {
+            table.add_row(row![
+                chart.id,
+                if step.active {
+                    step.name.green().bold()
+                } else {
+                    step.name.normal()
+                },
+                if step.active { "*" } else { "" },
+                if step.active {
+                    format!("{:.3}", chart.t)
+                } else {
+                    String::new()
+                },
+                step.last_duration
+                    .map(|d| format!("{:.3}", d))
+                    .unwrap_or_default()
+            ]);
+        }
+    }
+    table.printstd();
+    Ok(())
+}
+
+async fn handle_trend(p: PlcTrendParams, var_dir: &Path) -> EResult<()> {
+    if let Some(id) = p.id {
+        let data = client::trend_get(&p.name, var_dir, &id, None, p.limit).await?;
+        if p.csv {
+            let paths: Vec<String> = data.paths.iter().map(|p| csv_escape(p)).collect();
+            println!("t,{}", paths.join(","));
+            for sample in data.samples {
+                let values: Vec<String> = sample
+                    .values
+                    .iter()
+                    .map(|v| csv_escape(&v.to_string()))
+                    .collect();
+                println!("{:.6},{}", sample.t, values.join(","));
+            }
+        } else {
+            let mut titles = vec!["t"];
+            titles.extend(data.paths.iter().map(String::as_str));
+            let mut table = ctable(&titles);
+            for sample in data.samples {
+                let mut cols = vec![cell!(format!("{:.3}", sample.t))];
+                cols.extend(sample.values.iter().map(|v| cell!(v.to_string().cyan())));
+                table.add_row(Row::new(cols));
+            }
+            table.printstd();
+        }
+    } else {
+        let trends = client::trend_list(&p.name, var_dir).await?;
+        let mut table = ctable(&["id", "interval", "samples", "paths"]);
+        for t in trends {
+            table.add_row(row![
+                t.id,
+                format!("{}s", t.interval),
+                format!("{}/{}", t.samples, t.size),
+                t.paths.join(", ")
+            ]);
+        }
+        table.printstd();
+    }
+    Ok(())
+}
+
 async fn handle_start(name: &str) -> EResult<()> {
     client::start(name).await?;
     println!("{} has been started", name);
@@ -246,6 +705,9 @@ async fn main() -> EResult<()> {
         Command::Info(p) => {
             handle_info(p, &var_dir).await?;
         }
+        Command::Io(p) => {
+            handle_io(p, &var_dir).await?;
+        }
         Command::Stat(p) => {
             handle_stat(p, &var_dir).await?;
         }
@@ -253,6 +715,65 @@ async fn main() -> EResult<()> {
             client::reset_stat(&p.name, &var_dir).await?;
             println!("{} stats have been reset", p.name);
         }
+        Command::Pause(p) => {
+            client::pause_task(&p.name, &var_dir, &p.task).await?;
+            println!("{} task {} has been paused", p.name, p.task);
+        }
+        Command::Resume(p) => {
+            client::resume_task(&p.name, &var_dir, &p.task).await?;
+            println!("{} task {} has been resumed", p.name, p.task);
+        }
+        Command::SetInterval(p) => {
+            client::set_task_interval(&p.name, &var_dir, &p.task, &p.interval).await?;
+            println!(
+                "{} task {} interval has been set to {}",
+                p.name, p.task, p.interval
+            );
+        }
+        Command::Get(p) => {
+            handle_get(p, &var_dir).await?;
+        }
+        Command::Set(p) => {
+            let value: Value = p.value.parse().unwrap();
+            client::context_set(&p.name, &var_dir, &p.path, value).await?;
+            println!("{}: {} has been set to {}", p.name, p.path, p.value);
+        }
+        Command::Watch(p) => {
+            handle_watch(p, &var_dir).await?;
+        }
+        Command::Force(p) => {
+            let value: Value = p.value.parse().unwrap();
+            client::force_set(&p.name, &var_dir, &p.path, value, p.persistent).await?;
+            println!("{}: {} has been forced to {}", p.name, p.path, p.value);
+        }
+        Command::Unforce(p) => {
+            client::force_clear(&p.name, &var_dir, p.path.as_deref()).await?;
+            if let Some(path) = p.path {
+                println!("{}: {} force has been cleared", p.name, path);
+            } else {
+                println!("{}: all forces have been cleared", p.name);
+            }
+        }
+        Command::Forces(p) => {
+            handle_forces(p, &var_dir).await?;
+        }
+        Command::Alarms(p) => {
+            handle_alarms(p, &var_dir).await?;
+        }
+        Command::Ack(p) => {
+            client::alarm_ack(&p.name, &var_dir, p.id.as_deref()).await?;
+            if let Some(id) = p.id {
+                println!("{}: alarm {} has been acknowledged", p.name, id);
+            } else {
+                println!("{}: all active alarms have been acknowledged", p.name);
+            }
+        }
+        Command::Trend(p) => {
+            handle_trend(p, &var_dir).await?;
+        }
+        Command::Sfc(p) => {
+            handle_sfc(p, &var_dir).await?;
+        }
         Command::Register(p) => {
             let aff: BTreeMap<String, Affinity> = p
                 .thread_affinity
@@ -1,8 +1,15 @@
-use crate::tasks::{Affinity, ThreadInfo};
+use crate::alarm::AlarmInfo;
+use crate::context::FieldInfo;
+use crate::force::ForceInfo;
+use crate::io::IoStatusInfo;
+use crate::sfc::SfcInfo;
+use crate::tasks::{Affinity, ThreadHistogram, ThreadInfo};
+use crate::trend::{TrendData, TrendInfo};
 use crate::{api, eapi, PlcInfo};
 use bmart_derive::{EnumStr, Sorting};
 use eva_common::payload::{pack, unpack};
 use eva_common::prelude::Value;
+use eva_common::value::to_value;
 use eva_common::{EResult, Error};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
@@ -108,6 +115,22 @@ pub async fn stat_extended(name: &str, var_dir: &Path) -> EResult<Vec<ThreadInfo
     Ok(tasks)
 }
 
+pub async fn stat_histogram(
+    name: &str,
+    var_dir: &Path,
+) -> EResult<BTreeMap<String, ThreadHistogram>> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let result: BTreeMap<String, ThreadHistogram> =
+        api_call(&socket_path, "thread_stats.histogram", None).await?;
+    Ok(result)
+}
+
+pub async fn io_status(name: &str, var_dir: &Path) -> EResult<BTreeMap<String, IoStatusInfo>> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let result: BTreeMap<String, IoStatusInfo> = api_call(&socket_path, "io.status", None).await?;
+    Ok(result)
+}
+
 pub async fn info(name: &str, var_dir: &Path) -> EResult<PlcInfo> {
     let socket_path = plc_socket_path(var_dir, name)?;
     let result: PlcInfo = api_call(&socket_path, "info", None).await?;
@@ -120,6 +143,166 @@ pub async fn reset_stat(name: &str, var_dir: &Path) -> EResult<()> {
     Ok(())
 }
 
+pub async fn pause_task(name: &str, var_dir: &Path, task: &str) -> EResult<()> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let params = to_value(api::TaskParams {
+        name: task.to_owned(),
+    })?;
+    api_call::<()>(&socket_path, "task.pause", Some(params)).await?;
+    Ok(())
+}
+
+pub async fn resume_task(name: &str, var_dir: &Path, task: &str) -> EResult<()> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let params = to_value(api::TaskParams {
+        name: task.to_owned(),
+    })?;
+    api_call::<()>(&socket_path, "task.resume", Some(params)).await?;
+    Ok(())
+}
+
+pub async fn set_task_interval(
+    name: &str,
+    var_dir: &Path,
+    task: &str,
+    interval: &str,
+) -> EResult<()> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let params = to_value(api::TaskIntervalParams {
+        name: task.to_owned(),
+        interval: interval.to_owned(),
+    })?;
+    api_call::<()>(&socket_path, "task.set_interval", Some(params)).await?;
+    Ok(())
+}
+
+pub async fn context_get(name: &str, var_dir: &Path, path: &str) -> EResult<Value> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let params = to_value(api::ContextPathParams {
+        path: path.to_owned(),
+    })?;
+    api_call(&socket_path, "context.get", Some(params)).await
+}
+
+/// Gets values and units of the context fields and field prefixes with a single API call, the
+/// values are consistent. All fields are returned if no paths are specified
+pub async fn context_get_many(
+    name: &str,
+    var_dir: &Path,
+    paths: &[String],
+) -> EResult<Vec<api::ContextValue>> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let params = to_value(api::ContextPathsParams {
+        paths: paths.to_vec(),
+    })?;
+    api_call(&socket_path, "context.get_many", Some(params)).await
+}
+
+pub async fn context_set(name: &str, var_dir: &Path, path: &str, value: Value) -> EResult<()> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let params = to_value(api::ContextSetParams {
+        path: path.to_owned(),
+        value,
+    })?;
+    api_call::<()>(&socket_path, "context.set", Some(params)).await?;
+    Ok(())
+}
+
+pub async fn context_list(
+    name: &str,
+    var_dir: &Path,
+    prefix: Option<&str>,
+) -> EResult<Vec<String>> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let params = to_value(api::ContextListParams {
+        prefix: prefix.map(ToOwned::to_owned),
+    })?;
+    api_call(&socket_path, "context.list", Some(params)).await
+}
+
+pub async fn context_meta(
+    name: &str,
+    var_dir: &Path,
+    prefix: Option<&str>,
+) -> EResult<Vec<FieldInfo>> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let params = to_value(api::ContextListParams {
+        prefix: prefix.map(ToOwned::to_owned),
+    })?;
+    api_call(&socket_path, "context.meta", Some(params)).await
+}
+
+pub async fn force_set(
+    name: &str,
+    var_dir: &Path,
+    path: &str,
+    value: Value,
+    persistent: bool,
+) -> EResult<()> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let params = to_value(api::ForceSetParams {
+        path: path.to_owned(),
+        value,
+        persistent,
+    })?;
+    api_call::<()>(&socket_path, "force.set", Some(params)).await?;
+    Ok(())
+}
+
+pub async fn force_clear(name: &str, var_dir: &Path, path: Option<&str>) -> EResult<()> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let params = to_value(api::ForceClearParams {
+        path: path.map(ToOwned::to_owned),
+    })?;
+    api_call::<()>(&socket_path, "force.clear", Some(params)).await?;
+    Ok(())
+}
+
+pub async fn force_list(name: &str, var_dir: &Path) -> EResult<Vec<ForceInfo>> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    api_call(&socket_path, "force.list", None).await
+}
+
+pub async fn alarm_list(name: &str, var_dir: &Path) -> EResult<Vec<AlarmInfo>> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    api_call(&socket_path, "alarm.list", None).await
+}
+
+pub async fn alarm_ack(name: &str, var_dir: &Path, id: Option<&str>) -> EResult<()> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let params = to_value(api::AlarmAckParams {
+        id: id.map(ToOwned::to_owned),
+    })?;
+    api_call::<()>(&socket_path, "alarm.ack", Some(params)).await?;
+    Ok(())
+}
+
+pub async fn sfc_list(name: &str, var_dir: &Path) -> EResult<Vec<SfcInfo>> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    api_call(&socket_path, "sfc.list", None).await
+}
+
+pub async fn trend_list(name: &str, var_dir: &Path) -> EResult<Vec<TrendInfo>> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    api_call(&socket_path, "trend.list", None).await
+}
+
+pub async fn trend_get(
+    name: &str,
+    var_dir: &Path,
+    id: &str,
+    since: Option<f64>,
+    limit: Option<usize>,
+) -> EResult<TrendData> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let params = to_value(api::TrendGetParams {
+        id: id.to_owned(),
+        since,
+        limit,
+    })?;
+    api_call(&socket_path, "trend.get", Some(params)).await
+}
+
 pub async fn test(name: &str, var_dir: &Path) -> EResult<()> {
     let socket_path = plc_socket_path(var_dir, name)?;
     api_call::<()>(&socket_path, "test", None).await?;
@@ -215,14 +398,30 @@ async fn _api_call<R>(socket_path: &Path, method: &str, params: Option<Value>) -
 where
     R: DeserializeOwned,
 {
-    let req = api::Request::new(method, params);
     let mut socket = UnixStream::connect(socket_path).await?;
+    write_request(&mut socket, method, params).await?;
+    read_response(&mut socket).await
+}
+
+async fn write_request(
+    socket: &mut UnixStream,
+    method: &str,
+    params: Option<Value>,
+) -> EResult<()> {
+    let req = api::Request::new(method, params);
     let packed = pack(&req)?;
     let mut buf = Vec::with_capacity(packed.len() + 5);
     buf.push(0);
     buf.extend(u32::try_from(packed.len())?.to_le_bytes());
     buf.extend(packed);
     socket.write_all(&buf).await?;
+    Ok(())
+}
+
+async fn read_response<R>(socket: &mut UnixStream) -> EResult<R>
+where
+    R: DeserializeOwned,
+{
     let mut buf: [u8; 5] = [0; 5];
     socket.read_exact(&mut buf).await?;
     if buf[0] != 0 {
@@ -235,6 +434,46 @@ where
     Ok(R::deserialize(response.result.unwrap_or_default())?)
 }
 
+/// Context watch stream, see [`context_watch`]
+pub struct Watcher {
+    socket: UnixStream,
+    timeout: Duration,
+}
+
+impl Watcher {
+    /// Waits for the next frame. The first frame contains all watched fields, the following ones
+    /// changed fields only
+    pub async fn next(&mut self) -> EResult<api::WatchFrame> {
+        tokio::time::timeout(self.timeout, read_response(&mut self.socket))
+            .await
+            .map_err(|_| Error::timeout())?
+    }
+}
+
+/// Subscribes to context field changes, sampled by the PLC with the given interval
+pub async fn context_watch(
+    name: &str,
+    var_dir: &Path,
+    paths: &[String],
+    interval: &str,
+) -> EResult<Watcher> {
+    let socket_path = plc_socket_path(var_dir, name)?;
+    let params = to_value(api::WatchParams {
+        paths: paths.to_vec(),
+        interval: interval.to_owned(),
+    })?;
+    let mut socket = tokio::time::timeout(TIMEOUT, UnixStream::connect(socket_path))
+        .await
+        .map_err(|_| Error::timeout())??;
+    write_request(&mut socket, "context.watch", Some(params)).await?;
+    let interval = Duration::from_nanos(crate::interval::parse_interval(interval)?);
+    Ok(Watcher {
+        socket,
+        // heartbeats are sent every second
+        timeout: interval.max(Duration::from_secs(1)) + TIMEOUT,
+    })
+}
+
 fn systemd_plc_and_service_name_from_path(plc: &Path) -> EResult<(String, String)> {
     let name = plc
         .file_name()
@@ -306,10 +545,9 @@ pub async fn register(
     }
     for (task_name, aff) in affinities {
         env.push(format!(
-            "PLC_THREAD_AFFINITY_{}={},{}",
+            "PLC_THREAD_AFFINITY_{}={}",
             task_name.replace('.', "__"),
-            aff.cpu_id,
-            aff.sched_priority
+            aff
         ));
     }
     ctx.insert("env", &env);
@@ -0,0 +1,37 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    static VIRTUAL_NOW: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Returns the current monotonic time
+///
+/// Programs and function blocks should use this function instead of `Instant::now()`, so their
+/// timers follow the virtual clock when driven by [`crate::testing::Harness`]
+#[inline]
+pub fn now() -> Instant {
+    VIRTUAL_NOW.with(Cell::get).unwrap_or_else(Instant::now)
+}
+
+/// Returns true if the virtual clock is active in the current thread
+#[inline]
+pub fn is_virtual() -> bool {
+    VIRTUAL_NOW.with(Cell::get).is_some()
+}
+
+pub(crate) fn set_virtual(t: Option<Instant>) {
+    VIRTUAL_NOW.with(|v| v.set(t));
+}
+
+/// Advances the virtual clock of the current thread
+///
+/// # Panics
+///
+/// Will panic if the virtual clock is not active
+pub fn advance(d: Duration) {
+    VIRTUAL_NOW.with(|v| {
+        let t = v.get().expect("virtual clock is not active");
+        v.set(Some(t + d));
+    });
+}
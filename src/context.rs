@@ -0,0 +1,533 @@
+use eva_common::value::Value;
+use eva_common::{EResult, Error};
+use log::warn;
+use once_cell::sync::OnceCell;
+use parking_lot::{RwLock, RwLockWriteGuard};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+static CONTEXT: OnceCell<&'static RwLock<dyn PathAccess + Send + Sync>> = OnceCell::new();
+static META: OnceCell<BTreeMap<&'static str, &'static FieldMeta>> = OnceCell::new();
+
+/// Context field access by path, e.g. "data.subfield.temp_out" or "connector[2].voltage"
+///
+/// The builder generates implementations for the context structures. Fields of custom types
+/// (which are not known by the builder) are not accessible
+pub trait PathAccess {
+    /// Gets the value. The indexes are applied to arrays, the path to structures
+    fn get_value(&self, idx: &[usize], path: &str) -> EResult<Value>;
+    /// Sets the value. The indexes are applied to arrays, the path to structures
+    fn set_value(&mut self, idx: &[usize], path: &str, value: Value) -> EResult<()>;
+    /// Appends paths of all accessible fields to the result
+    fn list_paths(&self, prefix: &str, result: &mut Vec<String>);
+}
+
+/// use init_plc!() macro to register the context
+///
+/// # Panics
+///
+/// Will panic if called twice
+pub fn register(context: &'static RwLock<dyn PathAccess + Send + Sync>) {
+    assert!(
+        CONTEXT.set(context).is_ok(),
+        "context is already registered"
+    );
+}
+
+fn context() -> EResult<&'static RwLock<dyn PathAccess + Send + Sync>> {
+    CONTEXT
+        .get()
+        .copied()
+        .ok_or_else(|| Error::not_ready("context is not registered"))
+}
+
+/// Gets a context field value by path
+pub fn get(path: &str) -> EResult<Value> {
+    context()?.read().get_value(&[], path)
+}
+
+/// Gets multiple context field values at once, the context is locked only once
+pub fn get_many<'a, I>(paths: I) -> EResult<Vec<Value>>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let ctx = context()?.read();
+    paths
+        .into_iter()
+        .map(|path| ctx.get_value(&[], path))
+        .collect()
+}
+
+/// Sets a context field value by path, numeric values are clamped to the field range
+pub fn set(path: &str, value: Value) -> EResult<()> {
+    let value = clamp(path, value)?;
+    context()?.write().set_value(&[], path, value)
+}
+
+/// Locks the context for writing
+pub(crate) fn write() -> EResult<RwLockWriteGuard<'static, dyn PathAccess + Send + Sync>> {
+    Ok(context()?.write())
+}
+
+/// Sets multiple context field values in the locked context, numeric values are clamped
+pub(crate) fn set_many<'a, I>(ctx: &mut dyn PathAccess, values: I) -> EResult<()>
+where
+    I: IntoIterator<Item = (&'a str, Value)>,
+{
+    for (path, value) in values {
+        ctx.set_value(&[], path, clamp(path, value)?)?;
+    }
+    Ok(())
+}
+
+/// Lists paths of the accessible context fields, optionally starting with the prefix
+pub fn list(prefix: Option<&str>) -> EResult<Vec<String>> {
+    let mut result = Vec::new();
+    context()?.read().list_paths("", &mut result);
+    if let Some(p) = prefix {
+        result.retain(|v| {
+            v.strip_prefix(p)
+                .is_some_and(|s| s.is_empty() || s.starts_with(['.', '[']))
+        });
+    }
+    Ok(result)
+}
+
+/// Context field metadata, generated from the expanded field definitions of plc.yml
+pub struct FieldMeta {
+    /// field path without array indexes, e.g. "connector.voltage"
+    pub path: &'static str,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub unit: Option<&'static str>,
+    pub description: Option<&'static str>,
+}
+
+impl FieldMeta {
+    /// Clamps a numeric value to the field range, non-numeric values are returned as-is
+    pub fn clamp(&self, value: Value) -> EResult<Value> {
+        if self.min.is_none() && self.max.is_none() || !value.is_numeric() {
+            return Ok(value);
+        }
+        let n = f64::try_from(value.clone())?;
+        if n.is_nan() {
+            return Err(Error::invalid_params(format!("{}: NaN value", self.path)));
+        }
+        if let Some(min) = self.min.filter(|&min| n < min) {
+            warn!(
+                "{}: value {} is below the minimum, set to {}",
+                self.path, value, min
+            );
+            return Ok(bound_value(min));
+        }
+        if let Some(max) = self.max.filter(|&max| n > max) {
+            warn!(
+                "{}: value {} is above the maximum, set to {}",
+                self.path, value, max
+            );
+            return Ok(bound_value(max));
+        }
+        Ok(value)
+    }
+}
+
+/// Integral bounds are returned as integers to be accepted by integer fields
+#[allow(clippy::cast_possible_truncation)]
+fn bound_value(bound: f64) -> Value {
+    if bound.fract() != 0.0 || bound.abs() > 9_007_199_254_740_992.0 {
+        Value::F64(bound)
+    } else if bound < 0.0 {
+        Value::I64(bound as i64)
+    } else {
+        Value::U64(bound as u64)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FieldInfo {
+    pub path: String,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub unit: Option<String>,
+    pub description: Option<String>,
+}
+
+/// use init_plc!() macro to register the field metadata
+///
+/// # Panics
+///
+/// Will panic if called twice
+pub fn register_meta(meta: &'static [FieldMeta]) {
+    assert!(
+        META.set(meta.iter().map(|m| (m.path, m)).collect()).is_ok(),
+        "field metadata is already registered"
+    );
+}
+
+/// Gets metadata of a context field, array indexes in the path are ignored
+pub fn meta(path: &str) -> Option<&'static FieldMeta> {
+    META.get()?.get(strip_indexes(path).as_str()).copied()
+}
+
+/// Lists metadata of the context fields, optionally starting with the prefix
+pub fn list_meta(prefix: Option<&str>) -> Vec<FieldInfo> {
+    let prefix = prefix.map(strip_indexes);
+    META.get()
+        .map(|m| m.values().copied().collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|m| match prefix {
+            Some(ref p) => m
+                .path
+                .strip_prefix(p.as_str())
+                .is_some_and(|s| s.is_empty() || s.starts_with('.')),
+            None => true,
+        })
+        .map(|m| FieldInfo {
+            path: m.path.to_owned(),
+            min: m.min,
+            max: m.max,
+            unit: m.unit.map(ToOwned::to_owned),
+            description: m.description.map(ToOwned::to_owned),
+        })
+        .collect()
+}
+
+/// Clamps numeric values to the ranges of the fields, the value may be an array or a structure
+///
+/// Called by the context API and the generated EAPI action handlers. The Modbus server writes
+/// the registers of the context "modbus" field, not the mapped fields, so values written by
+/// Modbus clients are not clamped: programs which copy them into fields with ranges should call
+/// this function
+pub fn clamp(path: &str, value: Value) -> EResult<Value> {
+    match META.get() {
+        Some(meta) if !meta.is_empty() => clamp_value(meta, &strip_indexes(path), value),
+        _ => Ok(value),
+    }
+}
+
+fn clamp_value(
+    meta: &BTreeMap<&'static str, &'static FieldMeta>,
+    path: &str,
+    value: Value,
+) -> EResult<Value> {
+    match value {
+        Value::Seq(s) => Ok(Value::Seq(
+            s.into_iter()
+                .map(|v| clamp_value(meta, path, v))
+                .collect::<EResult<Vec<Value>>>()?,
+        )),
+        Value::Map(m) => {
+            let mut result = BTreeMap::new();
+            for (k, v) in m {
+                let v = if let Value::String(ref field) = k {
+                    clamp_value(meta, &join_path(path, field), v)?
+                } else {
+                    v
+                };
+                result.insert(k, v);
+            }
+            Ok(Value::Map(result))
+        }
+        v => {
+            if let Some(m) = meta.get(path) {
+                m.clamp(v)
+            } else {
+                Ok(v)
+            }
+        }
+    }
+}
+
+/// Removes array indexes from a path, e.g. "connector[2].voltage" becomes "connector.voltage"
+pub fn strip_indexes(path: &str) -> String {
+    let mut result = String::with_capacity(path.len());
+    let mut in_idx = false;
+    for c in path.chars() {
+        match c {
+            '[' => in_idx = true,
+            ']' => in_idx = false,
+            _ if !in_idx => result.push(c),
+            _ => {}
+        }
+    }
+    result
+}
+
+/// Splits a path into the first field name, its indexes and the rest of the path
+///
+/// Used by the generated code
+pub fn split_path(path: &str) -> EResult<(&str, Vec<usize>, &str)> {
+    let (segment, rest) = path.split_once('.').unwrap_or((path, ""));
+    let (name, mut idx_s) = segment
+        .split_once('[')
+        .map_or((segment, ""), |(n, i)| (n, i));
+    let mut idx = Vec::new();
+    while !idx_s.is_empty() {
+        let (i, r) = idx_s
+            .split_once(']')
+            .ok_or_else(|| Error::invalid_params(format!("invalid path: {}", path)))?;
+        idx.push(
+            i.trim()
+                .parse()
+                .map_err(|_| Error::invalid_params(format!("invalid index: {}", path)))?,
+        );
+        idx_s = r.strip_prefix('[').unwrap_or(r);
+        if !idx_s.is_empty() && !r.starts_with('[') {
+            return Err(Error::invalid_params(format!("invalid path: {}", path)));
+        }
+    }
+    if name.is_empty() {
+        return Err(Error::invalid_params(format!("invalid path: {}", path)));
+    }
+    Ok((name, idx, rest))
+}
+
+/// Checks that no array indexes are applied to a structure
+///
+/// Used by the generated code
+#[inline]
+pub fn check_struct_path(idx: &[usize]) -> EResult<()> {
+    if idx.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::not_found("no such index, not an array"))
+    }
+}
+
+/// Used by the generated code
+#[inline]
+pub fn join_path(prefix: &str, field: &str) -> String {
+    if prefix.is_empty() {
+        field.to_owned()
+    } else {
+        format!("{}.{}", prefix, field)
+    }
+}
+
+/// Checks that the path points to a value (no indexes and sub-fields left)
+#[inline]
+pub fn check_value_path(idx: &[usize], path: &str) -> EResult<()> {
+    if idx.is_empty() && path.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::not_found("no such field or index"))
+    }
+}
+
+macro_rules! impl_path_access {
+    ($t: ty) => {
+        impl PathAccess for $t {
+            fn get_value(&self, idx: &[usize], path: &str) -> EResult<Value> {
+                check_value_path(idx, path)?;
+                Ok(Value::from(*self))
+            }
+            fn set_value(&mut self, idx: &[usize], path: &str, value: Value) -> EResult<()> {
+                check_value_path(idx, path)?;
+                *self = value.try_into()?;
+                Ok(())
+            }
+            fn list_paths(&self, prefix: &str, result: &mut Vec<String>) {
+                result.push(prefix.to_owned());
+            }
+        }
+    };
+}
+
+impl_path_access!(bool);
+impl_path_access!(u8);
+impl_path_access!(u16);
+impl_path_access!(u32);
+impl_path_access!(u64);
+impl_path_access!(i8);
+impl_path_access!(i16);
+impl_path_access!(i32);
+impl_path_access!(i64);
+impl_path_access!(f32);
+impl_path_access!(f64);
+
+impl PathAccess for usize {
+    fn get_value(&self, idx: &[usize], path: &str) -> EResult<Value> {
+        check_value_path(idx, path)?;
+        Ok(Value::U64(u64::try_from(*self)?))
+    }
+    fn set_value(&mut self, idx: &[usize], path: &str, value: Value) -> EResult<()> {
+        check_value_path(idx, path)?;
+        *self = usize::try_from(u64::try_from(value)?)?;
+        Ok(())
+    }
+    fn list_paths(&self, prefix: &str, result: &mut Vec<String>) {
+        result.push(prefix.to_owned());
+    }
+}
+
+impl PathAccess for isize {
+    fn get_value(&self, idx: &[usize], path: &str) -> EResult<Value> {
+        check_value_path(idx, path)?;
+        Ok(Value::I64(i64::try_from(*self)?))
+    }
+    fn set_value(&mut self, idx: &[usize], path: &str, value: Value) -> EResult<()> {
+        check_value_path(idx, path)?;
+        *self = isize::try_from(i64::try_from(value)?)?;
+        Ok(())
+    }
+    fn list_paths(&self, prefix: &str, result: &mut Vec<String>) {
+        result.push(prefix.to_owned());
+    }
+}
+
+/// Durations are accessed as seconds
+impl PathAccess for Duration {
+    fn get_value(&self, idx: &[usize], path: &str) -> EResult<Value> {
+        check_value_path(idx, path)?;
+        Ok(Value::F64(self.as_secs_f64()))
+    }
+    fn set_value(&mut self, idx: &[usize], path: &str, value: Value) -> EResult<()> {
+        check_value_path(idx, path)?;
+        *self = Duration::try_from_secs_f64(value.try_into()?).map_err(Error::invalid_params)?;
+        Ok(())
+    }
+    fn list_paths(&self, prefix: &str, result: &mut Vec<String>) {
+        result.push(prefix.to_owned());
+    }
+}
+
+impl<T: PathAccess, const N: usize> PathAccess for [T; N] {
+    fn get_value(&self, idx: &[usize], path: &str) -> EResult<Value> {
+        if let Some((i, idx_rest)) = idx.split_first() {
+            self.get(*i)
+                .ok_or_else(|| Error::not_found(format!("index out of range: {}", i)))?
+                .get_value(idx_rest, path)
+        } else if path.is_empty() {
+            Ok(Value::Seq(
+                self.iter()
+                    .map(|v| v.get_value(&[], ""))
+                    .collect::<EResult<Vec<Value>>>()?,
+            ))
+        } else {
+            Err(Error::not_found("no such field, array index expected"))
+        }
+    }
+    fn set_value(&mut self, idx: &[usize], path: &str, value: Value) -> EResult<()> {
+        if let Some((i, idx_rest)) = idx.split_first() {
+            self.get_mut(*i)
+                .ok_or_else(|| Error::not_found(format!("index out of range: {}", i)))?
+                .set_value(idx_rest, path, value)
+        } else if path.is_empty() {
+            let values: Vec<Value> = value.try_into()?;
+            if values.len() != N {
+                return Err(Error::invalid_params(format!(
+                    "array size mismatch: {} expected",
+                    N
+                )));
+            }
+            for (v, value) in self.iter_mut().zip(values) {
+                v.set_value(&[], "", value)?;
+            }
+            Ok(())
+        } else {
+            Err(Error::not_found("no such field, array index expected"))
+        }
+    }
+    fn list_paths(&self, prefix: &str, result: &mut Vec<String>) {
+        for (i, v) in self.iter().enumerate() {
+            v.list_paths(&format!("{}[{}]", prefix, i), result);
+        }
+    }
+}
+
+impl<T: PathAccess> PathAccess for Box<T> {
+    #[inline]
+    fn get_value(&self, idx: &[usize], path: &str) -> EResult<Value> {
+        self.as_ref().get_value(idx, path)
+    }
+    #[inline]
+    fn set_value(&mut self, idx: &[usize], path: &str, value: Value) -> EResult<()> {
+        self.as_mut().set_value(idx, path, value)
+    }
+    #[inline]
+    fn list_paths(&self, prefix: &str, result: &mut Vec<String>) {
+        self.as_ref().list_paths(prefix, result);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(
+            split_path("data.subfield.temp_out").unwrap(),
+            ("data", vec![], "subfield.temp_out")
+        );
+        assert_eq!(
+            split_path("connector[2].voltage").unwrap(),
+            ("connector", vec![2], "voltage")
+        );
+        assert_eq!(
+            split_path("flags[1][3]").unwrap(),
+            ("flags", vec![1, 3], "")
+        );
+        assert!(split_path("flags[1").is_err());
+        assert!(split_path("flags[x]").is_err());
+        assert!(split_path("flags[1]x").is_err());
+        assert!(split_path("[1]").is_err());
+    }
+
+    #[test]
+    fn test_array_access() {
+        let mut a: [[u16; 2]; 3] = <_>::default();
+        a.set_value(&[1, 1], "", Value::String("5".to_owned()))
+            .unwrap();
+        assert_eq!(a[1][1], 5);
+        assert_eq!(a.get_value(&[1, 1], "").unwrap(), Value::U16(5));
+        assert!(a.set_value(&[3, 0], "", Value::U8(1)).is_err());
+        assert!(a.set_value(&[0, 0], "", Value::I8(-1)).is_err());
+        let mut paths = Vec::new();
+        a.list_paths("a", &mut paths);
+        assert_eq!(paths.len(), 6);
+        assert_eq!(paths[3], "a[1][1]");
+    }
+
+    #[test]
+    fn test_clamp() {
+        static TEMP: FieldMeta = FieldMeta {
+            path: "connector.temp",
+            min: Some(-20.0),
+            max: Some(80.5),
+            unit: Some("°C"),
+            description: None,
+        };
+        let meta: BTreeMap<&'static str, &'static FieldMeta> =
+            [(TEMP.path, &TEMP)].into_iter().collect();
+        let path = strip_indexes("connector[1].temp");
+        assert_eq!(path, "connector.temp");
+        assert_eq!(
+            clamp_value(&meta, &path, Value::I16(-50)).unwrap(),
+            Value::I64(-20)
+        );
+        assert_eq!(
+            clamp_value(&meta, &path, Value::String("100".to_owned())).unwrap(),
+            Value::F64(80.5)
+        );
+        assert_eq!(
+            clamp_value(&meta, &path, Value::U8(20)).unwrap(),
+            Value::U8(20)
+        );
+        assert!(clamp_value(&meta, &path, Value::F64(f64::NAN)).is_err());
+        let mut connector = BTreeMap::new();
+        connector.insert(Value::String("temp".to_owned()), Value::U8(90));
+        let value =
+            clamp_value(&meta, "connector", Value::Seq(vec![Value::Map(connector)])).unwrap();
+        assert_eq!(
+            value,
+            Value::Seq(vec![Value::Map(
+                [(Value::String("temp".to_owned()), Value::F64(80.5))]
+                    .into_iter()
+                    .collect()
+            )])
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::api::{TaskIntervalParams, TaskParams};
 use crate::tasks;
 use busrt::async_trait;
 use busrt::ipc::{Client, Config};
@@ -151,11 +152,25 @@ impl RpcHandlers for Handlers {
                 }
             }
             "thread_stats.get" => {
+                if payload.is_empty() {
+                    Ok(Some(pack(&tasks::thread_infos())?))
+                } else {
+                    Err(RpcError::params(None))
+                }
+            }
+            "io.status" => {
+                if payload.is_empty() {
+                    Ok(Some(pack(&crate::io::status_info())?))
+                } else {
+                    Err(RpcError::params(None))
+                }
+            }
+            "thread_stats.histogram" => {
                 if payload.is_empty() {
                     let mut result = BTreeMap::new();
                     let thread_stats = &tasks::controller_stats().lock().thread_stats;
                     for (name, st) in thread_stats {
-                        result.insert(name, st.info());
+                        result.insert(name, st.histogram());
                     }
                     Ok(Some(pack(&result)?))
                 } else {
@@ -170,6 +185,31 @@ impl RpcHandlers for Handlers {
                     Err(RpcError::params(None))
                 }
             }
+            "task.pause" => {
+                if payload.is_empty() {
+                    return Err(RpcError::params(None));
+                }
+                let p: TaskParams = unpack(payload)?;
+                tasks::pause_task(&p.name)?;
+                Ok(None)
+            }
+            "task.resume" => {
+                if payload.is_empty() {
+                    return Err(RpcError::params(None));
+                }
+                let p: TaskParams = unpack(payload)?;
+                tasks::resume_task(&p.name)?;
+                Ok(None)
+            }
+            "task.set_interval" => {
+                if payload.is_empty() {
+                    return Err(RpcError::params(None));
+                }
+                let p: TaskIntervalParams = unpack(payload)?;
+                let interval = crate::interval::parse_interval(&p.interval)?;
+                tasks::set_task_interval(&p.name, Duration::from_nanos(interval))?;
+                Ok(None)
+            }
             "action" => {
                 if payload.is_empty() {
                     return Err(RpcError::params(None));
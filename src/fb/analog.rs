@@ -0,0 +1,223 @@
+use super::impl_fb_path_access;
+use crate::clock;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Ramp: the output follows the input with the rate limit
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ramp {
+    pub input: f32,
+    /// max rate of change per second, zero disables the limit
+    pub rate: f32,
+    pub out: f32,
+    /// the output has not reached the input yet
+    pub busy: bool,
+    #[serde(skip)]
+    last: Option<Instant>,
+}
+
+impl Ramp {
+    pub fn new(rate: f32) -> Self {
+        Self {
+            rate,
+            ..Self::default()
+        }
+    }
+    pub fn call(&mut self, input: f32) -> f32 {
+        let now = clock::now();
+        let dt = self
+            .last
+            .replace(now)
+            .map_or(0.0, |t| now.saturating_duration_since(t).as_secs_f32());
+        self.input = input;
+        if self.rate > 0.0 {
+            let step = self.rate * dt;
+            self.out = input.min(self.out + step).max(self.out - step);
+        } else {
+            self.out = input;
+        }
+        self.busy = self.out != input;
+        self.out
+    }
+}
+
+impl_fb_path_access!(Ramp, input, rate, out, busy);
+
+/// Limiter: the output is the input limited by MIN and MAX
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limiter {
+    pub input: f32,
+    pub min: f32,
+    pub max: f32,
+    pub out: f32,
+    /// the input is above MAX
+    pub high: bool,
+    /// the input is below MIN
+    pub low: bool,
+}
+
+impl Limiter {
+    pub fn new(min: f32, max: f32) -> Self {
+        Self {
+            min,
+            max,
+            ..Self::default()
+        }
+    }
+    pub fn call(&mut self, input: f32) -> f32 {
+        self.input = input;
+        self.high = input > self.max;
+        self.low = input < self.min;
+        self.out = input.min(self.max).max(self.min);
+        self.out
+    }
+}
+
+impl_fb_path_access!(Limiter, input, min, max, out, high, low);
+
+/// Hysteresis: Q is set when the input rises above HIGH and cleared when it falls below LOW
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hysteresis {
+    pub input: f32,
+    pub high: f32,
+    pub low: f32,
+    pub q: bool,
+}
+
+impl Hysteresis {
+    pub fn new(low: f32, high: f32) -> Self {
+        Self {
+            high,
+            low,
+            ..Self::default()
+        }
+    }
+    pub fn call(&mut self, input: f32) -> bool {
+        self.input = input;
+        if input > self.high {
+            self.q = true;
+        } else if input < self.low {
+            self.q = false;
+        }
+        self.q
+    }
+}
+
+impl_fb_path_access!(Hysteresis, input, high, low, q);
+
+/// Linear scaling from the input range to the output one, e.g. raw ADC values to engineering
+/// units
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scale {
+    pub input: f32,
+    pub in_min: f32,
+    pub in_max: f32,
+    pub out_min: f32,
+    pub out_max: f32,
+    /// limit the output to the output range
+    pub clamp: bool,
+    pub out: f32,
+}
+
+impl Scale {
+    pub fn new(in_min: f32, in_max: f32, out_min: f32, out_max: f32) -> Self {
+        Self {
+            in_min,
+            in_max,
+            out_min,
+            out_max,
+            ..Self::default()
+        }
+    }
+    pub fn call(&mut self, input: f32) -> f32 {
+        self.input = input;
+        let in_range = self.in_max - self.in_min;
+        self.out = if in_range == 0.0 {
+            self.out_min
+        } else {
+            self.out_min + (input - self.in_min) * (self.out_max - self.out_min) / in_range
+        };
+        if self.clamp {
+            self.out = self
+                .out
+                .min(self.out_min.max(self.out_max))
+                .max(self.out_min.min(self.out_max));
+        }
+        self.out
+    }
+}
+
+impl_fb_path_access!(Scale, input, in_min, in_max, out_min, out_max, clamp, out);
+
+/// Moving average of the last WINDOW input values
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MovingAvg {
+    pub input: f32,
+    pub window: u16,
+    pub out: f32,
+    #[serde(skip)]
+    values: VecDeque<f32>,
+}
+
+impl MovingAvg {
+    pub fn new(window: u16) -> Self {
+        Self {
+            window,
+            ..Self::default()
+        }
+    }
+    pub fn call(&mut self, input: f32) -> f32 {
+        self.input = input;
+        let window = usize::from(self.window.max(1));
+        while self.values.len() >= window {
+            self.values.pop_front();
+        }
+        self.values.push_back(input);
+        #[allow(clippy::cast_precision_loss)]
+        let len = self.values.len() as f32;
+        self.out = self.values.iter().sum::<f32>() / len;
+        self.out
+    }
+}
+
+impl_fb_path_access!(MovingAvg, input, window, out);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_analog() {
+        clock::set_virtual(Some(Instant::now()));
+        let mut ramp = Ramp::new(10.0);
+        assert_eq!(ramp.call(100.0), 0.0);
+        clock::advance(Duration::from_millis(500));
+        assert_eq!(ramp.call(100.0), 5.0);
+        assert!(ramp.busy);
+        clock::advance(Duration::from_secs(1));
+        assert_eq!(ramp.call(-10.0), -5.0);
+        clock::set_virtual(None);
+        let mut hyst = Hysteresis::new(20.0, 25.0);
+        assert!(!hyst.call(24.0));
+        assert!(hyst.call(26.0));
+        assert!(hyst.call(21.0));
+        assert!(!hyst.call(19.0));
+        let mut scale = Scale::new(4.0, 20.0, 0.0, 100.0);
+        assert_eq!(scale.call(12.0), 50.0);
+        assert_eq!(scale.call(24.0), 125.0);
+        scale.clamp = true;
+        assert_eq!(scale.call(24.0), 100.0);
+        let mut avg = MovingAvg::new(3);
+        avg.call(1.0);
+        avg.call(2.0);
+        assert_eq!(avg.call(3.0), 2.0);
+        assert_eq!(avg.call(7.0), 4.0);
+    }
+}
@@ -0,0 +1,40 @@
+use super::impl_fb_path_access;
+use serde::{Deserialize, Serialize};
+
+/// Set-dominant bistable (SR)
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Sr {
+    pub s1: bool,
+    pub r: bool,
+    pub q1: bool,
+}
+
+impl Sr {
+    pub fn call(&mut self, s1: bool, r: bool) -> bool {
+        self.s1 = s1;
+        self.r = r;
+        self.q1 = s1 || (!r && self.q1);
+        self.q1
+    }
+}
+
+impl_fb_path_access!(Sr, s1, r, q1);
+
+/// Reset-dominant bistable (RS)
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Rs {
+    pub s: bool,
+    pub r1: bool,
+    pub q1: bool,
+}
+
+impl Rs {
+    pub fn call(&mut self, s: bool, r1: bool) -> bool {
+        self.s = s;
+        self.r1 = r1;
+        self.q1 = !r1 && (s || self.q1);
+        self.q1
+    }
+}
+
+impl_fb_path_access!(Rs, s, r1, q1);
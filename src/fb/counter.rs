@@ -0,0 +1,145 @@
+use super::impl_fb_path_access;
+use serde::{Deserialize, Serialize};
+
+/// Up counter (CTU): CV is incremented on CU rising edge, Q is set when CV reaches PV
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Ctu {
+    pub cu: bool,
+    pub reset: bool,
+    /// preset value
+    pub pv: i32,
+    pub q: bool,
+    /// current value
+    pub cv: i32,
+}
+
+impl Ctu {
+    pub fn new(pv: i32) -> Self {
+        Self {
+            pv,
+            ..Self::default()
+        }
+    }
+    pub fn call(&mut self, cu: bool, reset: bool) -> bool {
+        if reset {
+            self.cv = 0;
+        } else if cu && !self.cu {
+            self.cv = self.cv.saturating_add(1);
+        }
+        self.cu = cu;
+        self.reset = reset;
+        self.q = self.cv >= self.pv;
+        self.q
+    }
+}
+
+impl_fb_path_access!(Ctu, cu, reset, pv, q, cv);
+
+/// Down counter (CTD): CV is decremented on CD rising edge and set to PV on LOAD, Q is set when
+/// CV reaches zero
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Ctd {
+    pub cd: bool,
+    pub load: bool,
+    /// preset value
+    pub pv: i32,
+    pub q: bool,
+    /// current value
+    pub cv: i32,
+}
+
+impl Ctd {
+    pub fn new(pv: i32) -> Self {
+        Self {
+            pv,
+            ..Self::default()
+        }
+    }
+    pub fn call(&mut self, cd: bool, load: bool) -> bool {
+        if load {
+            self.cv = self.pv;
+        } else if cd && !self.cd {
+            self.cv = self.cv.saturating_sub(1);
+        }
+        self.cd = cd;
+        self.load = load;
+        self.q = self.cv <= 0;
+        self.q
+    }
+}
+
+impl_fb_path_access!(Ctd, cd, load, pv, q, cv);
+
+/// Up-down counter (CTUD)
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Ctud {
+    pub cu: bool,
+    pub cd: bool,
+    pub reset: bool,
+    pub load: bool,
+    /// preset value
+    pub pv: i32,
+    /// CV reached PV
+    pub qu: bool,
+    /// CV reached zero
+    pub qd: bool,
+    /// current value
+    pub cv: i32,
+}
+
+impl Ctud {
+    pub fn new(pv: i32) -> Self {
+        Self {
+            pv,
+            ..Self::default()
+        }
+    }
+    pub fn call(&mut self, cu: bool, cd: bool, reset: bool, load: bool) {
+        if reset {
+            self.cv = 0;
+        } else if load {
+            self.cv = self.pv;
+        } else {
+            if cu && !self.cu {
+                self.cv = self.cv.saturating_add(1);
+            }
+            if cd && !self.cd {
+                self.cv = self.cv.saturating_sub(1);
+            }
+        }
+        self.cu = cu;
+        self.cd = cd;
+        self.reset = reset;
+        self.load = load;
+        self.qu = self.cv >= self.pv;
+        self.qd = self.cv <= 0;
+    }
+}
+
+impl_fb_path_access!(Ctud, cu, cd, reset, load, pv, qu, qd, cv);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_counters() {
+        let mut ctu = Ctu::new(2);
+        assert!(!ctu.call(true, false));
+        assert!(!ctu.call(true, false));
+        assert!(!ctu.call(false, false));
+        assert!(ctu.call(true, false));
+        assert!(!ctu.call(true, true));
+        assert_eq!(ctu.cv, 0);
+        let mut ctud = Ctud::new(3);
+        ctud.call(false, false, false, true);
+        assert!(ctud.qu);
+        ctud.call(true, true, false, false);
+        assert_eq!(ctud.cv, 3);
+        ctud.call(false, true, false, false);
+        ctud.call(false, false, false, false);
+        ctud.call(false, true, false, false);
+        assert_eq!(ctud.cv, 2);
+        assert!(!ctud.qu && !ctud.qd);
+    }
+}
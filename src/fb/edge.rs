@@ -0,0 +1,36 @@
+use super::impl_fb_path_access;
+use serde::{Deserialize, Serialize};
+
+/// Rising edge detector (R_TRIG)
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct RTrig {
+    pub clk: bool,
+    pub q: bool,
+}
+
+impl RTrig {
+    pub fn call(&mut self, clk: bool) -> bool {
+        self.q = clk && !self.clk;
+        self.clk = clk;
+        self.q
+    }
+}
+
+impl_fb_path_access!(RTrig, clk, q);
+
+/// Falling edge detector (F_TRIG)
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct FTrig {
+    pub clk: bool,
+    pub q: bool,
+}
+
+impl FTrig {
+    pub fn call(&mut self, clk: bool) -> bool {
+        self.q = !clk && self.clk;
+        self.clk = clk;
+        self.q
+    }
+}
+
+impl_fb_path_access!(FTrig, clk, q);
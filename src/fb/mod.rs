@@ -0,0 +1,143 @@
+mod analog;
+mod bistable;
+mod counter;
+mod edge;
+mod pid;
+mod timer;
+
+pub use analog::{Hysteresis, Limiter, MovingAvg, Ramp, Scale};
+pub use bistable::{Rs, Sr};
+pub use counter::{Ctd, Ctu, Ctud};
+pub use edge::{FTrig, RTrig};
+pub use pid::Pid;
+pub use timer::{Tof, Ton, Tp};
+
+/// plc.yml type names of the function blocks which can be used as context field types
+pub const IEC_TYPES: &[(&str, &str)] = &[
+    ("TON", "::rplc::fb::Ton"),
+    ("TOF", "::rplc::fb::Tof"),
+    ("TP", "::rplc::fb::Tp"),
+    ("CTU", "::rplc::fb::Ctu"),
+    ("CTD", "::rplc::fb::Ctd"),
+    ("CTUD", "::rplc::fb::Ctud"),
+    ("R_TRIG", "::rplc::fb::RTrig"),
+    ("F_TRIG", "::rplc::fb::FTrig"),
+    ("SR", "::rplc::fb::Sr"),
+    ("RS", "::rplc::fb::Rs"),
+    ("PID", "::rplc::fb::Pid"),
+    ("RAMP", "::rplc::fb::Ramp"),
+    ("LIMITER", "::rplc::fb::Limiter"),
+    ("HYSTERESIS", "::rplc::fb::Hysteresis"),
+    ("SCALE", "::rplc::fb::Scale"),
+    ("MOVING_AVG", "::rplc::fb::MovingAvg"),
+];
+
+/// Default field values of a function block type, used by the builder to get the field types
+pub(crate) fn default_fields(tp: &str) -> Option<Vec<(String, eva_common::value::Value)>> {
+    fn fields<T: Default + crate::context::PathAccess>() -> eva_common::value::Value {
+        T::default().get_value(&[], "").unwrap_or_default()
+    }
+    let value = match tp {
+        "::rplc::fb::Ton" => fields::<Ton>(),
+        "::rplc::fb::Tof" => fields::<Tof>(),
+        "::rplc::fb::Tp" => fields::<Tp>(),
+        "::rplc::fb::Ctu" => fields::<Ctu>(),
+        "::rplc::fb::Ctd" => fields::<Ctd>(),
+        "::rplc::fb::Ctud" => fields::<Ctud>(),
+        "::rplc::fb::RTrig" => fields::<RTrig>(),
+        "::rplc::fb::FTrig" => fields::<FTrig>(),
+        "::rplc::fb::Sr" => fields::<Sr>(),
+        "::rplc::fb::Rs" => fields::<Rs>(),
+        "::rplc::fb::Pid" => fields::<Pid>(),
+        "::rplc::fb::Ramp" => fields::<Ramp>(),
+        "::rplc::fb::Limiter" => fields::<Limiter>(),
+        "::rplc::fb::Hysteresis" => fields::<Hysteresis>(),
+        "::rplc::fb::Scale" => fields::<Scale>(),
+        "::rplc::fb::MovingAvg" => fields::<MovingAvg>(),
+        _ => return None,
+    };
+    if let eva_common::value::Value::Map(m) = value {
+        Some(m.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    } else {
+        None
+    }
+}
+
+/// Durations of the function blocks are serialized as seconds
+pub(crate) mod duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_f64(value.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Duration::try_from_secs_f64(f64::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Implements context path access for the listed function block fields
+macro_rules! impl_fb_path_access {
+    ($t: ty, $($field: ident),+) => {
+        impl $crate::context::PathAccess for $t {
+            fn get_value(
+                &self,
+                idx: &[usize],
+                path: &str,
+            ) -> ::eva_common::EResult<::eva_common::value::Value> {
+                $crate::context::check_struct_path(idx)?;
+                if path.is_empty() {
+                    let mut result = ::std::collections::BTreeMap::new();
+                    $(
+                        result.insert(
+                            stringify!($field).into(),
+                            $crate::context::PathAccess::get_value(&self.$field, &[], "")?,
+                        );
+                    )+
+                    return Ok(::eva_common::value::Value::Map(result));
+                }
+                let (field, idx, rest) = $crate::context::split_path(path)?;
+                match field {
+                    $(stringify!($field) => {
+                        $crate::context::PathAccess::get_value(&self.$field, &idx, rest)
+                    })+
+                    _ => Err(::eva_common::Error::not_found(field)),
+                }
+            }
+            fn set_value(
+                &mut self,
+                idx: &[usize],
+                path: &str,
+                value: ::eva_common::value::Value,
+            ) -> ::eva_common::EResult<()> {
+                $crate::context::check_struct_path(idx)?;
+                let (field, idx, rest) = $crate::context::split_path(path)?;
+                match field {
+                    $(stringify!($field) => {
+                        $crate::context::PathAccess::set_value(&mut self.$field, &idx, rest, value)
+                    })+
+                    _ => Err(::eva_common::Error::not_found(field)),
+                }
+            }
+            fn list_paths(&self, prefix: &str, result: &mut Vec<String>) {
+                $(
+                    $crate::context::PathAccess::list_paths(
+                        &self.$field,
+                        &$crate::context::join_path(prefix, stringify!($field)),
+                        result,
+                    );
+                )+
+            }
+        }
+    };
+}
+
+pub(crate) use impl_fb_path_access;
@@ -0,0 +1,145 @@
+use super::impl_fb_path_access;
+use crate::clock;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// PID controller
+///
+/// The derivative term is calculated on the process value to avoid setpoint kicks. Integration
+/// is stopped while the output is saturated (anti-windup). In the manual mode the output follows
+/// MAN_OUT and the integral term tracks it, in the auto mode MAN_OUT tracks the output, so
+/// switching between the modes is bumpless. Use a negative KP for reverse acting loops
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Pid {
+    /// setpoint
+    pub sp: f32,
+    /// process value
+    pub pv: f32,
+    /// proportional gain
+    pub kp: f32,
+    /// integral time in seconds, zero disables the integral action
+    pub ti: f32,
+    /// derivative time in seconds, zero disables the derivative action
+    pub td: f32,
+    pub out_min: f32,
+    pub out_max: f32,
+    pub manual: bool,
+    /// manual mode output
+    pub man_out: f32,
+    pub out: f32,
+    pub error: f32,
+    i_term: f32,
+    #[serde(skip)]
+    prev_pv: Option<f32>,
+    #[serde(skip)]
+    last: Option<Instant>,
+}
+
+impl Default for Pid {
+    fn default() -> Self {
+        Self {
+            sp: 0.0,
+            pv: 0.0,
+            kp: 1.0,
+            ti: 0.0,
+            td: 0.0,
+            out_min: 0.0,
+            out_max: 100.0,
+            manual: false,
+            man_out: 0.0,
+            out: 0.0,
+            error: 0.0,
+            i_term: 0.0,
+            prev_pv: None,
+            last: None,
+        }
+    }
+}
+
+impl Pid {
+    pub fn new(kp: f32, ti: f32, td: f32) -> Self {
+        Self {
+            kp,
+            ti,
+            td,
+            ..Self::default()
+        }
+    }
+    /// Calculates the output for the process value, the setpoint is taken from SP
+    pub fn call(&mut self, pv: f32) -> f32 {
+        let now = clock::now();
+        let dt = self
+            .last
+            .replace(now)
+            .map_or(0.0, |t| now.saturating_duration_since(t).as_secs_f32());
+        self.pv = pv;
+        if !pv.is_finite() {
+            return self.out;
+        }
+        self.error = self.sp - pv;
+        let p = self.kp * self.error;
+        let d = match self.prev_pv {
+            Some(prev) if dt > 0.0 && self.td > 0.0 => -self.kp * self.td * (pv - prev) / dt,
+            _ => 0.0,
+        };
+        self.prev_pv = Some(pv);
+        if self.manual {
+            self.out = self.limit(self.man_out);
+            self.i_term = self.out - p - d;
+        } else {
+            if self.ti > 0.0 && dt > 0.0 {
+                let di = self.kp * self.error * dt / self.ti;
+                let out = p + self.i_term + d;
+                if !(out >= self.out_max && di > 0.0 || out <= self.out_min && di < 0.0) {
+                    self.i_term += di;
+                }
+            }
+            self.out = self.limit(p + self.i_term + d);
+            self.man_out = self.out;
+        }
+        self.out
+    }
+    /// Resets the integral term and the derivative history
+    pub fn reset(&mut self) {
+        self.i_term = 0.0;
+        self.prev_pv = None;
+        self.last = None;
+    }
+    fn limit(&self, value: f32) -> f32 {
+        value.min(self.out_max).max(self.out_min)
+    }
+}
+
+impl_fb_path_access!(Pid, sp, pv, kp, ti, td, out_min, out_max, manual, man_out, out, error);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_pid() {
+        clock::set_virtual(Some(Instant::now()));
+        let mut pid = Pid::new(2.0, 1.0, 0.0);
+        pid.sp = 50.0;
+        assert_eq!(pid.call(45.0), 10.0);
+        clock::advance(Duration::from_secs(1));
+        assert_eq!(pid.call(45.0), 20.0);
+        // saturated, the integral term must not wind up
+        for _ in 0..100 {
+            clock::advance(Duration::from_secs(1));
+            assert_eq!(pid.call(0.0), 100.0);
+        }
+        clock::advance(Duration::from_secs(1));
+        assert!(pid.call(60.0) < 20.0);
+        // bumpless transfer
+        pid.manual = true;
+        pid.man_out = 40.0;
+        assert_eq!(pid.call(50.0), 40.0);
+        pid.manual = false;
+        assert_eq!(pid.call(50.0), 40.0);
+        assert_eq!(pid.man_out, 40.0);
+        clock::set_virtual(None);
+    }
+}
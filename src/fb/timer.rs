@@ -0,0 +1,197 @@
+use super::impl_fb_path_access;
+use crate::clock;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+// the timer start is restored from the elapsed time, so retained timers continue after restart
+fn started(start: &mut Option<Instant>, et: Duration) -> Instant {
+    *start.get_or_insert_with(|| {
+        let now = clock::now();
+        now.checked_sub(et).unwrap_or(now)
+    })
+}
+
+/// On-delay timer (TON): Q is set when IN stays true for PT
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Ton {
+    pub input: bool,
+    /// preset time
+    #[serde(with = "super::duration_secs")]
+    pub pt: Duration,
+    pub q: bool,
+    /// elapsed time
+    #[serde(with = "super::duration_secs")]
+    pub et: Duration,
+    #[serde(skip)]
+    start: Option<Instant>,
+}
+
+impl Ton {
+    pub fn new(pt: Duration) -> Self {
+        Self {
+            pt,
+            ..Self::default()
+        }
+    }
+    pub fn call(&mut self, input: bool) -> bool {
+        self.input = input;
+        if input {
+            let start = started(&mut self.start, self.et);
+            self.et = clock::now().saturating_duration_since(start).min(self.pt);
+            self.q = self.et >= self.pt;
+        } else {
+            self.start = None;
+            self.et = Duration::ZERO;
+            self.q = false;
+        }
+        self.q
+    }
+}
+
+impl_fb_path_access!(Ton, input, pt, q, et);
+
+/// Off-delay timer (TOF): Q is set while IN is true and cleared when IN stays false for PT
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Tof {
+    pub input: bool,
+    /// preset time
+    #[serde(with = "super::duration_secs")]
+    pub pt: Duration,
+    pub q: bool,
+    /// elapsed time
+    #[serde(with = "super::duration_secs")]
+    pub et: Duration,
+    #[serde(skip)]
+    start: Option<Instant>,
+}
+
+impl Tof {
+    pub fn new(pt: Duration) -> Self {
+        Self {
+            pt,
+            ..Self::default()
+        }
+    }
+    pub fn call(&mut self, input: bool) -> bool {
+        self.input = input;
+        if input {
+            self.start = None;
+            self.et = Duration::ZERO;
+            self.q = true;
+        } else if self.q {
+            let start = started(&mut self.start, self.et);
+            self.et = clock::now().saturating_duration_since(start).min(self.pt);
+            self.q = self.et < self.pt;
+        }
+        self.q
+    }
+}
+
+impl_fb_path_access!(Tof, input, pt, q, et);
+
+/// Pulse timer (TP): Q is set for PT on IN rising edge
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Tp {
+    pub input: bool,
+    /// preset time
+    #[serde(with = "super::duration_secs")]
+    pub pt: Duration,
+    pub q: bool,
+    /// elapsed time
+    #[serde(with = "super::duration_secs")]
+    pub et: Duration,
+    #[serde(skip)]
+    start: Option<Instant>,
+}
+
+impl Tp {
+    pub fn new(pt: Duration) -> Self {
+        Self {
+            pt,
+            ..Self::default()
+        }
+    }
+    pub fn call(&mut self, input: bool) -> bool {
+        if !self.q && input && !self.input {
+            self.start = Some(clock::now());
+            self.et = Duration::ZERO;
+            self.q = true;
+        }
+        if self.q {
+            let start = started(&mut self.start, self.et);
+            self.et = clock::now().saturating_duration_since(start).min(self.pt);
+            self.q = self.et < self.pt;
+        }
+        if !self.q && !input {
+            self.et = Duration::ZERO;
+        }
+        self.input = input;
+        self.q
+    }
+}
+
+impl_fb_path_access!(Tp, input, pt, q, et);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::context::PathAccess;
+    use eva_common::value::Value;
+
+    fn ms(v: u64) -> Duration {
+        Duration::from_millis(v)
+    }
+
+    #[test]
+    fn test_timers() {
+        clock::set_virtual(Some(Instant::now()));
+        let mut ton = Ton::new(ms(100));
+        let mut tof = Tof::new(ms(100));
+        let mut tp = Tp::new(ms(100));
+        assert!(!ton.call(true));
+        assert!(tof.call(true));
+        assert!(tp.call(true));
+        clock::advance(ms(60));
+        assert!(!ton.call(true));
+        assert!(tof.call(false));
+        assert!(tp.call(false));
+        assert_eq!(ton.et, ms(60));
+        clock::advance(ms(60));
+        assert!(ton.call(true));
+        assert!(tof.call(false));
+        assert!(!tp.call(true));
+        assert_eq!(ton.et, ms(100));
+        assert!(!ton.call(false));
+        assert_eq!(ton.et, Duration::ZERO);
+        assert_eq!(tp.et, ms(100));
+        assert!(!tp.call(false));
+        assert_eq!(tp.et, Duration::ZERO);
+        clock::advance(ms(40));
+        assert!(!tof.call(false));
+        assert_eq!(tof.et, ms(100));
+        clock::set_virtual(None);
+    }
+
+    #[test]
+    fn test_timer_restore() {
+        clock::set_virtual(Some(Instant::now()));
+        let mut ton = Ton::new(ms(100));
+        ton.call(true);
+        clock::advance(ms(70));
+        ton.call(true);
+        let value = crate::retain::to_value(&ton).unwrap();
+        let mut restored: Ton = crate::retain::from_value(value).unwrap();
+        assert_eq!(restored.pt, ms(100));
+        assert_eq!(restored.et, ms(70));
+        // the time before the first call after restoring is not counted
+        clock::advance(ms(10));
+        assert!(!restored.call(true));
+        assert_eq!(restored.et, ms(70));
+        clock::advance(ms(30));
+        assert!(restored.call(true));
+        restored.set_value(&[], "pt", Value::F64(0.5)).unwrap();
+        assert_eq!(restored.pt, ms(500));
+        assert_eq!(restored.get_value(&[], "q").unwrap(), Value::Bool(true));
+        clock::set_virtual(None);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::context::PathAccess;
+use eva_common::payload::{pack, unpack};
+use eva_common::value::Value;
+use eva_common::{EResult, Error};
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic;
+
+static FORCES: Lazy<Mutex<BTreeMap<String, Force>>> = Lazy::new(<_>::default);
+static ACTIVE: atomic::AtomicBool = atomic::AtomicBool::new(false);
+
+thread_local! {
+    // set when the current input iteration has applied forces under its context lock
+    static APPLIED_LOCKED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Forced context field value
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Force {
+    pub value: Value,
+    /// the force is kept after the PLC restart
+    #[serde(default)]
+    pub persistent: bool,
+}
+
+/// Forced context field info
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ForceInfo {
+    pub path: String,
+    pub value: Value,
+    pub persistent: bool,
+}
+
+fn forces_file() -> PathBuf {
+    let mut path = crate::var_dir();
+    path.push(format!("{}.forces", crate::name()));
+    path
+}
+
+/// use init_plc!() macro to restore persistent forces
+pub fn init() {
+    let path = forces_file();
+    let data = match fs::read(&path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+        Err(e) => {
+            error!("unable to read {}: {}", path.to_string_lossy(), e);
+            return;
+        }
+    };
+    let saved: BTreeMap<String, Value> = match unpack(&data) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("forces file {} is corrupt: {}", path.to_string_lossy(), e);
+            return;
+        }
+    };
+    for (path, value) in saved {
+        if let Err(e) = set(&path, value, true) {
+            warn!("persistent force {} not restored: {}", path, e);
+        } else {
+            warn!("persistent force {} restored", path);
+        }
+    }
+}
+
+fn save(forces: &BTreeMap<String, Force>) -> EResult<()> {
+    let persistent: BTreeMap<&str, &Value> = forces
+        .iter()
+        .filter(|(_, f)| f.persistent)
+        .map(|(k, f)| (k.as_str(), &f.value))
+        .collect();
+    let path = forces_file();
+    if persistent.is_empty() {
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(Error::io(e)),
+        }
+    } else {
+        crate::write_file_atomic(&path, &pack(&persistent)?)
+    }
+}
+
+/// Forces a context field to the value
+///
+/// The value is set immediately. Then it is applied by the generated input workers while the
+/// context is still locked with the input values, and before each program and output
+/// iteration, so neither inputs nor programs can change it. Persistent forces are kept after
+/// the PLC restart
+pub fn set(path: &str, value: Value, persistent: bool) -> EResult<()> {
+    // the context is never locked while the forces are, input workers lock them the other way
+    crate::context::set(path, value.clone())?;
+    let mut forces = FORCES.lock();
+    let prev = forces.insert(path.to_owned(), Force { value, persistent });
+    ACTIVE.store(true, atomic::Ordering::Release);
+    if persistent || prev.is_some_and(|f| f.persistent) {
+        save(&forces)?;
+    }
+    info!("context field {} forced", path);
+    Ok(())
+}
+
+/// Clears the force of a context field
+pub fn clear(path: &str) -> EResult<()> {
+    let mut forces = FORCES.lock();
+    let prev = forces
+        .remove(path)
+        .ok_or_else(|| Error::not_found(format!("{} is not forced", path)))?;
+    ACTIVE.store(!forces.is_empty(), atomic::Ordering::Release);
+    if prev.persistent {
+        save(&forces)?;
+    }
+    info!("context field {} force cleared", path);
+    Ok(())
+}
+
+/// Clears all forces
+pub fn clear_all() -> EResult<()> {
+    let mut forces = FORCES.lock();
+    let had_persistent = forces.values().any(|f| f.persistent);
+    forces.clear();
+    ACTIVE.store(false, atomic::Ordering::Release);
+    if had_persistent {
+        save(&forces)?;
+    }
+    info!("all forces cleared");
+    Ok(())
+}
+
+pub fn list() -> Vec<ForceInfo> {
+    FORCES
+        .lock()
+        .iter()
+        .map(|(path, f)| ForceInfo {
+            path: path.clone(),
+            value: f.value.clone(),
+            persistent: f.persistent,
+        })
+        .collect()
+}
+
+/// Returns the number of active forces
+pub fn count() -> usize {
+    if is_active() {
+        FORCES.lock().len()
+    } else {
+        0
+    }
+}
+
+#[inline]
+pub fn is_active() -> bool {
+    ACTIVE.load(atomic::Ordering::Acquire)
+}
+
+/// Applies forced values to the context, called by the task loops
+#[inline]
+pub(crate) fn apply() {
+    if is_active() {
+        match crate::context::write() {
+            Ok(mut ctx) => apply_forces(&mut *ctx),
+            Err(e) => error!("unable to apply forces: {}", e),
+        }
+    }
+}
+
+/// Applies forced values to the context locked by an input worker, so the inputs never
+/// overwrite forced fields, even for a moment
+///
+/// Called by the generated input workers. Custom inputs may call it as well, otherwise forces
+/// are applied after each input iteration
+#[inline]
+pub fn apply_locked(ctx: &mut dyn PathAccess) {
+    if is_active() {
+        apply_forces(ctx);
+    }
+    APPLIED_LOCKED.with(|v| v.set(true));
+}
+
+/// Applies forced values after an input iteration, unless the input has applied them under its
+/// context lock
+#[inline]
+pub(crate) fn apply_after_input() {
+    if !APPLIED_LOCKED.with(|v| v.replace(false)) {
+        apply();
+    }
+}
+
+fn apply_forces(ctx: &mut dyn PathAccess) {
+    let forces = FORCES.lock();
+    if let Err(e) = crate::context::set_many(
+        ctx,
+        forces
+            .iter()
+            .map(|(path, f)| (path.as_str(), f.value.clone())),
+    ) {
+        error!("unable to apply forces: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parking_lot::RwLock;
+
+    #[derive(Default)]
+    struct TestContext {
+        temp: u16,
+        fan: bool,
+    }
+
+    impl PathAccess for TestContext {
+        fn get_value(&self, idx: &[usize], path: &str) -> EResult<Value> {
+            match path {
+                "temp" => self.temp.get_value(idx, ""),
+                "fan" => self.fan.get_value(idx, ""),
+                _ => Err(Error::not_found(path)),
+            }
+        }
+        fn set_value(&mut self, idx: &[usize], path: &str, value: Value) -> EResult<()> {
+            match path {
+                "temp" => self.temp.set_value(idx, "", value),
+                "fan" => self.fan.set_value(idx, "", value),
+                _ => Err(Error::not_found(path)),
+            }
+        }
+        fn list_paths(&self, _prefix: &str, result: &mut Vec<String>) {
+            result.extend(["temp".to_owned(), "fan".to_owned()]);
+        }
+    }
+
+    static CONTEXT: Lazy<RwLock<TestContext>> = Lazy::new(<_>::default);
+
+    #[test]
+    fn test_force() {
+        crate::NAME.set("rplc_test_force".to_owned()).unwrap();
+        crate::context::register(&*CONTEXT);
+        let _ = fs::remove_file(forces_file());
+        // set
+        set("temp", Value::U16(25), false).unwrap();
+        assert_eq!(CONTEXT.read().temp, 25);
+        assert_eq!(count(), 1);
+        assert!(!forces_file().exists());
+        assert!(set("humidity", Value::U16(1), false).is_err());
+        assert_eq!(count(), 1);
+        // apply, an input worker overwrites the field in the same lock
+        {
+            let mut ctx = CONTEXT.write();
+            ctx.temp = 30;
+            apply_locked(&mut *ctx);
+            assert_eq!(ctx.temp, 25);
+        }
+        apply_after_input();
+        CONTEXT.write().temp = 31;
+        apply();
+        assert_eq!(CONTEXT.read().temp, 25);
+        // a custom input which does not apply forces by itself
+        CONTEXT.write().temp = 32;
+        apply_after_input();
+        assert_eq!(CONTEXT.read().temp, 25);
+        // persistence, only persistent forces are restored after the restart
+        set("fan", Value::Bool(true), true).unwrap();
+        assert!(forces_file().exists());
+        clear_all().unwrap();
+        assert!(!forces_file().exists());
+        set("fan", Value::Bool(true), true).unwrap();
+        set("temp", Value::U16(20), false).unwrap();
+        FORCES.lock().clear();
+        ACTIVE.store(false, atomic::Ordering::Release);
+        CONTEXT.write().fan = false;
+        init();
+        let forces = list();
+        assert_eq!(forces.len(), 1);
+        assert_eq!(forces[0].path, "fan");
+        assert!(forces[0].persistent);
+        assert!(CONTEXT.read().fan);
+        // clear
+        clear("fan").unwrap();
+        assert!(clear("fan").is_err());
+        assert_eq!(count(), 0);
+        assert!(!forces_file().exists());
+        CONTEXT.write().fan = false;
+        apply();
+        assert!(!CONTEXT.read().fan);
+    }
+}
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+
+const SUB_BITS: u32 = 2;
+const SUB_BUCKETS: usize = 1 << SUB_BITS;
+const BUCKETS: usize = 1 + 32 * SUB_BUCKETS;
+
+/// Log-bucketed histogram of microsecond values
+///
+/// Each power-of-two range is split into 4 linear sub-buckets, so the relative error of a
+/// reported percentile is below 25%
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    counts: Box<[u64; BUCKETS]>,
+    count: u64,
+    max: u32,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            counts: Box::new([0; BUCKETS]),
+            count: 0,
+            max: 0,
+        }
+    }
+}
+
+#[inline]
+fn bucket_index(value: u32) -> usize {
+    if value == 0 {
+        return 0;
+    }
+    let e = 31 - value.leading_zeros();
+    let sub = if e >= SUB_BITS {
+        (value >> (e - SUB_BITS)) as usize & (SUB_BUCKETS - 1)
+    } else {
+        (value << (SUB_BITS - e)) as usize & (SUB_BUCKETS - 1)
+    };
+    1 + e as usize * SUB_BUCKETS + sub
+}
+
+/// Returns the upper bound (inclusive) of the bucket
+#[inline]
+fn bucket_upper_bound(idx: usize) -> u32 {
+    if idx == 0 {
+        return 0;
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    let e = ((idx - 1) / SUB_BUCKETS) as u32;
+    #[allow(clippy::cast_possible_truncation)]
+    let sub = ((idx - 1) % SUB_BUCKETS) as u32;
+    let (lower, width) = if e >= SUB_BITS {
+        (
+            (1u64 << e) + (u64::from(sub) << (e - SUB_BITS)),
+            1u64 << (e - SUB_BITS),
+        )
+    } else {
+        ((1u64 << e) + u64::from(sub >> (SUB_BITS - e)), 1)
+    };
+    u32::try_from(lower + width - 1).unwrap_or(u32::MAX)
+}
+
+impl Histogram {
+    #[inline]
+    pub fn record(&mut self, value: u32) {
+        let bucket = &mut self.counts[bucket_index(value)];
+        *bucket = bucket.saturating_add(1);
+        self.count = self.count.saturating_add(1);
+        if value > self.max {
+            self.max = value;
+        }
+    }
+    pub fn reset(&mut self) {
+        *self.counts = [0; BUCKETS];
+        self.count = 0;
+        self.max = 0;
+    }
+    #[inline]
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+    /// Returns the value below which the given fraction (0.0-1.0) of records falls
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn percentile(&self, q: f64) -> u32 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((self.count as f64 * q).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+        for (idx, c) in self.counts.iter().enumerate() {
+            seen += c;
+            if seen >= rank {
+                return bucket_upper_bound(idx).min(self.max);
+            }
+        }
+        self.max
+    }
+    pub fn info(&self) -> HistogramInfo {
+        HistogramInfo {
+            count: self.count,
+            p50: self.percentile(0.5),
+            p90: self.percentile(0.9),
+            p99: self.percentile(0.99),
+            p999: self.percentile(0.999),
+            max: self.max,
+            buckets: self
+                .counts
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| **c > 0)
+                .map(|(idx, c)| (bucket_upper_bound(idx), *c))
+                .collect(),
+        }
+    }
+}
+
+/// Histogram percentiles and non-empty buckets (upper bound, count). All values are in
+/// microseconds
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct HistogramInfo {
+    pub count: u64,
+    pub p50: u32,
+    pub p90: u32,
+    pub p99: u32,
+    pub p999: u32,
+    pub max: u32,
+    pub buckets: Vec<(u32, u64)>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_buckets() {
+        for v in [
+            0u32,
+            1,
+            2,
+            3,
+            4,
+            5,
+            7,
+            8,
+            100,
+            65_535,
+            65_536,
+            1_000_000,
+            u32::MAX,
+        ] {
+            let idx = bucket_index(v);
+            assert!(bucket_upper_bound(idx) >= v, "{v}");
+            if idx > 0 {
+                assert!(bucket_upper_bound(idx - 1) < v, "{v}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let mut h = Histogram::default();
+        for v in 1..=1000 {
+            h.record(v);
+        }
+        h.record(200_000);
+        assert_eq!(h.count(), 1001);
+        let p50 = h.percentile(0.5);
+        assert!((500..=640).contains(&p50), "{p50}");
+        assert_eq!(h.percentile(1.0), 200_000);
+        assert_eq!(h.info().max, 200_000);
+        h.reset();
+        assert_eq!(h.percentile(0.99), 0);
+    }
+}
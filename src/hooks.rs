@@ -0,0 +1,155 @@
+use bmart_derive::EnumStr;
+use log::{debug, error};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+type HookFn = Arc<dyn Fn() + Send + Sync>;
+
+static HOOKS: Lazy<Mutex<BTreeMap<Kind, Vec<Hook>>>> = Lazy::new(<_>::default);
+static STOP_DEADLINE: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Lifecycle hook kind
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, EnumStr)]
+#[enumstr(rename_all = "lowercase")]
+pub enum Kind {
+    /// the PLC is starting, called before I/O is launched
+    Start,
+    /// the PLC has reached the active status, called asynchronously
+    Active,
+    /// the PLC is stopping, called after inputs and programs are stopped but before the final
+    /// output sync (the former shutdown function)
+    Stopping,
+    /// the PLC is stopped, called after the final output sync
+    Stopped,
+    /// the watchdog has driven the PLC into the safe state
+    Fault,
+}
+
+struct Hook {
+    priority: i16,
+    timeout: Option<Duration>,
+    f: HookFn,
+}
+
+/// Registers a lifecycle hook
+///
+/// Hooks of the same kind are called in ascending priority order, hooks with equal priorities in
+/// the order of registration. If the timeout is set, a hook is called in a separate thread and
+/// abandoned if not finished in time. Stopping and stopped hooks are always limited by the PLC
+/// stop timeout
+pub fn register<F>(kind: Kind, priority: i16, timeout: Option<Duration>, f: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    let mut hooks = HOOKS.lock();
+    let entries = hooks.entry(kind).or_default();
+    let pos = entries.partition_point(|h| h.priority <= priority);
+    entries.insert(
+        pos,
+        Hook {
+            priority,
+            timeout,
+            f: Arc::new(f),
+        },
+    );
+}
+
+#[inline]
+pub fn on_start<F>(priority: i16, f: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    register(Kind::Start, priority, None, f);
+}
+
+#[inline]
+pub fn on_active<F>(priority: i16, f: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    register(Kind::Active, priority, None, f);
+}
+
+#[inline]
+pub fn on_stopping<F>(priority: i16, f: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    register(Kind::Stopping, priority, None, f);
+}
+
+#[inline]
+pub fn on_stopped<F>(priority: i16, f: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    register(Kind::Stopped, priority, None, f);
+}
+
+#[inline]
+pub fn on_fault<F>(priority: i16, f: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    register(Kind::Fault, priority, None, f);
+}
+
+/// Returns true if there are hooks of the kind registered
+pub fn has(kind: Kind) -> bool {
+    HOOKS.lock().get(&kind).is_some_and(|v| !v.is_empty())
+}
+
+pub(crate) fn set_stop_deadline(deadline: Instant) {
+    STOP_DEADLINE.lock().replace(deadline);
+}
+
+/// Runs all hooks of the kind in the current thread. Returns false if there are no hooks
+/// registered
+pub(crate) fn run(kind: Kind) -> bool {
+    let hooks: Vec<(Option<Duration>, HookFn)> = if let Some(v) = HOOKS.lock().get(&kind) {
+        v.iter().map(|h| (h.timeout, h.f.clone())).collect()
+    } else {
+        return false;
+    };
+    if hooks.is_empty() {
+        return false;
+    }
+    debug!("running {} hooks", kind);
+    let deadline = if kind == Kind::Stopping || kind == Kind::Stopped {
+        *STOP_DEADLINE.lock()
+    } else {
+        None
+    };
+    for (i, (timeout, f)) in hooks.into_iter().enumerate() {
+        let timeout = match (timeout, deadline) {
+            (Some(t), Some(d)) => Some(t.min(d.saturating_duration_since(Instant::now()))),
+            (None, Some(d)) => Some(d.saturating_duration_since(Instant::now())),
+            (t, None) => t,
+        };
+        if let Some(t) = timeout {
+            let (tx, rx) = mpsc::channel();
+            crate::tasks::spawn0(move || {
+                f();
+                let _ = tx.send(());
+            });
+            if rx.recv_timeout(t).is_err() {
+                error!("{} hook #{} timeout ({:?})", kind, i + 1, t);
+            }
+        } else {
+            f();
+        }
+    }
+    true
+}
+
+/// Runs all hooks of the kind in a separate thread
+pub(crate) fn run_async(kind: Kind) {
+    if has(kind) {
+        crate::tasks::spawn0(move || {
+            run(kind);
+        });
+    }
+}
@@ -0,0 +1,796 @@
+use eva_common::value::Value;
+use eva_common::{EResult, Error};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECS_PER_DAY: u32 = 86_400;
+const MS_PER_DAY: u32 = SECS_PER_DAY * 1_000;
+
+/// The default STRING/WSTRING capacity if not specified
+pub const DEFAULT_STRING_LEN: usize = 80;
+
+/// IEC 61131-3 STRING[N], a fixed-capacity UTF-8 string, N is the capacity in bytes
+///
+/// Modbus: (N + 1) / 2 registers, two bytes per register, padded with zeros
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct IecString<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> IecString<N> {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+    /// Sets the string, truncates it at the char boundary if it does not fit
+    pub fn set(&mut self, s: &str) {
+        let mut len = s.len().min(N);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.buf[..len].copy_from_slice(&s.as_bytes()[..len]);
+        self.buf[len..].fill(0);
+        self.len = len;
+    }
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+    /// Creates the string from raw bytes, stops at the first zero byte
+    pub fn from_bytes(bytes: &[u8]) -> EResult<Self> {
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        std::str::from_utf8(&bytes[..len])
+            .map_err(Error::invalid_data)?
+            .try_into()
+    }
+    /// Raw bytes, padded with zeros up to the capacity
+    #[inline]
+    pub fn as_bytes_padded(&self) -> &[u8; N] {
+        &self.buf
+    }
+}
+
+impl<const N: usize> Default for IecString<N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Deref for IecString<N> {
+    type Target = str;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> TryFrom<&str> for IecString<N> {
+    type Error = Error;
+    fn try_from(s: &str) -> EResult<Self> {
+        if s.len() > N {
+            return Err(Error::invalid_data(format!(
+                "string too long: {} bytes, STRING[{}]",
+                s.len(),
+                N
+            )));
+        }
+        let mut result = Self::new();
+        result.set(s);
+        Ok(result)
+    }
+}
+
+impl<const N: usize> FromStr for IecString<N> {
+    type Err = Error;
+    #[inline]
+    fn from_str(s: &str) -> EResult<Self> {
+        s.try_into()
+    }
+}
+
+impl<const N: usize> PartialEq<str> for IecString<N> {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<const N: usize> PartialEq<&str> for IecString<N> {
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<const N: usize> fmt::Display for IecString<N> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<const N: usize> fmt::Debug for IecString<N> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// IEC 61131-3 WSTRING[N], a fixed-capacity UTF-16 string, N is the capacity in code units
+///
+/// Modbus: N registers, one code unit per register, padded with zeros
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct IecWString<const N: usize> {
+    buf: [u16; N],
+    len: usize,
+}
+
+impl<const N: usize> IecWString<N> {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+    /// Sets the string, truncates it at the char boundary if it does not fit
+    pub fn set(&mut self, s: &str) {
+        let mut len = 0;
+        for c in s.chars() {
+            let c_len = c.len_utf16();
+            if len + c_len > N {
+                break;
+            }
+            c.encode_utf16(&mut self.buf[len..len + c_len]);
+            len += c_len;
+        }
+        self.buf[len..].fill(0);
+        self.len = len;
+    }
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+    #[inline]
+    pub fn as_utf16(&self) -> &[u16] {
+        &self.buf[..self.len]
+    }
+    /// Creates the string from UTF-16 code units, stops at the first zero unit
+    pub fn from_utf16(units: &[u16]) -> EResult<Self> {
+        let len = units.iter().position(|&u| u == 0).unwrap_or(units.len());
+        String::from_utf16(&units[..len])
+            .map_err(Error::invalid_data)?
+            .as_str()
+            .try_into()
+    }
+    /// Raw code units, padded with zeros up to the capacity
+    #[inline]
+    pub fn as_utf16_padded(&self) -> &[u16; N] {
+        &self.buf
+    }
+}
+
+impl<const N: usize> Default for IecWString<N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> TryFrom<&str> for IecWString<N> {
+    type Error = Error;
+    fn try_from(s: &str) -> EResult<Self> {
+        let len = s.encode_utf16().count();
+        if len > N {
+            return Err(Error::invalid_data(format!(
+                "string too long: {} chars, WSTRING[{}]",
+                len, N
+            )));
+        }
+        let mut result = Self::new();
+        result.set(s);
+        Ok(result)
+    }
+}
+
+impl<const N: usize> FromStr for IecWString<N> {
+    type Err = Error;
+    #[inline]
+    fn from_str(s: &str) -> EResult<Self> {
+        s.try_into()
+    }
+}
+
+impl<const N: usize> fmt::Display for IecWString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in char::decode_utf16(self.as_utf16().iter().copied()) {
+            fmt::Write::write_char(f, c.unwrap_or(char::REPLACEMENT_CHARACTER))?;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> fmt::Debug for IecWString<N> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_string(), f)
+    }
+}
+
+/// IEC 61131-3 TIME, milliseconds
+///
+/// Accessed as seconds by the context API, Modbus: 2 registers (u32)
+#[derive(Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Time(pub u32);
+
+/// IEC 61131-3 DATE, seconds since the UNIX epoch (midnight, UTC)
+///
+/// Accessed as "YYYY-MM-DD" by the context API, Modbus: 2 registers (u32)
+#[derive(Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Date(pub u32);
+
+/// IEC 61131-3 TIME_OF_DAY, milliseconds since midnight
+///
+/// Accessed as "HH:MM:SS.mmm" by the context API, Modbus: 2 registers (u32)
+#[derive(Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Tod(pub u32);
+
+/// IEC 61131-3 DATE_AND_TIME, seconds since the UNIX epoch (UTC)
+///
+/// Accessed as "YYYY-MM-DDTHH:MM:SS" by the context API, Modbus: 2 registers (u32)
+#[derive(Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Dt(pub u32);
+
+impl Time {
+    #[inline]
+    pub fn as_secs_f64(self) -> f64 {
+        f64::from(self.0) / 1_000.0
+    }
+    pub fn from_secs_f64(secs: f64) -> EResult<Self> {
+        let ms = (secs * 1_000.0).round();
+        if (0.0..=f64::from(u32::MAX)).contains(&ms) {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            Ok(Self(ms as u32))
+        } else {
+            Err(Error::invalid_data(format!("TIME out of range: {}", secs)))
+        }
+    }
+}
+
+impl From<Time> for Duration {
+    #[inline]
+    fn from(t: Time) -> Self {
+        Duration::from_millis(u64::from(t.0))
+    }
+}
+
+/// Saturates at the max TIME value
+impl From<Duration> for Time {
+    #[inline]
+    fn from(d: Duration) -> Self {
+        Self(u32::try_from(d.as_millis()).unwrap_or(u32::MAX))
+    }
+}
+
+impl Date {
+    /// # Panics
+    ///
+    /// Will panic if the system clock is before the UNIX epoch
+    pub fn today() -> Self {
+        Dt::now().date()
+    }
+    pub fn from_ymd(year: i64, month: u32, day: u32) -> EResult<Self> {
+        let days = days_from_civil(year, month, day)
+            .filter(|&days| days >= 0)
+            .ok_or_else(|| {
+                Error::invalid_data(format!("invalid date: {}-{}-{}", year, month, day))
+            })?;
+        u32::try_from(days * i64::from(SECS_PER_DAY))
+            .map(Self)
+            .map_err(|_| Error::invalid_data(format!("DATE out of range: {}", year)))
+    }
+    /// Returns (year, month, day)
+    #[inline]
+    pub fn ymd(self) -> (i64, u32, u32) {
+        civil_from_days(i64::from(self.0 / SECS_PER_DAY))
+    }
+}
+
+impl Tod {
+    /// # Panics
+    ///
+    /// Will panic if the system clock is before the UNIX epoch
+    pub fn now() -> Self {
+        Dt::now().tod()
+    }
+    pub fn from_hms_milli(hour: u32, min: u32, sec: u32, milli: u32) -> EResult<Self> {
+        if hour < 24 && min < 60 && sec < 60 && milli < 1_000 {
+            Ok(Self(((hour * 60 + min) * 60 + sec) * 1_000 + milli))
+        } else {
+            Err(Error::invalid_data(format!(
+                "invalid time of day: {:02}:{:02}:{:02}.{:03}",
+                hour, min, sec, milli
+            )))
+        }
+    }
+    /// Returns (hour, minute, second, millisecond)
+    #[inline]
+    pub fn hms_milli(self) -> (u32, u32, u32, u32) {
+        let secs = self.0 / 1_000;
+        (secs / 3_600, secs / 60 % 60, secs % 60, self.0 % 1_000)
+    }
+}
+
+impl Dt {
+    /// # Panics
+    ///
+    /// Will panic if the system clock is before the UNIX epoch
+    pub fn now() -> Self {
+        SystemTime::now().try_into().unwrap()
+    }
+    pub fn from_date_tod(date: Date, tod: Tod) -> EResult<Self> {
+        date.0
+            .checked_add(tod.0 / 1_000)
+            .map(Self)
+            .ok_or_else(|| Error::invalid_data("DT out of range"))
+    }
+    #[inline]
+    pub fn date(self) -> Date {
+        Date(self.0 - self.0 % SECS_PER_DAY)
+    }
+    #[inline]
+    pub fn tod(self) -> Tod {
+        Tod(self.0 % SECS_PER_DAY * 1_000)
+    }
+}
+
+impl TryFrom<SystemTime> for Dt {
+    type Error = Error;
+    fn try_from(t: SystemTime) -> EResult<Self> {
+        let secs = t
+            .duration_since(UNIX_EPOCH)
+            .map_err(Error::invalid_data)?
+            .as_secs();
+        u32::try_from(secs)
+            .map(Self)
+            .map_err(|_| Error::invalid_data("DT out of range"))
+    }
+}
+
+impl From<Dt> for SystemTime {
+    #[inline]
+    fn from(dt: Dt) -> Self {
+        UNIX_EPOCH + Duration::from_secs(u64::from(dt.0))
+    }
+}
+
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some(era * 146_097 + doe - 719_468)
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Strips an IEC literal prefix (e.g. "T#", "TIME#"), case-insensitive
+fn strip_literal_prefix<'a>(s: &'a str, prefixes: &[&str]) -> &'a str {
+    let s = s.trim();
+    if let Some((prefix, value)) = s.split_once('#') {
+        if prefixes.iter().any(|p| p.eq_ignore_ascii_case(prefix)) {
+            return value;
+        }
+    }
+    s
+}
+
+fn parse_num<T: FromStr>(s: &str, orig: &str) -> EResult<T> {
+    s.parse()
+        .map_err(|_| Error::invalid_data(format!("invalid value: {}", orig)))
+}
+
+fn parse_date(s: &str, orig: &str) -> EResult<Date> {
+    let mut sp = s.splitn(3, '-');
+    let (year, month, day) = (
+        parse_num(sp.next().unwrap_or_default(), orig)?,
+        parse_num(sp.next().unwrap_or_default(), orig)?,
+        parse_num(sp.next().unwrap_or_default(), orig)?,
+    );
+    Date::from_ymd(year, month, day)
+}
+
+fn parse_tod(s: &str, orig: &str) -> EResult<Tod> {
+    let mut sp = s.splitn(3, ':');
+    let hour = parse_num(sp.next().unwrap_or_default(), orig)?;
+    let min = parse_num(sp.next().unwrap_or_default(), orig)?;
+    let (sec, milli) = if let Some(sec_s) = sp.next() {
+        let (sec, frac) = sec_s.split_once('.').unwrap_or((sec_s, ""));
+        if !frac.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::invalid_data(format!("invalid value: {}", orig)));
+        }
+        // milliseconds, the extra digits are truncated
+        let milli = frac
+            .bytes()
+            .chain(std::iter::repeat(b'0'))
+            .take(3)
+            .fold(0, |acc, b| acc * 10 + u32::from(b - b'0'));
+        (parse_num(sec, orig)?, milli)
+    } else {
+        (0, 0)
+    };
+    Tod::from_hms_milli(hour, min, sec, milli)
+}
+
+/// Parses IEC literals ("T#1h30m", "TIME#1.5s") or seconds ("90")
+impl FromStr for Time {
+    type Err = Error;
+    fn from_str(s: &str) -> EResult<Self> {
+        let value = strip_literal_prefix(s, &["T", "TIME"]).replace('_', "");
+        if let Ok(secs) = value.parse::<f64>() {
+            return Self::from_secs_f64(secs);
+        }
+        let mut ms = 0.0;
+        let mut rest = value.as_str();
+        while !rest.is_empty() {
+            let num_len = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .ok_or_else(|| Error::invalid_data(format!("no time unit: {}", s)))?;
+            let num: f64 = parse_num(&rest[..num_len], s)?;
+            rest = &rest[num_len..];
+            let unit_len = rest
+                .find(|c: char| c.is_ascii_digit() || c == '.')
+                .unwrap_or(rest.len());
+            ms += num
+                * match rest[..unit_len].to_ascii_lowercase().as_str() {
+                    "d" => 86_400_000.0,
+                    "h" => 3_600_000.0,
+                    "m" => 60_000.0,
+                    "s" => 1_000.0,
+                    "ms" => 1.0,
+                    v => return Err(Error::invalid_data(format!("invalid time unit: {}", v))),
+                };
+            rest = &rest[unit_len..];
+        }
+        Self::from_secs_f64(ms / 1_000.0)
+    }
+}
+
+/// Parses "YYYY-MM-DD" or IEC literals ("D#2024-01-31")
+impl FromStr for Date {
+    type Err = Error;
+    #[inline]
+    fn from_str(s: &str) -> EResult<Self> {
+        parse_date(strip_literal_prefix(s, &["D", "DATE"]), s)
+    }
+}
+
+/// Parses "HH:MM[:SS[.mmm]]" or IEC literals ("TOD#12:30:00")
+impl FromStr for Tod {
+    type Err = Error;
+    #[inline]
+    fn from_str(s: &str) -> EResult<Self> {
+        parse_tod(strip_literal_prefix(s, &["TOD", "TIME_OF_DAY"]), s)
+    }
+}
+
+/// Parses "YYYY-MM-DDTHH:MM[:SS]" (a space is allowed instead of "T") or IEC literals
+/// ("DT#2024-01-31-12:30:00")
+impl FromStr for Dt {
+    type Err = Error;
+    fn from_str(s: &str) -> EResult<Self> {
+        let value = strip_literal_prefix(s, &["DT", "DATE_AND_TIME"]);
+        let pos = value
+            .char_indices()
+            .filter(|(_, c)| matches!(c, 'T' | 't' | ' ' | '-'))
+            .nth(2)
+            .map(|(pos, _)| pos)
+            .ok_or_else(|| Error::invalid_data(format!("invalid date and time: {}", s)))?;
+        Self::from_date_tod(
+            parse_date(&value[..pos], s)?,
+            parse_tod(&value[pos + 1..], s)?,
+        )
+    }
+}
+
+/// Formats as an IEC literal, e.g. "T#1h30m"
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 0 {
+            return f.write_str("T#0ms");
+        }
+        f.write_str("T#")?;
+        let mut rest = self.0;
+        for (unit, ms) in [
+            ("d", MS_PER_DAY),
+            ("h", 3_600_000),
+            ("m", 60_000),
+            ("s", 1_000),
+            ("ms", 1),
+        ] {
+            if rest >= ms {
+                write!(f, "{}{}", rest / ms, unit)?;
+                rest %= ms;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.ymd();
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+impl fmt::Display for Tod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (hour, min, sec, milli) = self.hms_milli();
+        write!(f, "{:02}:{:02}:{:02}", hour, min, sec)?;
+        if milli > 0 {
+            write!(f, ".{:03}", milli)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Dt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}T{}", self.date(), self.tod())
+    }
+}
+
+fn value_to_string(value: Value) -> EResult<String> {
+    match value {
+        Value::String(s) => Ok(s),
+        v if v.is_numeric() || matches!(v, Value::Bool(_)) => Ok(v.to_string()),
+        _ => Err(Error::invalid_data("string expected")),
+    }
+}
+
+impl<const N: usize> TryFrom<Value> for IecString<N> {
+    type Error = Error;
+    #[inline]
+    fn try_from(value: Value) -> EResult<Self> {
+        value_to_string(value)?.as_str().try_into()
+    }
+}
+
+impl<const N: usize> TryFrom<Value> for IecWString<N> {
+    type Error = Error;
+    #[inline]
+    fn try_from(value: Value) -> EResult<Self> {
+        value_to_string(value)?.as_str().try_into()
+    }
+}
+
+/// Numbers are seconds
+impl TryFrom<Value> for Time {
+    type Error = Error;
+    fn try_from(value: Value) -> EResult<Self> {
+        if let Value::String(s) = value {
+            s.parse()
+        } else {
+            Self::from_secs_f64(value.try_into()?)
+        }
+    }
+}
+
+/// Numbers are UNIX timestamps, the time of day is dropped
+impl TryFrom<Value> for Date {
+    type Error = Error;
+    fn try_from(value: Value) -> EResult<Self> {
+        if let Value::String(s) = value {
+            s.parse()
+        } else {
+            Ok(Dt::try_from(value)?.date())
+        }
+    }
+}
+
+/// Numbers are seconds since midnight
+impl TryFrom<Value> for Tod {
+    type Error = Error;
+    fn try_from(value: Value) -> EResult<Self> {
+        if let Value::String(s) = value {
+            s.parse()
+        } else {
+            let t = Time::try_from(value)?;
+            if t.0 < MS_PER_DAY {
+                Ok(Self(t.0))
+            } else {
+                Err(Error::invalid_data("TOD out of range"))
+            }
+        }
+    }
+}
+
+/// Numbers are UNIX timestamps
+impl TryFrom<Value> for Dt {
+    type Error = Error;
+    fn try_from(value: Value) -> EResult<Self> {
+        if let Value::String(s) = value {
+            s.parse()
+        } else {
+            let ts: f64 = value.try_into()?;
+            if (0.0..=f64::from(u32::MAX)).contains(&ts) {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                Ok(Self(ts as u32))
+            } else {
+                Err(Error::invalid_data(format!("DT out of range: {}", ts)))
+            }
+        }
+    }
+}
+
+impl From<Time> for Value {
+    #[inline]
+    fn from(t: Time) -> Self {
+        Value::F64(t.as_secs_f64())
+    }
+}
+
+macro_rules! impl_string_value {
+    ($t: ty $(, $n: ident)?) => {
+        impl$(<const $n: usize>)? From<$t> for Value {
+            #[inline]
+            fn from(v: $t) -> Self {
+                Value::String(v.to_string())
+            }
+        }
+    };
+}
+
+impl_string_value!(IecString<N>, N);
+impl_string_value!(IecWString<N>, N);
+impl_string_value!(Date);
+impl_string_value!(Tod);
+impl_string_value!(Dt);
+
+macro_rules! impl_serde {
+    ($t: ty $(, $n: ident)?) => {
+        impl$(<const $n: usize>)? Serialize for $t {
+            #[inline]
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                Value::from(*self).serialize(serializer)
+            }
+        }
+
+        impl<'de $(, const $n: usize)?> Deserialize<'de> for $t {
+            #[inline]
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                Value::deserialize(deserializer)?
+                    .try_into()
+                    .map_err(serde::de::Error::custom)
+            }
+        }
+
+        impl$(<const $n: usize>)? crate::context::PathAccess for $t {
+            fn get_value(&self, idx: &[usize], path: &str) -> EResult<Value> {
+                crate::context::check_value_path(idx, path)?;
+                Ok(Value::from(*self))
+            }
+            fn set_value(&mut self, idx: &[usize], path: &str, value: Value) -> EResult<()> {
+                crate::context::check_value_path(idx, path)?;
+                *self = value.try_into()?;
+                Ok(())
+            }
+            fn list_paths(&self, prefix: &str, result: &mut Vec<String>) {
+                result.push(prefix.to_owned());
+            }
+        }
+    };
+}
+
+impl_serde!(IecString<N>, N);
+impl_serde!(IecWString<N>, N);
+impl_serde!(Time);
+impl_serde!(Date);
+impl_serde!(Tod);
+impl_serde!(Dt);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_strings() {
+        let mut s: IecString<5> = "hello".parse().unwrap();
+        assert_eq!(s, "hello");
+        assert!("hello!".parse::<IecString<5>>().is_err());
+        s.set("приві");
+        assert_eq!(s, "пр");
+        assert_eq!(IecString::<5>::from_bytes(b"ab\0\0\0").unwrap(), "ab");
+        let mut w: IecWString<3> = "añb".parse().unwrap();
+        assert_eq!(w.to_string(), "añb");
+        w.set("a😀b");
+        assert_eq!(w.to_string(), "a😀");
+        w.set("ab😀");
+        assert_eq!(w.to_string(), "ab");
+        let value = crate::retain::to_value(&s).unwrap();
+        assert_eq!(value, Value::String("пр".to_owned()));
+        let restored: IecString<5> = crate::retain::from_value(value).unwrap();
+        assert_eq!(restored, s);
+    }
+
+    #[test]
+    fn test_time() {
+        assert_eq!("T#1h2m3s4ms".parse::<Time>().unwrap(), Time(3_723_004));
+        assert_eq!("time#1.5s".parse::<Time>().unwrap(), Time(1_500));
+        assert_eq!("90".parse::<Time>().unwrap(), Time(90_000));
+        assert!("T#1x".parse::<Time>().is_err());
+        assert_eq!(Time(3_723_004).to_string(), "T#1h2m3s4ms");
+        assert_eq!(Duration::from(Time(1_500)), Duration::from_millis(1_500));
+    }
+
+    #[test]
+    fn test_date_time() {
+        let date: Date = "D#2024-02-29".parse().unwrap();
+        assert_eq!(date.0, 1_709_164_800);
+        assert_eq!(date.ymd(), (2024, 2, 29));
+        assert!("2023-02-29".parse::<Date>().is_err());
+        assert!("1969-12-31".parse::<Date>().is_err());
+        let tod: Tod = "TOD#12:30:05.5".parse().unwrap();
+        assert_eq!(tod, Tod(45_005_500));
+        assert_eq!(tod.to_string(), "12:30:05.500");
+        assert!("24:00".parse::<Tod>().is_err());
+        assert_eq!("12:30:05.12345".parse::<Tod>().unwrap(), Tod(45_005_123));
+        assert!("12:30:05.éé".parse::<Tod>().is_err());
+        assert!("12:30:05.1x".parse::<Tod>().is_err());
+        assert!("T#1é5s".parse::<Time>().is_err());
+        assert!("DT#2024-02-29-12:30:05.é".parse::<Dt>().is_err());
+        let dt: Dt = "DT#2024-02-29-12:30:05".parse().unwrap();
+        assert_eq!(dt, "2024-02-29T12:30:05".parse().unwrap());
+        assert_eq!(dt, "2024-02-29 12:30:05".parse().unwrap());
+        assert_eq!(dt.date(), date);
+        assert_eq!(dt.tod(), Tod(45_005_000));
+        assert_eq!(dt.to_string(), "2024-02-29T12:30:05");
+        assert_eq!(Dt::try_from(Value::U32(dt.0)).unwrap(), dt);
+    }
+}
@@ -1,8 +1,9 @@
-use crate::tasks::{self, ConvX};
+use crate::tasks::{self, ConvX, Heartbeat, TaskControl, TickReport};
 use eva_common::EResult;
 use log::warn;
 use serde::{Deserialize, Deserializer};
 use std::cmp::Ordering;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
 
@@ -15,6 +16,8 @@ pub struct Loop {
     // only Input or Program, used to mark ready
     task_kind: Option<tasks::Kind>,
     marked: bool,
+    heartbeat: Option<Arc<Heartbeat>>,
+    control: Option<Arc<TaskControl>>,
 }
 
 #[negative_impl::negative_impl]
@@ -32,6 +35,7 @@ impl Loop {
     ///
     /// will panic if interval in us > i64::MAX
     pub fn prepare(interval: Duration, shift: Duration, report: bool) -> Self {
+        let mut watched = true;
         let task_kind: Option<tasks::Kind> = if let Some(ch) = tasks::thread_name().chars().next() {
             match ch {
                 'I' => {
@@ -46,12 +50,26 @@ impl Loop {
                     tasks::wait_can_run_output();
                     None
                 }
-                _ => None,
+                // chains wait for the status by themselves
+                'C' => None,
+                _ => {
+                    watched = false;
+                    None
+                }
             }
         } else {
+            watched = false;
             None
         };
         tasks::sleep(shift);
+        let (heartbeat, control) = if watched {
+            (
+                Some(tasks::register_heartbeat(interval)),
+                Some(tasks::register_task_control(Some(interval))),
+            )
+        } else {
+            (None, None)
+        };
         let now = Instant::now();
         Loop {
             next_iter: now + interval,
@@ -61,6 +79,21 @@ impl Loop {
             report,
             task_kind,
             marked: task_kind.is_none(),
+            heartbeat,
+            control,
+        }
+    }
+    /// Returns true if the task has been paused via the API
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.control.as_ref().is_some_and(|c| c.is_paused())
+    }
+    fn apply_interval(&mut self, interval: Duration) {
+        self.next_iter = self.next_iter - self.interval + interval;
+        self.interval = interval;
+        self.int_micros = i64::try_from(interval.as_micros()).unwrap_or(i64::MAX);
+        if let Some(ref heartbeat) = self.heartbeat {
+            heartbeat.set_interval(interval);
         }
     }
 
@@ -71,7 +104,13 @@ impl Loop {
             }
             self.marked = true;
         }
+        if let Some(interval) = self.control.as_ref().and_then(|c| c.interval()) {
+            if interval != self.interval {
+                self.apply_interval(interval);
+            }
+        }
         let t = Instant::now();
+        let exec = t.saturating_duration_since(self.t);
         let result = match t.cmp(&self.next_iter) {
             Ordering::Greater => false,
             Ordering::Equal => true,
@@ -96,14 +135,32 @@ impl Loop {
             #[allow(clippy::cast_possible_truncation)]
             let jitter = (self.int_micros - (t.duration_since(self.t)).as_micros() as i64)
                 .unsigned_abs()
-                .as_u16_max();
-            tasks::report_jitter(jitter);
+                .as_u32_max();
+            tasks::report_tick(TickReport {
+                jitter,
+                exec: exec.as_micros().as_u32_max(),
+                overrun: !result,
+            });
             self.t = t;
         };
+        if let Some(ref heartbeat) = self.heartbeat {
+            heartbeat.beat();
+        }
         result
     }
 }
 
+impl Drop for Loop {
+    fn drop(&mut self) {
+        if let Some(ref heartbeat) = self.heartbeat {
+            tasks::unregister_heartbeat(heartbeat);
+        }
+        if let Some(ref control) = self.control {
+            tasks::unregister_task_control(control);
+        }
+    }
+}
+
 pub(crate) fn parse_interval(s: &str) -> EResult<u64> {
     if let Some(v) = s.strip_suffix("ms") {
         Ok(v.parse::<u64>()? * 1_000_000)
@@ -1,6 +1,8 @@
+use crate::builder::config::{mapping_error, FieldSchema};
 use eva_common::value::Value;
 use eva_common::OID;
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::error::Error;
 
 fn default_cache() -> u64 {
@@ -28,7 +30,7 @@ struct OutputConfig {
 #[derive(Deserialize)]
 #[serde(deny_unknown_fields)]
 struct InputConfig {
-    action_map: Vec<OidMap>,
+    action_map: Vec<ActionMap>,
 }
 
 #[derive(Deserialize)]
@@ -38,6 +40,15 @@ struct OidMap {
     value: String,
 }
 
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ActionMap {
+    oid: OID,
+    value: String,
+    #[serde(default)]
+    trigger: bool,
+}
+
 fn generate_output(id: &str, i: usize, scope: &mut codegen::Scope, output_config: OutputConfig) {
     let mut output_fn = codegen::Function::new(&format!("output_{}_{}", id, i + 1));
     output_fn
@@ -49,13 +60,11 @@ fn generate_output(id: &str, i: usize, scope: &mut codegen::Scope, output_config
             "cache",
             "&mut ::rplc::export::eva_sdk::controller::RawStateCache",
         );
-    let mut block = codegen::Block::new(&format!(
-        "if let Err(e) = output_{}_{}_worker(oids, cache)",
+    output_fn.push_block(crate::io::generate_worker_call(
         id,
-        i + 1
+        &format!("output_{}_{}_worker(oids, cache)", id, i + 1),
+        &[],
     ));
-    block.line("::rplc::export::log::error!(\"{}: {}\", ::rplc::tasks::thread_name(), e);");
-    output_fn.push_block(block);
     let mut worker_fn = codegen::Function::new(&format!("output_{}_{}_worker", id, i + 1));
     worker_fn
         .arg(
@@ -107,25 +116,92 @@ fn generate_input(id: &str, i: usize, scope: &mut codegen::Scope, input_config:
         handler_fn.ret("::rplc::export::eva_common::EResult<()>");
         handler_fn.line("let params = action.take_unit_params()?;");
         handler_fn.line(format!(
-            "CONTEXT.write().{} = params.value.try_into()?;",
-            entry.value
+            "let value = ::rplc::context::clamp(\"{}\", params.value)?;",
+            entry.value.trim()
         ));
+        if entry.trigger {
+            handler_fn.line("let value = value.try_into()?;");
+            let mut changed_block = codegen::Block::new("let changed =");
+            changed_block.line("let mut ctx = CONTEXT.write();");
+            changed_block.line(format!(
+                "let prev = ::std::mem::replace(&mut ctx.{}, value);",
+                entry.value
+            ));
+            changed_block.line("::rplc::force::apply_locked(&mut *ctx);");
+            changed_block.line(format!("prev != ctx.{}", entry.value));
+            changed_block.after(";");
+            handler_fn.push_block(changed_block);
+            handler_fn.line(format!(
+                "if changed {{ ::rplc::trigger::fire(\"ctx.{}\"); }}",
+                entry.value.trim()
+            ));
+        } else {
+            let mut set_block = codegen::Block::new("");
+            set_block.line("let mut ctx = CONTEXT.write();");
+            set_block.line(format!("ctx.{} = value.try_into()?;", entry.value));
+            set_block.line("::rplc::force::apply_locked(&mut *ctx);");
+            handler_fn.push_block(set_block);
+        }
+        handler_fn.line(format!(
+            "::rplc::io::report_ok(\"{id}\", &mut CONTEXT.write().io_status.{id});"
+        ));
+        handler_fn.line(format!("::rplc::trigger::fire(\"io.{id}\");"));
         handler_fn.line("Ok(())");
         scope.push_fn(handler_fn);
     }
 }
 
+/// Validates the mappings against the context schema
+pub(crate) fn validate(
+    id: &str,
+    inputs: &[Value],
+    outputs: &[Value],
+    schema: &FieldSchema,
+) -> Result<(), Box<dyn Error>> {
+    let mut handled: BTreeMap<OID, String> = BTreeMap::new();
+    for (i, input) in inputs.iter().enumerate() {
+        let input_config = InputConfig::deserialize(input.clone())?;
+        for (n, entry) in input_config.action_map.into_iter().enumerate() {
+            let location = format!("io {}, input[{}].action_map[{}].value", id, i, n);
+            schema.resolve_mapping(&location, &entry.value)?;
+            if let Some(other) = handled.insert(entry.oid.clone(), location.clone()) {
+                return Err(mapping_error(
+                    &location,
+                    &entry.value,
+                    format!("actions of {} are already handled by {}", entry.oid, other),
+                ));
+            }
+        }
+    }
+    let mut written: BTreeMap<OID, String> = BTreeMap::new();
+    for (i, output) in outputs.iter().enumerate() {
+        let output_config = OutputConfig::deserialize(output.clone())?;
+        for (n, entry) in output_config.oid_map.into_iter().enumerate() {
+            let location = format!("io {}, output[{}].oid_map[{}].value", id, i, n);
+            schema.resolve_mapping(&location, &entry.value)?;
+            if let Some(other) = written.insert(entry.oid.clone(), location.clone()) {
+                return Err(mapping_error(
+                    &location,
+                    &entry.value,
+                    format!("{} is already written by {}", entry.oid, other),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn generate_io(
     id: &str,
     cfg: &Value,
     inputs: &[Value],
     outputs: &[Value],
 ) -> Result<codegen::Scope, Box<dyn Error>> {
-    let id = id.to_lowercase();
     let mut scope = codegen::Scope::new();
     assert_eq!(cfg, &Value::Unit, "EVA ICS I/O must have no config");
     let mut launch_fn = codegen::Function::new(&format!("launch_datasync_{id}"));
     launch_fn.allow("clippy::redundant_clone, clippy::unreadable_literal");
+    launch_fn.line(format!("::rplc::io::register(\"{id}\");"));
     launch_fn.line("use ::rplc::export::eva_common::OID;");
     for (i, input) in inputs.iter().enumerate() {
         let input_config = InputConfig::deserialize(input.clone())?;
@@ -145,7 +221,7 @@ pub(crate) fn generate_io(
         }
         launch_fn.line("];");
         launch_fn.line("::rplc::eapi::append_action_handlers_bulk(&oids, handlers);");
-        generate_input(&id, i, &mut scope, input_config);
+        generate_input(id, i, &mut scope, input_config);
     }
     for (i, output) in outputs.iter().enumerate() {
         let output_config = OutputConfig::deserialize(output.clone())?;
@@ -170,7 +246,7 @@ pub(crate) fn generate_io(
         block.line(format!("output_{}_{}(&oids, &mut cache);", id, i + 1));
         block.after(");");
         launch_fn.push_block(block);
-        generate_output(&id, i, &mut scope, output_config);
+        generate_output(id, i, &mut scope, output_config);
     }
     scope.push_fn(launch_fn);
     Ok(scope)
@@ -1,4 +1,10 @@
+use crate::clock;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[cfg(feature = "eva")]
 pub mod eapi;
@@ -7,6 +13,8 @@ pub mod modbus;
 #[cfg(feature = "opcua")]
 pub mod opcua;
 
+static IO_STATUS: Lazy<Mutex<BTreeMap<String, IoStatus>>> = Lazy::new(<_>::default);
+
 #[derive(Deserialize, Serialize, Debug, Copy, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum Kind {
@@ -17,3 +25,246 @@ pub enum Kind {
     #[cfg(feature = "eva")]
     Eapi,
 }
+
+/// I/O link status, generated as ctx.io_status.ID for each I/O and updated by its workers
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct IoStatus {
+    /// the last I/O operation has been successful
+    pub online: bool,
+    /// consecutive errors
+    pub errors: u32,
+    #[serde(skip)]
+    pub last_success: Option<Instant>,
+    pub last_error: Option<String>,
+}
+
+impl IoStatus {
+    /// Time elapsed since the last successful I/O operation
+    pub fn since_success(&self) -> Option<Duration> {
+        self.last_success
+            .map(|t| clock::now().saturating_duration_since(t))
+    }
+}
+
+/// Quality of a mapped input value
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum QualityStatus {
+    /// no value has been received yet
+    #[default]
+    Unknown,
+    Good,
+    Uncertain,
+    Bad,
+    /// the value has not been updated for longer than the mapping stale age
+    Stale,
+}
+
+/// Quality and timestamp of a mapped input value, generated as ctx.quality.PATH (non-alphanumeric
+/// characters of the target path are replaced with underscores) for input mappings with
+/// "quality: true"
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Quality {
+    status: QualityStatus,
+    timestamp: Option<f64>,
+    #[serde(skip)]
+    updated: Option<Instant>,
+    #[serde(skip)]
+    stale_after: Option<Duration>,
+}
+
+impl Quality {
+    /// Current quality status, good and uncertain values become stale after the stale age
+    pub fn status(&self) -> QualityStatus {
+        if matches!(self.status, QualityStatus::Good | QualityStatus::Uncertain)
+            && self
+                .stale_after
+                .is_some_and(|max| self.age().is_some_and(|age| age > max))
+        {
+            QualityStatus::Stale
+        } else {
+            self.status
+        }
+    }
+    #[inline]
+    pub fn is_good(&self) -> bool {
+        self.status() == QualityStatus::Good
+    }
+    /// The value timestamp (seconds since the epoch): the source timestamp for OPC-UA, the
+    /// receive time for Modbus
+    #[inline]
+    pub fn timestamp(&self) -> Option<f64> {
+        self.timestamp
+    }
+    /// Time elapsed since the value has been updated
+    pub fn age(&self) -> Option<Duration> {
+        self.updated
+            .map(|t| clock::now().saturating_duration_since(t))
+    }
+    /// Sets the value quality, called by the generated code. If the timestamp is not specified,
+    /// the current system time is used
+    pub fn set(
+        &mut self,
+        status: QualityStatus,
+        timestamp: Option<f64>,
+        stale_after: Option<Duration>,
+    ) {
+        self.status = status;
+        self.timestamp = Some(timestamp.unwrap_or_else(system_timestamp));
+        self.updated = Some(clock::now());
+        self.stale_after = stale_after;
+    }
+    /// Marks the value bad, keeping the timestamp of the last update
+    pub fn set_bad(&mut self) {
+        self.status = QualityStatus::Bad;
+    }
+}
+
+fn system_timestamp() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// Returns the context quality field name for the target path
+pub(crate) fn quality_field(target: &str) -> String {
+    let mut result = String::new();
+    for c in target.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            result.push(c.to_ascii_lowercase());
+        } else if !result.is_empty() && !result.ends_with('_') {
+            result.push('_');
+        }
+    }
+    result.trim_end_matches('_').to_owned()
+}
+
+/// Quality field of an input mapping
+#[allow(dead_code)]
+pub(crate) struct QualityTarget {
+    pub(crate) field: String,
+    pub(crate) stale: Option<u64>,
+}
+
+#[allow(dead_code)]
+impl QualityTarget {
+    pub(crate) fn new(target: &str, stale: Option<u64>) -> Self {
+        Self {
+            field: quality_field(target),
+            stale,
+        }
+    }
+    pub(crate) fn stale_str(&self) -> String {
+        self.stale.map_or_else(
+            || "None".to_owned(),
+            |v| format!("Some(::std::time::Duration::from_nanos({v}))"),
+        )
+    }
+    /// generated code to set the quality when a context lock is held
+    pub(crate) fn set_good(&self, timestamp: &str) -> String {
+        format!(
+            "ctx.quality.{}.set(::rplc::io::QualityStatus::Good, {}, {});",
+            self.field,
+            timestamp,
+            self.stale_str()
+        )
+    }
+    pub(crate) fn set_bad(&self) -> String {
+        format!("ctx.quality.{}.set_bad();", self.field)
+    }
+}
+
+/// Generates the code which marks all quality targets bad, used on I/O errors
+#[allow(dead_code)]
+pub(crate) fn generate_quality_bad(targets: &[QualityTarget]) -> Option<String> {
+    if targets.is_empty() {
+        None
+    } else {
+        let mut code = "{ let mut ctx = CONTEXT.write(); ".to_owned();
+        for t in targets {
+            code.push_str(&t.set_bad());
+        }
+        code.push_str(" }");
+        Some(code)
+    }
+}
+
+/// I/O link status, reported by the API
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct IoStatusInfo {
+    pub online: bool,
+    pub errors: u32,
+    /// seconds since the last successful operation
+    pub last_success: Option<f64>,
+    pub last_error: Option<String>,
+}
+
+impl From<&IoStatus> for IoStatusInfo {
+    fn from(s: &IoStatus) -> Self {
+        Self {
+            online: s.online,
+            errors: s.errors,
+            last_success: s.since_success().map(|v| v.as_secs_f64()),
+            last_error: s.last_error.clone(),
+        }
+    }
+}
+
+/// Registers I/O in the status registry, called by the generated code
+pub fn register(id: &str) {
+    IO_STATUS.lock().entry(id.to_owned()).or_default();
+}
+
+/// Reports a successful I/O operation, called by the generated code
+pub fn report_ok(id: &str, status: &mut IoStatus) {
+    if status.errors > 0 {
+        info!("I/O {} is back online", id);
+    }
+    status.online = true;
+    status.errors = 0;
+    status.last_success = Some(clock::now());
+    IO_STATUS.lock().insert(id.to_owned(), status.clone());
+}
+
+/// Reports a failed I/O operation, called by the generated code
+pub fn report_error(id: &str, status: &mut IoStatus, error: &str) {
+    if status.errors == 0 {
+        warn!("I/O {} is degraded", id);
+    }
+    status.online = false;
+    status.errors = status.errors.saturating_add(1);
+    status.last_error = Some(error.to_owned());
+    IO_STATUS.lock().insert(id.to_owned(), status.clone());
+}
+
+/// Returns true if any I/O has failed its last operation
+pub fn degraded() -> bool {
+    IO_STATUS.lock().values().any(|s| s.errors > 0)
+}
+
+pub(crate) fn status_info() -> BTreeMap<String, IoStatusInfo> {
+    IO_STATUS
+        .lock()
+        .iter()
+        .map(|(id, s)| (id.clone(), s.into()))
+        .collect()
+}
+
+/// Generates a worker call, which logs errors and updates ctx.io_status.ID
+#[allow(dead_code)]
+pub(crate) fn generate_worker_call(id: &str, call: &str, on_error: &[String]) -> codegen::Block {
+    let mut block = codegen::Block::new(&format!("match {call}"));
+    block.line(format!(
+        "Ok(()) => ::rplc::io::report_ok(\"{id}\", &mut CONTEXT.write().io_status.{id}),"
+    ));
+    let mut err_block = codegen::Block::new("Err(e) =>");
+    for line in on_error {
+        err_block.line(line);
+    }
+    err_block.line("::rplc::export::log::error!(\"{}: {}\", ::rplc::tasks::thread_name(), e);");
+    err_block.line(format!(
+        "::rplc::io::report_error(\"{id}\", &mut CONTEXT.write().io_status.{id}, &e.to_string());"
+    ));
+    block.push_block(err_block);
+    block
+}
@@ -1,10 +1,12 @@
+use crate::builder::config::{mapping_error, FieldSchema};
+use crate::io::QualityTarget;
 use crate::tasks;
 use eva_common::value::Value;
 use serde::Deserialize;
 use std::error::Error;
 use std::fmt::Write as _;
 use std::net::SocketAddr;
-pub use types::{Coils, Registers, SwapModbusEndianess};
+pub use types::{CoilSlice, Coils, RegisterEnum, RegisterSlice, Registers, SwapModbusEndianess};
 
 mod regs;
 mod types;
@@ -51,6 +53,25 @@ struct RegMapInput {
     #[serde(default, flatten)]
     offset: regs::MapOffset,
     target: String,
+    #[serde(default)]
+    trigger: bool,
+    #[serde(default)]
+    quality: bool,
+    #[serde(
+        default,
+        deserialize_with = "crate::interval::deserialize_opt_interval_as_nanos"
+    )]
+    stale: Option<u64>,
+}
+
+impl RegMapInput {
+    fn quality_target(&self) -> Option<QualityTarget> {
+        if self.quality {
+            Some(QualityTarget::new(&self.target, self.stale))
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -158,10 +179,18 @@ fn push_input_worker(
 ) {
     let f_input = scope.new_fn(&format!("input_{id}_{num}"));
     f_input.arg("comm", "&::rplc::comm::Communicator");
-    let mut loop_block =
-        codegen::Block::new(&format!("if let Err(e) = input_{id}_{num}_worker(comm)"));
-    loop_block.line("::rplc::export::log::error!(\"{}: {}\", ::rplc::tasks::thread_name(), e);");
-    f_input.push_block(loop_block);
+    let quality_targets: Vec<QualityTarget> = config
+        .map
+        .iter()
+        .filter_map(RegMapInput::quality_target)
+        .collect();
+    f_input.push_block(crate::io::generate_worker_call(
+        id,
+        &format!("input_{id}_{num}_worker(comm)"),
+        &crate::io::generate_quality_bad(&quality_targets)
+            .into_iter()
+            .collect::<Vec<String>>(),
+    ));
     let f_input_worker = scope.new_fn(&format!("input_{id}_{num}_worker"));
     f_input_worker.arg("comm", "&::rplc::comm::Communicator");
     f_input_worker.ret("Result<(), Box<dyn ::std::error::Error>>");
@@ -224,29 +253,69 @@ fn push_input_worker(
             f_input_worker.line("let regs = Registers(data);");
         }
     }
+    let mut triggers = Vec::new();
     if !config.map.is_empty() {
         let mut cp_block = codegen::Block::new("");
         cp_block.line("let mut ctx = CONTEXT.write();");
         for i in config.map {
             cp_block.line(format!("// {}", i.target));
+            let quality = i.quality_target();
             let mut cp_block_try_into = codegen::Block::new("match slice.try_into()");
-            cp_block_try_into.line(format!("Ok(v) => ctx.{} = v,", i.target));
-            cp_block_try_into.line(format!(
-                "Err(e) => ::rplc::export::log::error!(\"modbus ctx.{} set err: {{}}\", e)",
+            let mut ok_block = codegen::Block::new("Ok(v) =>");
+            if i.trigger {
+                ok_block.line(format!(
+                    "let prev = ::std::mem::replace(&mut ctx.{}, v);",
+                    i.target
+                ));
+                ok_block.line(format!(
+                    "if prev != ctx.{} {{ changed_{} = true; }}",
+                    i.target,
+                    triggers.len()
+                ));
+                triggers.push(i.target.trim().to_owned());
+            } else {
+                ok_block.line(format!("ctx.{} = v;", i.target));
+            }
+            if let Some(ref q) = quality {
+                ok_block.line(q.set_good("None"));
+            }
+            cp_block_try_into.push_block(ok_block);
+            let mut err_block = codegen::Block::new("Err(e) =>");
+            err_block.line(format!(
+                "::rplc::export::log::error!(\"modbus ctx.{} set err: {{}}\", e);",
                 i.target
             ));
+            if let Some(ref q) = quality {
+                err_block.line(q.set_bad());
+            }
+            cp_block_try_into.push_block(err_block);
             let mut cp_block_match_slice_at =
                 codegen::Block::new(&format!("match regs.slice_at({})", i.offset.offset()));
             cp_block_match_slice_at.line("Ok(slice) => ");
             cp_block_match_slice_at.push_block(cp_block_try_into);
-            cp_block_match_slice_at.line(format!(
-                "Err(e) => ::rplc::export::log::error!(\"modbus slice err ctx.{}: {{}}\", e)",
+            let mut slice_err_block = codegen::Block::new("Err(e) =>");
+            slice_err_block.line(format!(
+                "::rplc::export::log::error!(\"modbus slice err ctx.{}: {{}}\", e);",
                 i.target
             ));
+            if let Some(ref q) = quality {
+                slice_err_block.line(q.set_bad());
+            }
+            cp_block_match_slice_at.push_block(slice_err_block);
             cp_block.push_block(cp_block_match_slice_at);
         }
+        cp_block.line("::rplc::force::apply_locked(&mut *ctx);");
+        for i in 0..triggers.len() {
+            f_input_worker.line(format!("let mut changed_{i} = false;"));
+        }
         f_input_worker.push_block(cp_block);
     }
+    for (i, trigger) in triggers.iter().enumerate() {
+        f_input_worker.line(format!(
+            "if changed_{i} {{ ::rplc::trigger::fire(\"ctx.{trigger}\"); }}"
+        ));
+    }
+    f_input_worker.line(format!("::rplc::trigger::fire(\"io.{id}\");"));
     f_input_worker.line("Ok(())");
 }
 
@@ -259,10 +328,11 @@ fn push_output_worker(
 ) {
     let f_output = scope.new_fn(&format!("output_{id}_{num}"));
     f_output.arg("comm", "&::rplc::comm::Communicator");
-    let mut loop_block =
-        codegen::Block::new(&format!("if let Err(e) = output_{id}_{num}_worker(comm)"));
-    loop_block.line("::rplc::export::log::error!(\"{}: {}\", ::rplc::tasks::thread_name(), e);");
-    f_output.push_block(loop_block);
+    f_output.push_block(crate::io::generate_worker_call(
+        id,
+        &format!("output_{id}_{num}_worker(comm)"),
+        &[],
+    ));
     let f_output_worker = scope.new_fn(&format!("output_{id}_{num}_worker"));
     f_output_worker.arg("comm", "&::rplc::comm::Communicator");
     f_output_worker.ret("Result<(), Box<dyn ::std::error::Error>>");
@@ -332,17 +402,205 @@ fn push_output_worker(
     f_output_worker.line("Ok(())");
 }
 
+/// Returns context quality fields of input mappings
+pub(crate) fn quality_fields(inputs: &[Value]) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut result = Vec::new();
+    for input in inputs {
+        let input_config = InputConfig::deserialize(input.clone())?;
+        for m in &input_config.map {
+            if m.stale.is_some() && !m.quality {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "ctx.{}: stale can be set for quality mappings only",
+                    m.target
+                ))
+                .into());
+            }
+            if let Some(q) = m.quality_target() {
+                result.push(q.field);
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn kind_units(kind: regs::Kind) -> &'static str {
+    match kind {
+        regs::Kind::Coil => "coils",
+        regs::Kind::Discrete => "discrete inputs",
+        regs::Kind::Input => "input registers",
+        regs::Kind::Holding => "holding registers",
+    }
+}
+
+/// Returns the number of registers/coils of a mapped context field, None for custom types,
+/// which are mapped with their own register conversions
+fn mapped_size(node: &FieldSchema, kind: regs::Kind) -> Result<Option<usize>, String> {
+    let (size, tp) = match node {
+        FieldSchema::Array(size, sub) => (*size, sub.as_ref()),
+        _ => (1, node),
+    };
+    let unit = match (kind, tp) {
+        (_, FieldSchema::Custom(_)) => return Ok(None),
+        (regs::Kind::Coil | regs::Kind::Discrete, FieldSchema::Value(t)) if t == "bool" => Some(1),
+        (regs::Kind::Input | regs::Kind::Holding, FieldSchema::Value(t)) => match t.as_str() {
+            "u16" | "i16" => Some(1),
+            "u32" | "i32" | "f32" | "::rplc::iec::Time" | "::rplc::iec::Date"
+            | "::rplc::iec::Tod" | "::rplc::iec::Dt" => Some(2),
+            "u64" | "i64" | "f64" => Some(4),
+            _ => {
+                if let Some(len) = t
+                    .strip_prefix("::rplc::iec::IecString<")
+                    .and_then(|v| v.strip_suffix('>'))
+                {
+                    len.parse::<usize>().ok().map(|l| l.div_ceil(2))
+                } else {
+                    t.strip_prefix("::rplc::iec::IecWString<")
+                        .and_then(|v| v.strip_suffix('>'))
+                        .and_then(|len| len.parse().ok())
+                }
+            }
+        },
+        (regs::Kind::Input | regs::Kind::Holding, FieldSchema::Enum(_, raw)) => {
+            match raw.as_str() {
+                "u64" | "i64" => Some(4),
+                "u32" | "i32" => Some(2),
+                _ => Some(1),
+            }
+        }
+        _ => None,
+    };
+    unit.map(|u| Some(u * size)).ok_or_else(|| {
+        format!(
+            "{} can not be mapped to {}",
+            node.type_name(),
+            kind_units(kind)
+        )
+    })
+}
+
+/// Checks that a mapping fits the register block and does not overlap other mappings
+fn check_map_range(
+    location: &str,
+    path: &str,
+    reg: &regs::Reg,
+    offset: u16,
+    node: Option<&FieldSchema>,
+    ranges: &mut Vec<(String, usize, usize)>,
+) -> Result<(), Box<dyn Error>> {
+    // fields of custom types have no known register layout, they are not checked
+    let size = if let Some(n) = node {
+        match mapped_size(n, reg.kind()).map_err(|e| mapping_error(location, path, e))? {
+            Some(v) => v,
+            None => return Ok(()),
+        }
+    } else {
+        return Ok(());
+    };
+    let (start, end) = (usize::from(offset), usize::from(offset) + size);
+    if end > usize::from(reg.number()) {
+        return Err(mapping_error(
+            location,
+            path,
+            format!(
+                "{} takes {} {} at offset {}, the block has {}",
+                node.map(FieldSchema::type_name).unwrap_or_default(),
+                size,
+                kind_units(reg.kind()),
+                offset,
+                reg.number()
+            ),
+        ));
+    }
+    if let Some((other, ..)) = ranges.iter().find(|(_, s, e)| start < *e && *s < end) {
+        return Err(mapping_error(
+            location,
+            path,
+            format!("the registers overlap with {}", other),
+        ));
+    }
+    ranges.push((location.to_owned(), start, end));
+    Ok(())
+}
+
+/// Validates the mappings against the context schema
+pub(crate) fn validate(
+    id: &str,
+    inputs: &[Value],
+    outputs: &[Value],
+    schema: &FieldSchema,
+) -> Result<(), Box<dyn Error>> {
+    for (i, input) in inputs.iter().enumerate() {
+        let input_config = InputConfig::deserialize(input.clone())?;
+        let mut ranges = Vec::new();
+        for (n, m) in input_config.map.into_iter().enumerate() {
+            let location = format!("io {}, input[{}].map[{}].target", id, i, n);
+            let mut offset = m.offset;
+            offset
+                .normalize(input_config.reg.offset())
+                .map_err(|e| mapping_error(&location, &m.target, e))?;
+            let node = schema.resolve_mapping(&location, &m.target)?;
+            check_map_range(
+                &location,
+                &m.target,
+                &input_config.reg,
+                offset.offset(),
+                node,
+                &mut ranges,
+            )?;
+        }
+    }
+    // output blocks of the same unit must not write the same registers
+    let mut blocks: Vec<(String, u8, regs::Kind, usize, usize)> = Vec::new();
+    for (i, output) in outputs.iter().enumerate() {
+        let output_config = OutputConfig::deserialize(output.clone())?;
+        let reg = &output_config.reg;
+        let location = format!("io {}, output[{}]", id, i);
+        let (start, end) = (
+            usize::from(reg.offset()),
+            usize::from(reg.offset()) + usize::from(reg.number()),
+        );
+        if let Some((other, ..)) = blocks.iter().find(|(_, unit, kind, s, e)| {
+            *unit == output_config.unit && *kind == reg.kind() && start < *e && *s < end
+        }) {
+            return Err(eva_common::Error::invalid_params(format!(
+                "plc.yml, {}: the registers overlap with {}",
+                location, other
+            ))
+            .into());
+        }
+        blocks.push((location, output_config.unit, reg.kind(), start, end));
+        let mut ranges = Vec::new();
+        for (n, m) in output_config.map.iter().enumerate() {
+            let location = format!("io {}, output[{}].map[{}].source", id, i, n);
+            let mut offset = m.offset.clone();
+            offset
+                .normalize(reg.offset())
+                .map_err(|e| mapping_error(&location, &m.source, e))?;
+            let node = schema.resolve_mapping(&location, &m.source)?;
+            check_map_range(
+                &location,
+                &m.source,
+                reg,
+                offset.offset(),
+                node,
+                &mut ranges,
+            )?;
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn generate_io(
     id: &str,
     cfg: &Value,
     inputs: &[Value],
     outputs: &[Value],
 ) -> Result<codegen::Scope, Box<dyn Error>> {
-    let id = id.to_lowercase();
     let mut scope = codegen::Scope::new();
     let config = Config::deserialize(cfg.clone())?;
     let mut launch_fn = codegen::Function::new(&format!("launch_datasync_{id}"));
     launch_fn.allow("clippy::redundant_clone, clippy::unreadable_literal");
+    launch_fn.line(format!("::rplc::io::register(\"{id}\");"));
     launch_fn.line(format!(
         "let comm_obj = {};",
         config
@@ -362,10 +620,10 @@ pub(crate) fn generate_io(
             input_config.sync,
             input_config.shift.unwrap_or_default(),
             i + 1,
-            &id,
+            id,
             &mut launch_fn,
         );
-        push_input_worker(i + 1, &id, input_config, config.proto, &mut scope);
+        push_input_worker(i + 1, id, input_config, config.proto, &mut scope);
     }
     for (i, output) in outputs.iter().enumerate() {
         let mut output_config = OutputConfig::deserialize(output.clone())?;
@@ -377,10 +635,10 @@ pub(crate) fn generate_io(
             output_config.sync,
             output_config.shift.unwrap_or_default(),
             i + 1,
-            &id,
+            id,
             &mut launch_fn,
         );
-        push_output_worker(i + 1, &id, output_config, config.proto, &mut scope);
+        push_output_worker(i + 1, id, output_config, config.proto, &mut scope);
     }
     scope.push_fn(launch_fn);
     Ok(scope)
@@ -1,3 +1,4 @@
+use crate::iec::{Date, Dt, IecString, IecWString, Time, Tod};
 use eva_common::{EResult, Error, ErrorKind};
 use std::ops::{Deref, DerefMut};
 
@@ -498,6 +499,195 @@ impl<'a, const N: usize> TryFrom<RegisterSlice<'a>> for [f64; N] {
     }
 }
 
+macro_rules! impl_u32_newtype {
+    ($t: ty) => {
+        impl From<&$t> for Registers {
+            fn from(s: &$t) -> Registers {
+                Registers::from(&s.0)
+            }
+        }
+
+        impl<const N: usize> From<&[$t; N]> for Registers {
+            fn from(s: &[$t; N]) -> Registers {
+                let mut result = Vec::with_capacity(N * 2);
+                for v in s {
+                    result.extend(u32_to_modbus_array(v.0));
+                }
+                Registers(result)
+            }
+        }
+
+        impl<'a> TryFrom<RegisterSlice<'a>> for $t {
+            type Error = Error;
+            fn try_from(s: RegisterSlice) -> Result<$t, Self::Error> {
+                u32::try_from(s).map(<$t>::from_raw)
+            }
+        }
+
+        impl<'a, const N: usize> TryFrom<RegisterSlice<'a>> for [$t; N] {
+            type Error = Error;
+            fn try_from(s: RegisterSlice) -> Result<[$t; N], Self::Error> {
+                let values: [u32; N] = s.try_into()?;
+                Ok(values.map(<$t>::from_raw))
+            }
+        }
+    };
+}
+
+trait FromRaw {
+    fn from_raw(v: u32) -> Self;
+}
+
+impl FromRaw for Time {
+    fn from_raw(v: u32) -> Self {
+        Self(v)
+    }
+}
+
+impl FromRaw for Date {
+    fn from_raw(v: u32) -> Self {
+        Self(v - v % 86_400)
+    }
+}
+
+impl FromRaw for Tod {
+    fn from_raw(v: u32) -> Self {
+        Self(v % 86_400_000)
+    }
+}
+
+impl FromRaw for Dt {
+    fn from_raw(v: u32) -> Self {
+        Self(v)
+    }
+}
+
+impl_u32_newtype!(Time);
+impl_u32_newtype!(Date);
+impl_u32_newtype!(Tod);
+impl_u32_newtype!(Dt);
+
+fn string_to_registers<const L: usize>(s: &IecString<L>, result: &mut Vec<u16>) {
+    for chunk in s.as_bytes_padded().chunks(2) {
+        result.push(u16::from_be_bytes([
+            chunk[0],
+            chunk.get(1).copied().unwrap_or_default(),
+        ]));
+    }
+}
+
+fn string_from_registers<const L: usize>(s: &[u16]) -> Result<IecString<L>, Error> {
+    let len = L.div_ceil(2);
+    if len > s.len() {
+        return Err(invalid_data!());
+    }
+    let bytes: Vec<u8> = s[..len].iter().flat_map(|v| v.to_be_bytes()).collect();
+    IecString::from_bytes(&bytes[..L])
+}
+
+impl<const L: usize> From<&IecString<L>> for Registers {
+    fn from(s: &IecString<L>) -> Registers {
+        let mut result = Vec::with_capacity(L.div_ceil(2));
+        string_to_registers(s, &mut result);
+        Registers(result)
+    }
+}
+
+impl<const L: usize, const N: usize> From<&[IecString<L>; N]> for Registers {
+    fn from(s: &[IecString<L>; N]) -> Registers {
+        let mut result = Vec::with_capacity(L.div_ceil(2) * N);
+        for v in s {
+            string_to_registers(v, &mut result);
+        }
+        Registers(result)
+    }
+}
+
+impl<'a, const L: usize> TryFrom<RegisterSlice<'a>> for IecString<L> {
+    type Error = Error;
+    fn try_from(s: RegisterSlice) -> Result<IecString<L>, Self::Error> {
+        string_from_registers(&s)
+    }
+}
+
+impl<'a, const L: usize, const N: usize> TryFrom<RegisterSlice<'a>> for [IecString<L>; N] {
+    type Error = Error;
+    fn try_from(s: RegisterSlice) -> Result<[IecString<L>; N], Self::Error> {
+        let len = L.div_ceil(2);
+        let mut result = [IecString::new(); N];
+        for (i, v) in result.iter_mut().enumerate() {
+            *v = string_from_registers(s.get(i * len..).ok_or_else(|| invalid_data!())?)?;
+        }
+        Ok(result)
+    }
+}
+
+impl<const L: usize> From<&IecWString<L>> for Registers {
+    fn from(s: &IecWString<L>) -> Registers {
+        Registers(s.as_utf16_padded().to_vec())
+    }
+}
+
+impl<const L: usize, const N: usize> From<&[IecWString<L>; N]> for Registers {
+    fn from(s: &[IecWString<L>; N]) -> Registers {
+        let mut result = Vec::with_capacity(L * N);
+        for v in s {
+            result.extend(v.as_utf16_padded());
+        }
+        Registers(result)
+    }
+}
+
+impl<'a, const L: usize> TryFrom<RegisterSlice<'a>> for IecWString<L> {
+    type Error = Error;
+    fn try_from(s: RegisterSlice) -> Result<IecWString<L>, Self::Error> {
+        IecWString::from_utf16(s.get(..L).ok_or_else(|| invalid_data!())?)
+    }
+}
+
+impl<'a, const L: usize, const N: usize> TryFrom<RegisterSlice<'a>> for [IecWString<L>; N] {
+    type Error = Error;
+    fn try_from(s: RegisterSlice) -> Result<[IecWString<L>; N], Self::Error> {
+        let mut result = [IecWString::new(); N];
+        for (i, v) in result.iter_mut().enumerate() {
+            *v = IecWString::from_utf16(s.get(i * L..(i + 1) * L).ok_or_else(|| invalid_data!())?)?;
+        }
+        Ok(result)
+    }
+}
+
+/// Implemented by generated enum types to map their arrays to registers
+pub trait RegisterEnum: Copy + Default {
+    /// Registers taken by a single value
+    const SIZE: usize;
+    fn to_registers(&self) -> Registers;
+    fn from_registers(s: RegisterSlice) -> EResult<Self>;
+}
+
+impl<T: RegisterEnum, const N: usize> From<&[T; N]> for Registers {
+    fn from(s: &[T; N]) -> Registers {
+        let mut result = Vec::with_capacity(T::SIZE * N);
+        for v in s {
+            result.extend(v.to_registers().0);
+        }
+        Registers(result)
+    }
+}
+
+impl<'a, T: RegisterEnum, const N: usize> TryFrom<RegisterSlice<'a>> for [T; N] {
+    type Error = Error;
+    fn try_from(s: RegisterSlice) -> Result<[T; N], Self::Error> {
+        let mut result = [T::default(); N];
+        for (i, v) in result.iter_mut().enumerate() {
+            *v = T::from_registers(RegisterSlice(
+                s.get(i * T::SIZE..(i + 1) * T::SIZE)
+                    .ok_or_else(|| invalid_data!())?,
+            ))?;
+        }
+        Ok(result)
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -701,4 +891,77 @@ mod test {
         let arr2: [f64; 4] = regs.slice_at(0).unwrap().try_into().unwrap();
         assert_eq!(arr, arr2);
     }
+
+    #[test]
+    fn test_iec() {
+        let s: IecString<5> = "hello".parse().unwrap();
+        let regs = Registers::from(&s);
+        assert_eq!(regs.0, vec![0x6865, 0x6c6c, 0x6f00]);
+        let s2: IecString<5> = regs.slice_at(0).unwrap().try_into().unwrap();
+        assert_eq!(s2, s);
+        let regs = Registers(vec![0x6869, 0, 0, 0x6162, 0x6300, 0]);
+        let arr: [IecString<5>; 2] = regs.slice_at(0).unwrap().try_into().unwrap();
+        assert_eq!(arr[0], "hi");
+        assert_eq!(arr[1], "abc");
+        let w: IecWString<3> = "añ".parse().unwrap();
+        let regs = Registers::from(&w);
+        assert_eq!(regs.0, vec![0x61, 0xf1, 0]);
+        let w2: IecWString<3> = regs.slice_at(0).unwrap().try_into().unwrap();
+        assert_eq!(w2, w);
+        let dt: Dt = "2024-02-29T12:30:05".parse().unwrap();
+        let regs = Registers::from(&dt);
+        let dt2: Dt = regs.slice_at(0).unwrap().try_into().unwrap();
+        assert_eq!(dt2, dt);
+        let t = [Time(1_500), Time(70_000)];
+        let regs = Registers::from(&t);
+        assert_eq!(regs.0, vec![0, 1_500, 1, 4_464]);
+        let t2: [Time; 2] = regs.slice_at(0).unwrap().try_into().unwrap();
+        assert_eq!(t2, t);
+    }
+
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    enum Mode {
+        #[default]
+        Off,
+        Auto,
+    }
+
+    impl From<&Mode> for Registers {
+        fn from(v: &Mode) -> Self {
+            Self::from(&(*v as u32))
+        }
+    }
+
+    impl<'a> TryFrom<RegisterSlice<'a>> for Mode {
+        type Error = Error;
+        fn try_from(s: RegisterSlice) -> Result<Self, Self::Error> {
+            match u32::try_from(s)? {
+                0 => Ok(Mode::Off),
+                1 => Ok(Mode::Auto),
+                _ => Err(invalid_data!()),
+            }
+        }
+    }
+
+    impl RegisterEnum for Mode {
+        const SIZE: usize = 2;
+        fn to_registers(&self) -> Registers {
+            self.into()
+        }
+        fn from_registers(s: RegisterSlice) -> EResult<Self> {
+            s.try_into()
+        }
+    }
+
+    #[test]
+    fn test_enum() {
+        let modes = [Mode::Auto, Mode::Off, Mode::Auto];
+        let regs = Registers::from(&modes);
+        assert_eq!(regs.0, vec![0, 1, 0, 0, 0, 1]);
+        let modes2: [Mode; 3] = regs.slice_at(0).unwrap().try_into().unwrap();
+        assert_eq!(modes2, modes);
+        assert!(TryInto::<[Mode; 3]>::try_into(regs.slice_at(2).unwrap()).is_err());
+        let regs = Registers(vec![0, 2]);
+        assert!(TryInto::<[Mode; 1]>::try_into(regs.slice_at(0).unwrap()).is_err());
+    }
 }
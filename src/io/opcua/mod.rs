@@ -1,13 +1,17 @@
+use crate::builder::config::{mapping_error, FieldSchema};
+use crate::io::{QualityStatus, QualityTarget};
 use crate::tasks;
 pub use cache::OpcCache;
 use eva_common::value::Value;
 use serde::Deserialize;
 pub use session::{OpcSafeSess, OpcSafeSession};
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::time::Duration;
 
 mod cache;
 mod session;
+mod types;
 
 #[derive(Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -47,6 +51,44 @@ struct OutputConfig {
 struct NodeMap {
     id: String,
     map: String,
+    #[serde(default)]
+    trigger: bool,
+    #[serde(default)]
+    quality: bool,
+    #[serde(
+        default,
+        deserialize_with = "crate::interval::deserialize_opt_interval_as_nanos"
+    )]
+    stale: Option<u64>,
+}
+
+impl NodeMap {
+    fn quality_target(&self) -> Option<QualityTarget> {
+        if self.quality {
+            Some(QualityTarget::new(&self.map, self.stale))
+        } else {
+            None
+        }
+    }
+}
+
+/// Converts OPC-UA status code to value quality status, used by the generated code
+pub fn quality_status(status: opcua::types::StatusCode) -> QualityStatus {
+    if status.is_good() {
+        QualityStatus::Good
+    } else if status.is_uncertain() {
+        QualityStatus::Uncertain
+    } else {
+        QualityStatus::Bad
+    }
+}
+
+/// Converts OPC-UA date/time to a timestamp (seconds since the epoch), used by the generated
+/// code
+#[allow(clippy::cast_precision_loss)]
+pub fn timestamp(dt: &opcua::types::DateTime) -> f64 {
+    let t = dt.as_chrono();
+    t.timestamp() as f64 + f64::from(t.timestamp_subsec_nanos()) / 1_000_000_000.0
 }
 
 fn default_timeout() -> f64 {
@@ -138,12 +180,18 @@ fn push_input_worker(num: usize, id: &str, config: InputConfig, scope: &mut code
         "node_ids",
         "&[::rplc::export::opcua::types::node_id::NodeId]",
     );
-    let mut loop_block = codegen::Block::new(&format!(
-        "if let Err(e) = input_{id}_{num}_worker(session, node_ids)"
+    let quality_targets: Vec<QualityTarget> = config
+        .nodes
+        .iter()
+        .filter_map(NodeMap::quality_target)
+        .collect();
+    let mut on_error = vec!["session.reconnect();".to_owned()];
+    on_error.extend(crate::io::generate_quality_bad(&quality_targets));
+    f_input.push_block(crate::io::generate_worker_call(
+        id,
+        &format!("input_{id}_{num}_worker(session, node_ids)"),
+        &on_error,
     ));
-    loop_block.line("session.reconnect();");
-    loop_block.line("::rplc::export::log::error!(\"{}: {}\", ::rplc::tasks::thread_name(), e);");
-    f_input.push_block(loop_block);
     let f_input_worker = scope.new_fn(&format!("input_{id}_{num}_worker"));
     f_input_worker.arg("session", "&::rplc::io::opcua::OpcSafeSession");
     f_input_worker.arg(
@@ -164,22 +212,64 @@ fn push_input_worker(num: usize, id: &str, config: InputConfig, scope: &mut code
         ));
     }
     f_input_worker.line("];");
-    f_input_worker.line("let result = session.read(&to_read, TimestampsToReturn::Neither, 0.0)??;");
+    f_input_worker.line(format!(
+        "let result = session.read(&to_read, TimestampsToReturn::{}, 0.0)??;",
+        if quality_targets.is_empty() {
+            "Neither"
+        } else {
+            "Source"
+        }
+    ));
+    let mut triggers = Vec::new();
+    for (i, node) in config.nodes.iter().enumerate() {
+        if node.trigger {
+            f_input_worker.line(format!("let mut changed_{i} = false;"));
+            triggers.push((i, node.map.trim().to_owned()));
+        }
+    }
     f_input_worker.line("let mut ctx = CONTEXT.write();");
     let mut for_block = codegen::Block::new("for (i, res) in result.into_iter().enumerate()");
     let mut match_idx_block = codegen::Block::new("match i");
     for (i, node) in config.nodes.into_iter().enumerate() {
+        let quality = node.quality_target();
         let mut idx_block = codegen::Block::new(&format!("{i} =>"));
+        if quality.is_some() {
+            idx_block.line("let status = ::rplc::io::opcua::quality_status(res.status());");
+            idx_block
+                .line("let ts = res.source_timestamp.as_ref().map(::rplc::io::opcua::timestamp);");
+        }
         let mut val_block = codegen::Block::new("if let Some(value) = res.value");
         let mut val_into_block = codegen::Block::new("if let Ok(v) = value.try_into()");
-        val_into_block.line(format!("ctx.{} = v;", node.map));
+        if node.trigger {
+            val_into_block.line(format!(
+                "let prev = ::std::mem::replace(&mut ctx.{}, v);",
+                node.map
+            ));
+            val_into_block.line(format!(
+                "if prev != ctx.{} {{ changed_{i} = true; }}",
+                node.map
+            ));
+        } else {
+            val_into_block.line(format!("ctx.{} = v;", node.map));
+        }
+        let set_bad = quality
+            .as_ref()
+            .map(QualityTarget::set_bad)
+            .unwrap_or_default();
+        if let Some(ref q) = quality {
+            val_into_block.line(format!(
+                "ctx.quality.{}.set(status, ts, {});",
+                q.field,
+                q.stale_str()
+            ));
+        }
         val_into_block.after(&format!(
-            " else {{ ::rplc::export::log::error!(\"OPC error set OPC {{}} to ctx.{{}}\", node_ids[{i}], \"{}\"); }}",
+            " else {{ ::rplc::export::log::error!(\"OPC error set OPC {{}} to ctx.{{}}\", node_ids[{i}], \"{}\"); {set_bad} }}",
             node.map
         ));
         val_block.push_block(val_into_block);
         val_block.after(&format!(
-            " else {{ ::rplc::export::log::error!(\"OPC read error {{}}\", node_ids[{i}]); }}"
+            " else {{ ::rplc::export::log::error!(\"OPC read error {{}}\", node_ids[{i}]); {set_bad} }}"
         ));
         idx_block.push_block(val_block);
         match_idx_block.push_block(idx_block);
@@ -187,6 +277,14 @@ fn push_input_worker(num: usize, id: &str, config: InputConfig, scope: &mut code
     match_idx_block.push_block(codegen::Block::new("_ =>"));
     for_block.push_block(match_idx_block);
     f_input_worker.push_block(for_block);
+    f_input_worker.line("::rplc::force::apply_locked(&mut *ctx);");
+    f_input_worker.line("drop(ctx);");
+    for (i, trigger) in triggers {
+        f_input_worker.line(format!(
+            "if changed_{i} {{ ::rplc::trigger::fire(\"ctx.{trigger}\"); }}"
+        ));
+    }
+    f_input_worker.line(format!("::rplc::trigger::fire(\"io.{id}\");"));
     f_input_worker.line("Ok(())");
 }
 
@@ -198,11 +296,11 @@ fn push_output_worker(num: usize, id: &str, config: OutputConfig, scope: &mut co
         "&[::rplc::export::opcua::types::node_id::NodeId]",
     );
     f_output.arg("cache", "&mut ::rplc::io::opcua::OpcCache");
-    let mut loop_block = codegen::Block::new(&format!(
-        "if let Err(e) = output_{id}_{num}_worker(session, node_ids, cache)"
+    f_output.push_block(crate::io::generate_worker_call(
+        id,
+        &format!("output_{id}_{num}_worker(session, node_ids, cache)"),
+        &[],
     ));
-    loop_block.line("::rplc::export::log::error!(\"{}: {}\", ::rplc::tasks::thread_name(), e);");
-    f_output.push_block(loop_block);
     let f_output_worker = scope.new_fn(&format!("output_{id}_{num}_worker"));
     f_output_worker.arg("session", "&::rplc::io::opcua::OpcSafeSession");
     f_output_worker.arg(
@@ -259,6 +357,59 @@ fn push_output_worker(num: usize, id: &str, config: OutputConfig, scope: &mut co
     f_output_worker.line("Ok(())");
 }
 
+/// Returns context quality fields of input mappings
+pub(crate) fn quality_fields(inputs: &[Value]) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut result = Vec::new();
+    for input in inputs {
+        let input_config = InputConfig::deserialize(input.clone())?;
+        for node in &input_config.nodes {
+            if node.stale.is_some() && !node.quality {
+                return Err(eva_common::Error::invalid_params(format!(
+                    "ctx.{}: stale can be set for quality mappings only",
+                    node.map
+                ))
+                .into());
+            }
+            if let Some(q) = node.quality_target() {
+                result.push(q.field);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Validates the mappings against the context schema
+pub(crate) fn validate(
+    id: &str,
+    inputs: &[Value],
+    outputs: &[Value],
+    schema: &FieldSchema,
+) -> Result<(), Box<dyn Error>> {
+    for (i, input) in inputs.iter().enumerate() {
+        let input_config = InputConfig::deserialize(input.clone())?;
+        for (n, node) in input_config.nodes.into_iter().enumerate() {
+            let location = format!("io {}, input[{}].nodes[{}].map", id, i, n);
+            schema.resolve_mapping(&location, &node.map)?;
+        }
+    }
+    let mut written: BTreeMap<String, String> = BTreeMap::new();
+    for (i, output) in outputs.iter().enumerate() {
+        let output_config = OutputConfig::deserialize(output.clone())?;
+        for (n, node) in output_config.nodes.into_iter().enumerate() {
+            let location = format!("io {}, output[{}].nodes[{}].map", id, i, n);
+            schema.resolve_mapping(&location, &node.map)?;
+            if let Some(other) = written.insert(node.id.clone(), location.clone()) {
+                return Err(mapping_error(
+                    &location,
+                    &node.map,
+                    format!("node {} is already written by {}", node.id, other),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
 #[allow(clippy::too_many_lines)]
 pub(crate) fn generate_io(
     id: &str,
@@ -270,10 +421,10 @@ pub(crate) fn generate_io(
     if inputs.is_empty() && outputs.is_empty() {
         return Ok(scope);
     }
-    let id = id.to_lowercase();
     let config = Config::deserialize(cfg.clone())?;
     let mut launch_fn = codegen::Function::new(format!("launch_datasync_{id}"));
     launch_fn.allow("clippy::redundant_clone, clippy::unreadable_literal");
+    launch_fn.line(format!("::rplc::io::register(\"{id}\");"));
     launch_fn.line("use ::rplc::export::opcua::client::prelude::*;");
     launch_fn.line("use ::std::path::Path;");
     if let Some(pki_dir) = config.pki_dir {
@@ -359,15 +510,33 @@ pub(crate) fn generate_io(
                 input_config.sync,
                 input_config.shift.unwrap_or_default(),
                 i + 1,
-                &id,
+                id,
                 &mut launch_fn,
                 None,
             );
-            push_input_worker(i + 1, &id, input_config, &mut scope);
+            push_input_worker(i + 1, id, input_config, &mut scope);
         }
     }
     for (i, output) in outputs.iter().enumerate() {
         let output_config = OutputConfig::deserialize(output.clone())?;
+        if output_config.nodes.iter().any(|n| n.trigger) {
+            return Err(eva_common::Error::invalid_params(format!(
+                "{}: triggers can be set for inputs only",
+                id
+            ))
+            .into());
+        }
+        if output_config
+            .nodes
+            .iter()
+            .any(|n| n.quality || n.stale.is_some())
+        {
+            return Err(eva_common::Error::invalid_params(format!(
+                "{}: quality can be set for inputs only",
+                id
+            ))
+            .into());
+        }
         if !output_config.nodes.is_empty() {
             push_launcher(
                 tasks::Kind::Output,
@@ -375,11 +544,11 @@ pub(crate) fn generate_io(
                 output_config.sync,
                 output_config.shift.unwrap_or_default(),
                 i + 1,
-                &id,
+                id,
                 &mut launch_fn,
                 Some(output_config.cache),
             );
-            push_output_worker(i + 1, &id, output_config, &mut scope);
+            push_output_worker(i + 1, id, output_config, &mut scope);
         }
     }
     scope.push_fn(launch_fn);
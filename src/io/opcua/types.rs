@@ -0,0 +1,156 @@
+use crate::iec::{Date, Dt, IecString, IecWString, Time, Tod};
+use eva_common::Error;
+use opcua::types::{DateTime, Variant, VariantTypeId};
+
+// seconds between 1601-01-01 (OPC-UA epoch) and 1970-01-01
+const EPOCH_DIFF: i64 = 11_644_473_600;
+const TICKS_PER_SECOND: i64 = 10_000_000;
+
+fn variant_to_string(v: Variant) -> Result<String, Error> {
+    if let Variant::String(s) = v.cast(VariantTypeId::String) {
+        Ok(s.as_ref().to_owned())
+    } else {
+        Err(Error::invalid_data("OPC-UA string expected"))
+    }
+}
+
+fn variant_to_u32(v: &Variant) -> Result<u32, Error> {
+    if let Variant::UInt32(v) = v.cast(VariantTypeId::UInt32) {
+        Ok(v)
+    } else {
+        Err(Error::invalid_data("OPC-UA UInt32 expected"))
+    }
+}
+
+fn variant_to_timestamp(v: &Variant) -> Result<u32, Error> {
+    if let Variant::DateTime(dt) = v {
+        u32::try_from(dt.ticks() / TICKS_PER_SECOND - EPOCH_DIFF).map_err(Error::invalid_data)
+    } else {
+        variant_to_u32(v)
+    }
+}
+
+fn timestamp_to_variant(ts: u32) -> Variant {
+    DateTime::from((i64::from(ts) + EPOCH_DIFF) * TICKS_PER_SECOND).into()
+}
+
+impl<const N: usize> From<IecString<N>> for Variant {
+    fn from(s: IecString<N>) -> Self {
+        Variant::from(s.as_str())
+    }
+}
+
+impl<const N: usize> TryFrom<Variant> for IecString<N> {
+    type Error = Error;
+    fn try_from(v: Variant) -> Result<Self, Self::Error> {
+        variant_to_string(v)?.as_str().try_into()
+    }
+}
+
+impl<const N: usize> From<IecWString<N>> for Variant {
+    fn from(s: IecWString<N>) -> Self {
+        Variant::from(s.to_string())
+    }
+}
+
+impl<const N: usize> TryFrom<Variant> for IecWString<N> {
+    type Error = Error;
+    fn try_from(v: Variant) -> Result<Self, Self::Error> {
+        variant_to_string(v)?.as_str().try_into()
+    }
+}
+
+/// TIME is mapped to OPC-UA Duration (Double, milliseconds)
+impl From<Time> for Variant {
+    fn from(t: Time) -> Self {
+        Variant::Double(f64::from(t.0))
+    }
+}
+
+impl TryFrom<Variant> for Time {
+    type Error = Error;
+    fn try_from(v: Variant) -> Result<Self, Self::Error> {
+        let ms = v
+            .as_f64()
+            .ok_or_else(|| Error::invalid_data("OPC-UA Duration expected"))?;
+        Time::from_secs_f64(ms / 1_000.0)
+    }
+}
+
+/// TOD is mapped to UInt32 (milliseconds since midnight)
+impl From<Tod> for Variant {
+    fn from(t: Tod) -> Self {
+        Variant::UInt32(t.0)
+    }
+}
+
+impl TryFrom<Variant> for Tod {
+    type Error = Error;
+    fn try_from(v: Variant) -> Result<Self, Self::Error> {
+        let ms = variant_to_u32(&v)?;
+        if ms < 86_400_000 {
+            Ok(Tod(ms))
+        } else {
+            Err(Error::invalid_data("TOD out of range"))
+        }
+    }
+}
+
+/// DATE is mapped to OPC-UA DateTime
+impl From<Date> for Variant {
+    fn from(d: Date) -> Self {
+        timestamp_to_variant(d.0)
+    }
+}
+
+impl TryFrom<Variant> for Date {
+    type Error = Error;
+    fn try_from(v: Variant) -> Result<Self, Self::Error> {
+        Ok(Dt(variant_to_timestamp(&v)?).date())
+    }
+}
+
+/// DT is mapped to OPC-UA DateTime
+impl From<Dt> for Variant {
+    fn from(dt: Dt) -> Self {
+        timestamp_to_variant(dt.0)
+    }
+}
+
+impl TryFrom<Variant> for Dt {
+    type Error = Error;
+    fn try_from(v: Variant) -> Result<Self, Self::Error> {
+        variant_to_timestamp(&v).map(Dt)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_variant() {
+        let dt: Dt = "2024-02-29T12:30:05".parse().unwrap();
+        let v = Variant::from(dt);
+        if let Variant::DateTime(ref d) = v {
+            assert_eq!(d.to_rfc3339(), "2024-02-29T12:30:05.000Z");
+        } else {
+            panic!("DateTime expected");
+        }
+        assert_eq!(Dt::try_from(v).unwrap(), dt);
+        let s: IecString<8> = Variant::from("hello").try_into().unwrap();
+        assert_eq!(s, "hello");
+        assert!(IecString::<2>::try_from(Variant::from("hello")).is_err());
+        assert_eq!(
+            Time::try_from(Variant::Double(1500.0)).unwrap(),
+            Time(1_500)
+        );
+    }
+
+    #[test]
+    fn test_timestamp() {
+        // after 2262, when nanoseconds overflow i64
+        let dt = DateTime::from((10_413_792_000 + EPOCH_DIFF) * TICKS_PER_SECOND + 5_000_000);
+        assert!((crate::io::opcua::timestamp(&dt) - 10_413_792_000.5).abs() < 1e-3);
+    }
+}
@@ -1,11 +1,13 @@
 #![ doc = include_str!( concat!( env!( "CARGO_MANIFEST_DIR" ), "/", "README.md" ) ) ]
-use log::{debug, info};
+use eva_common::{EResult, Error};
+use log::{debug, error, info};
 use once_cell::sync::{Lazy, OnceCell};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::fmt::Write as _;
 use std::fs;
+use std::io::Write as _;
 use std::panic;
 use std::path::{Path, PathBuf};
 use std::process;
@@ -13,17 +15,31 @@ use std::sync::atomic;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+pub mod alarm;
 pub mod api;
 pub mod builder;
+pub mod chain;
 #[cfg(feature = "client")]
 pub mod client;
+pub mod clock;
 pub mod comm;
+pub mod context;
 #[cfg(feature = "eva")]
 pub mod eapi;
+pub mod fb;
+pub mod force;
+pub mod histogram;
+pub mod hooks;
+pub mod iec;
 pub mod interval;
 pub mod io;
+pub mod retain;
 pub mod server;
+pub mod sfc;
 pub mod tasks;
+pub mod testing;
+pub mod trend;
+pub mod trigger;
 
 pub mod prelude {
     pub use super::{init_plc, plc_context, plc_context_mut, run_plc};
@@ -74,6 +90,15 @@ pub struct PlcInfo {
     pub status: i16,
     pub pid: u32,
     pub uptime: f64,
+    /// some I/O has failed its last operation
+    #[serde(default)]
+    pub degraded: bool,
+    /// number of forced context fields
+    #[serde(default)]
+    pub forces: usize,
+    /// number of active alarms
+    #[serde(default)]
+    pub alarms: usize,
 }
 
 pub(crate) fn plc_info() -> PlcInfo {
@@ -85,6 +110,9 @@ pub(crate) fn plc_info() -> PlcInfo {
         status: tasks::status() as i16,
         pid: process::id(),
         uptime: uptime().as_secs_f64(),
+        degraded: io::degraded(),
+        forces: force::count(),
+        alarms: alarm::active_count(),
     }
 }
 
@@ -112,8 +140,17 @@ pub fn uptime() -> Duration {
 pub fn init(name: &str, description: &str, version: &str, stack_size: Option<usize>) {
     eva_common::self_test();
     panic::set_hook(Box::new(|s| {
-        eprintln!("PANIC: {}", s);
-        std::process::exit(1);
+        let action = tasks::report_panic(&s.to_string());
+        if action == tasks::PanicAction::Abort {
+            eprintln!("PANIC: {}", s);
+            std::process::exit(1);
+        }
+        error!(
+            "task {} panicked: {}, action: {}",
+            tasks::thread_name(),
+            s,
+            action
+        );
     }));
     HOSTNAME
         .set(hostname::get().unwrap().to_string_lossy().to_string())
@@ -167,6 +204,17 @@ macro_rules! init_plc {
             crate::plc::VERSION,
             crate::plc::STACK_SIZE,
         );
+        ::rplc::tasks::set_watchdog(crate::plc::WATCHDOG);
+        ::rplc::tasks::set_rt_profile(crate::plc::RT);
+        ::rplc::tasks::set_panic_policy(crate::plc::PANIC_POLICY);
+        ::rplc::chain::register(crate::plc::CHAINS);
+        ::rplc::trend::register(crate::plc::TRENDS);
+        ::rplc::alarm::register(crate::plc::ALARMS);
+        ::rplc::sfc::register(crate::plc::SFC);
+        ::rplc::context::register(&*crate::plc::context::CONTEXT);
+        ::rplc::context::register_meta(crate::plc::context::FIELD_META);
+        ::rplc::force::init();
+        ::rplc::retain::init(crate::plc::RETAIN);
     };
 }
 
@@ -198,6 +246,27 @@ pub fn var_dir() -> PathBuf {
     env::var("PLC_VAR_DIR").map_or_else(|_| env::temp_dir(), |p| Path::new(&p).to_owned())
 }
 
+/// Writes a file atomically: the data is written to a temporary file which then replaces the
+/// target
+pub(crate) fn write_file_atomic(path: &Path, data: &[u8]) -> EResult<()> {
+    let tmp = path.with_extension(format!(
+        "{}.tmp",
+        path.extension().unwrap_or_default().to_string_lossy()
+    ));
+    {
+        let mut f = fs::File::create(&tmp).map_err(Error::io)?;
+        f.write_all(data).map_err(Error::io)?;
+        f.sync_all().map_err(Error::io)?;
+    }
+    fs::rename(&tmp, path).map_err(Error::io)?;
+    if let Some(dir) = path.parent() {
+        if let Ok(d) = fs::File::open(dir) {
+            let _ = d.sync_all();
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn name() -> &'static str {
     NAME.get().map(String::as_str).unwrap()
 }
@@ -224,9 +293,14 @@ pub fn run<F: Fn(), F2: Fn()>(
     info!("system: {}, cpus: {}", HOSTNAME.get().unwrap(), cpus());
     info!("{}", msg);
     register_signals();
+    hooks::run(hooks::Kind::Start);
     #[cfg(feature = "eva")]
     eapi::launch(_eapi_action_pool_size);
     launch_datasync();
+    sfc::spawn_all();
+    chain::spawn_all();
+    trend::spawn_all();
+    alarm::spawn();
     tasks::set_syncing();
     tasks::set_preparing_if_no_inputs();
     tasks::set_active_if_no_inputs_and_programs();
@@ -239,11 +313,15 @@ pub fn run<F: Fn(), F2: Fn()>(
         tasks::step_sleep();
         check_health();
     }
+    hooks::set_stop_deadline(Instant::now() + stop_timeout);
     tasks::spawn0(move || {
         tasks::sleep(stop_timeout);
         panic!("timeout has been reached, FORCE STOP");
     });
-    if tasks::status() == tasks::Status::Active {
+    if matches!(
+        tasks::status(),
+        tasks::Status::Active | tasks::Status::Fault
+    ) {
         tasks::shutdown();
         if tasks::status() != tasks::Status::Stopped {
             stop_datasync();
@@ -255,12 +333,13 @@ pub fn run<F: Fn(), F2: Fn()>(
     } else {
         tasks::set_stopped();
     }
+    hooks::run(hooks::Kind::Stopped);
     fs::remove_file(pid_file).unwrap();
     fs::remove_file(socket_path).unwrap();
 }
 
 fn check_health() {
-    //tasks::check_health();
+    tasks::check_health();
 }
 
 #[allow(clippy::crate_in_macro_def)]
@@ -309,4 +388,3 @@ pub fn cpus() -> usize {
 // TODO sync context with EthernetIP structures
 // TODO sync context with CANOpen registers
 // TODO sync context with TwinCAT registers
-// TODO chains: inputs, programs, outputs
@@ -0,0 +1,307 @@
+use crate::interval::Loop;
+use crate::{hooks, tasks};
+use eva_common::payload::{pack, unpack};
+use eva_common::value::Value;
+use eva_common::{EResult, Error};
+use log::{debug, error, info, warn};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const FORMAT_VERSION: u16 = 1;
+
+static RETAIN: OnceCell<&'static Retain> = OnceCell::new();
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Retained context fields, generated as crate::plc::RETAIN from the fields marked as RETAIN in
+/// plc.yml
+pub struct Retain {
+    /// context paths of the retained fields
+    pub fields: &'static [&'static str],
+    /// periodic save interval
+    pub interval: Option<Duration>,
+    /// collects values of the retained fields
+    pub dump: fn() -> EResult<BTreeMap<&'static str, Value>>,
+    /// sets a retained field value
+    pub load: fn(&str, Value) -> EResult<()>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RetainFile {
+    version: u16,
+    fields: BTreeMap<String, Value>,
+}
+
+/// Used by the generated code
+#[inline]
+pub fn to_value<T: Serialize>(value: &T) -> EResult<Value> {
+    eva_common::value::to_value(value).map_err(Error::invalid_data)
+}
+
+/// Used by the generated code
+#[inline]
+pub fn from_value<T: for<'de> Deserialize<'de>>(value: Value) -> EResult<T> {
+    value.deserialize_into().map_err(Error::invalid_data)
+}
+
+fn retain_file() -> PathBuf {
+    let mut path = crate::var_dir();
+    path.push(format!("{}.retain", crate::name()));
+    path
+}
+
+/// use init_plc!() macro to restore retained fields
+///
+/// Restores the retained fields from the retain file, registers the stopped hook which saves the
+/// fields on shutdown and spawns the periodic save service
+///
+/// # Panics
+///
+/// Will panic if called twice
+pub fn init(retain: Option<&'static Retain>) {
+    if let Some(r) = retain {
+        assert!(RETAIN.set(r).is_ok(), "retain is already initialized");
+        restore(r, &retain_file());
+        hooks::on_stopped(i16::MAX, save_logged);
+        if let Some(interval) = r.interval {
+            tasks::spawn_service("retain", move || {
+                let mut int = Loop::prepare0(interval);
+                loop {
+                    int.tick();
+                    save_logged();
+                }
+            });
+        }
+    }
+}
+
+fn restore(retain: &Retain, path: &Path) {
+    let data = match fs::read(path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            info!("no retain file, retained fields have default values");
+            return;
+        }
+        Err(e) => {
+            error!("unable to read {}: {}", path.to_string_lossy(), e);
+            return;
+        }
+    };
+    let file: RetainFile = match unpack(&data) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("retain file {} is corrupt: {}", path.to_string_lossy(), e);
+            keep_invalid(path);
+            return;
+        }
+    };
+    if file.version != FORMAT_VERSION {
+        error!(
+            "retain file {} version {} is not supported",
+            path.to_string_lossy(),
+            file.version
+        );
+        keep_invalid(path);
+        return;
+    }
+    let mut restored = 0;
+    let mut values = file.fields;
+    for field in retain.fields {
+        if let Some(value) = values.remove(*field) {
+            match (retain.load)(field, value) {
+                Ok(()) => restored += 1,
+                Err(e) => warn!("retained field {} not restored: {}", field, e),
+            }
+        } else {
+            warn!("retained field {} not found in the retain file", field);
+        }
+    }
+    for field in values.keys() {
+        warn!("retain file field {} is not retained, ignored", field);
+    }
+    info!(
+        "retained fields restored: {}/{}",
+        restored,
+        retain.fields.len()
+    );
+}
+
+fn keep_invalid(path: &Path) {
+    let invalid = path.with_extension("retain.invalid");
+    if let Err(e) = fs::rename(path, &invalid) {
+        error!("unable to rename {}: {}", path.to_string_lossy(), e);
+    } else {
+        warn!("invalid retain file moved to {}", invalid.to_string_lossy());
+    }
+}
+
+fn save_logged() {
+    if let Err(e) = save() {
+        error!("unable to save retained fields: {}", e);
+    }
+}
+
+/// Saves the retained fields
+///
+/// The fields are saved automatically on shutdown and periodically, the function can be called
+/// to save them immediately
+pub fn save() -> EResult<()> {
+    let retain = RETAIN
+        .get()
+        .ok_or_else(|| Error::failed("no retained fields"))?;
+    save_file(retain, &retain_file())
+}
+
+fn save_file(retain: &Retain, path: &Path) -> EResult<()> {
+    let fields = (retain.dump)()?
+        .into_iter()
+        .map(|(k, v)| (k.to_owned(), v))
+        .collect();
+    let data = pack(&RetainFile {
+        version: FORMAT_VERSION,
+        fields,
+    })?;
+    let _lock = SAVE_LOCK.lock();
+    crate::write_file_atomic(path, &data)?;
+    debug!("retained fields saved");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static LOADED: RefCell<BTreeMap<String, Value>> = const { RefCell::new(BTreeMap::new()) };
+    }
+
+    fn dump() -> EResult<BTreeMap<&'static str, Value>> {
+        Ok([
+            ("a", Value::String("on".to_owned())),
+            ("b", Value::Bool(true)),
+        ]
+        .into())
+    }
+
+    fn load(field: &str, value: Value) -> EResult<()> {
+        if field == "b" && !matches!(value, Value::Bool(_)) {
+            return Err(Error::invalid_data("expected a boolean"));
+        }
+        LOADED.with(|v| v.borrow_mut().insert(field.to_owned(), value));
+        Ok(())
+    }
+
+    fn loaded() -> BTreeMap<String, Value> {
+        LOADED.with(|v| std::mem::take(&mut *v.borrow_mut()))
+    }
+
+    static TEST_RETAIN: Retain = Retain {
+        fields: &["a", "b"],
+        interval: None,
+        dump,
+        load,
+    };
+
+    fn test_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rplc_test_{}.retain", name));
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(path.with_extension("retain.invalid"));
+        path
+    }
+
+    fn write_fields(path: &Path, fields: &[(&str, Value)]) {
+        let file = RetainFile {
+            version: FORMAT_VERSION,
+            fields: fields
+                .iter()
+                .map(|(k, v)| ((*k).to_owned(), v.clone()))
+                .collect(),
+        };
+        fs::write(path, pack(&file).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_save_restore() {
+        let path = test_file("save_restore");
+        save_file(&TEST_RETAIN, &path).unwrap();
+        assert!(!path.with_extension("retain.tmp").exists());
+        restore(&TEST_RETAIN, &path);
+        assert_eq!(
+            loaded(),
+            dump()
+                .unwrap()
+                .into_iter()
+                .map(|(k, v)| (k.to_owned(), v))
+                .collect()
+        );
+        // the file is replaced on the next save
+        save_file(&TEST_RETAIN, &path).unwrap();
+        restore(&TEST_RETAIN, &path);
+        assert_eq!(loaded().len(), 2);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_restore_corrupt() {
+        let path = test_file("corrupt");
+        fs::write(&path, b"not a retain file").unwrap();
+        restore(&TEST_RETAIN, &path);
+        assert!(loaded().is_empty());
+        assert!(!path.exists());
+        let invalid = path.with_extension("retain.invalid");
+        assert!(invalid.exists());
+        fs::remove_file(invalid).unwrap();
+        // unsupported format version
+        fs::write(
+            &path,
+            pack(&RetainFile {
+                version: FORMAT_VERSION + 1,
+                fields: <_>::default(),
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        restore(&TEST_RETAIN, &path);
+        assert!(!path.exists());
+        fs::remove_file(path.with_extension("retain.invalid")).unwrap();
+        // no file
+        restore(&TEST_RETAIN, &path);
+        assert!(loaded().is_empty());
+    }
+
+    #[test]
+    fn test_restore_schema_mismatch() {
+        let path = test_file("mismatch");
+        // a missing field and an unknown one
+        write_fields(
+            &path,
+            &[
+                ("a", Value::String("auto".to_owned())),
+                ("x", Value::U16(1)),
+            ],
+        );
+        restore(&TEST_RETAIN, &path);
+        assert_eq!(
+            loaded(),
+            [("a".to_owned(), Value::String("auto".to_owned()))].into()
+        );
+        // a field type has been changed
+        write_fields(
+            &path,
+            &[("a", Value::String("off".to_owned())), ("b", Value::U16(1))],
+        );
+        restore(&TEST_RETAIN, &path);
+        assert_eq!(
+            loaded(),
+            [("a".to_owned(), Value::String("off".to_owned()))].into()
+        );
+        // the file is kept
+        assert!(path.exists());
+        fs::remove_file(path).unwrap();
+    }
+}
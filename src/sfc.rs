@@ -0,0 +1,234 @@
+use crate::{clock, tasks};
+use eva_common::value::Value;
+use eva_common::{EResult, Error};
+use log::info;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+static CHARTS: OnceCell<&'static [Chart]> = OnceCell::new();
+
+/// Sequential function chart, generated from the "sfc" section of plc.yml
+///
+/// Each chart is executed as a program with the chart id as the name, its state is available in
+/// the context as ctx.sfc.ID
+pub struct Chart {
+    pub id: &'static str,
+    /// step names, the first one is the initial step
+    pub steps: &'static [&'static str],
+    pub interval: Duration,
+    pub shift: Duration,
+    /// the generated chart program
+    pub program: fn(),
+    /// gets the chart state from the context
+    pub state: fn() -> SfcState,
+}
+
+impl Chart {
+    /// Chart program definition for [`crate::testing::Harness`]
+    pub fn as_program(&self) -> crate::testing::Program {
+        crate::testing::Program {
+            name: self.id,
+            f: self.program,
+            period: crate::testing::ProgramPeriod::Loop {
+                interval: self.interval,
+                shift: self.shift,
+            },
+        }
+    }
+}
+
+/// Chart state, a context field
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SfcState {
+    /// active step number
+    pub step: u16,
+    /// active step time
+    #[serde(with = "crate::fb::duration_secs")]
+    pub t: Duration,
+    /// last durations of the steps, seconds
+    pub durations: Vec<Option<f64>>,
+    #[serde(skip)]
+    started: Option<Instant>,
+    #[serde(skip)]
+    entered: bool,
+    /// number of the chart steps, known after the first scan
+    #[serde(skip)]
+    steps: u16,
+}
+
+impl SfcState {
+    /// Updates the step timer, returns true if the step has been just activated (including the
+    /// first scan after the PLC restart). The number of the chart steps is used to validate
+    /// steps set with the context API
+    ///
+    /// Used by the generated code
+    pub fn scan(&mut self, steps: u16) -> bool {
+        self.steps = steps;
+        let now = clock::now();
+        let started = *self
+            .started
+            .get_or_insert_with(|| now.checked_sub(self.t).unwrap_or(now));
+        self.t = now.saturating_duration_since(started);
+        !std::mem::replace(&mut self.entered, true)
+    }
+    /// Activates the step
+    pub fn go(&mut self, step: u16) {
+        let current = usize::from(self.step);
+        if self.durations.len() <= current {
+            self.durations.resize(current + 1, None);
+        }
+        self.durations[current] = Some(self.t.as_secs_f64());
+        self.step = step;
+        self.t = Duration::ZERO;
+        self.started = None;
+        self.entered = false;
+    }
+}
+
+/// The active step can be changed with the context API (e.g. for manual sequence control)
+impl crate::context::PathAccess for SfcState {
+    fn get_value(&self, idx: &[usize], path: &str) -> EResult<Value> {
+        crate::context::check_struct_path(idx)?;
+        match path {
+            "" => {
+                let mut result = std::collections::BTreeMap::new();
+                result.insert("step".into(), Value::U16(self.step));
+                result.insert("t".into(), Value::F64(self.t.as_secs_f64()));
+                Ok(Value::Map(result))
+            }
+            "step" => Ok(Value::U16(self.step)),
+            "t" => Ok(Value::F64(self.t.as_secs_f64())),
+            _ => Err(Error::not_found(path)),
+        }
+    }
+    fn set_value(&mut self, idx: &[usize], path: &str, value: Value) -> EResult<()> {
+        crate::context::check_struct_path(idx)?;
+        match path {
+            "step" => {
+                let step: u16 = value.try_into()?;
+                if self.steps == 0 {
+                    return Err(Error::failed("the chart is not started yet"));
+                }
+                if step >= self.steps {
+                    return Err(Error::invalid_params(format!(
+                        "invalid step {} (the chart has {} steps)",
+                        step, self.steps
+                    )));
+                }
+                self.go(step);
+                Ok(())
+            }
+            "t" => Err(Error::access("the step time is read-only")),
+            _ => Err(Error::not_found(path)),
+        }
+    }
+    fn list_paths(&self, prefix: &str, result: &mut Vec<String>) {
+        result.push(crate::context::join_path(prefix, "step"));
+        result.push(crate::context::join_path(prefix, "t"));
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SfcInfo {
+    pub id: String,
+    /// active step name
+    pub step: String,
+    /// active step time, seconds
+    pub t: f64,
+    pub steps: Vec<SfcStepInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SfcStepInfo {
+    pub name: String,
+    pub active: bool,
+    /// last step duration, seconds
+    pub last_duration: Option<f64>,
+}
+
+/// use init_plc!() macro to register charts
+///
+/// # Panics
+///
+/// Will panic if called twice
+pub fn register(charts: &'static [Chart]) {
+    assert!(CHARTS.set(charts).is_ok(), "charts are already registered");
+}
+
+/// Spawns the chart programs, called by the controller before chains are spawned
+pub(crate) fn spawn_all() {
+    for chart in CHARTS.get().copied().unwrap_or_default() {
+        info!("sfc {}: {}", chart.id, chart.steps.join(", "));
+        tasks::spawn_program_loop(chart.id, chart.program, chart.interval, chart.shift);
+    }
+}
+
+pub fn list() -> Vec<SfcInfo> {
+    CHARTS
+        .get()
+        .copied()
+        .unwrap_or_default()
+        .iter()
+        .map(|chart| {
+            let state = (chart.state)();
+            SfcInfo {
+                id: chart.id.to_owned(),
+                step: chart
+                    .steps
+                    .get(usize::from(state.step))
+                    .map(|&v| v.to_owned())
+                    .unwrap_or_default(),
+                t: state.t.as_secs_f64(),
+                steps: chart
+                    .steps
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| SfcStepInfo {
+                        name: (*name).to_owned(),
+                        active: i == usize::from(state.step),
+                        last_duration: state.durations.get(i).copied().flatten(),
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::context::PathAccess;
+
+    #[test]
+    fn test_steps() {
+        clock::set_virtual(Some(Instant::now()));
+        let mut state = SfcState::default();
+        assert!(state.set_value(&[], "step", Value::U8(1)).is_err());
+        assert!(state.scan(3));
+        clock::advance(Duration::from_millis(300));
+        assert!(!state.scan(3));
+        assert_eq!(state.t, Duration::from_millis(300));
+        state.go(2);
+        assert_eq!(state.durations, [Some(0.3)]);
+        assert!(state.scan(3));
+        assert_eq!(state.t, Duration::ZERO);
+        clock::advance(Duration::from_millis(100));
+        state.scan(3);
+        assert!(state.set_value(&[], "step", Value::U8(3)).is_err());
+        assert_eq!(state.step, 2);
+        state.set_value(&[], "step", Value::U8(1)).unwrap();
+        assert_eq!(state.step, 1);
+        assert_eq!(state.durations, [Some(0.3), None, Some(0.1)]);
+        assert!(state.set_value(&[], "t", Value::F64(0.0)).is_err());
+        // the step time is restored after the PLC restart
+        let mut restored: SfcState =
+            crate::retain::from_value(crate::retain::to_value(&state).unwrap()).unwrap();
+        clock::advance(Duration::from_millis(200));
+        restored.t = Duration::from_millis(500);
+        assert!(restored.scan(3));
+        assert_eq!(restored.t, Duration::from_millis(500));
+        clock::set_virtual(None);
+    }
+}
@@ -1,4 +1,6 @@
 use crate::cpus;
+use crate::histogram::{Histogram, HistogramInfo};
+use crate::hooks;
 use crate::interval::Loop;
 use bmart_derive::EnumStr;
 use eva_common::{EResult, Error};
@@ -6,22 +8,39 @@ use log::{debug, error, info, warn};
 use once_cell::sync::{Lazy, OnceCell};
 use parking_lot::{Condvar, Mutex};
 use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::collections::{btree_map, BTreeMap};
 use std::env;
+use std::fmt;
+use std::panic;
+use std::process;
 use std::str::FromStr;
 use std::sync::atomic;
 use std::sync::mpsc;
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 static CONTROLLER_STATS: Lazy<Mutex<ControllerStats>> = Lazy::new(<_>::default);
 static WAIT_HANDLES: Lazy<Mutex<Option<Vec<thread::JoinHandle<()>>>>> = Lazy::new(<_>::default);
-static STATS_TX: OnceCell<Mutex<mpsc::SyncSender<(String, u16)>>> = OnceCell::new();
-static SHUTDOWN_FN: OnceCell<Box<dyn Fn() + Send + Sync>> = OnceCell::new();
+static STATS_TX: OnceCell<Mutex<mpsc::SyncSender<(String, TickReport)>>> = OnceCell::new();
+static WATCHDOG: OnceCell<Watchdog> = OnceCell::new();
+static RT_PROFILE: OnceCell<RtProfile> = OnceCell::new();
+static PANIC_POLICY: OnceCell<PanicPolicy> = OnceCell::new();
+static PANICS: Lazy<Mutex<BTreeMap<String, PanicStats>>> = Lazy::new(<_>::default);
+static HEARTBEATS: Lazy<Mutex<BTreeMap<String, Arc<Heartbeat>>>> = Lazy::new(<_>::default);
+static MONOTONIC_BASE: Lazy<Instant> = Lazy::new(Instant::now);
+static TASK_CONTROLS: Lazy<Mutex<BTreeMap<String, Arc<TaskControl>>>> = Lazy::new(<_>::default);
 static STATUS_CHANGED: Condvar = Condvar::new();
 static STATUS_MUTEX: Mutex<()> = Mutex::new(());
 
+thread_local! {
+    static TASK_KIND: Cell<Option<Kind>> = const { Cell::new(None) };
+}
+
 static STATUS: atomic::AtomicI16 = atomic::AtomicI16::new(Status::Inactive as i16);
+// set if a task has been faulted before the inputs were synced, outputs are not written then
+static FAULT_BEFORE_SYNC: atomic::AtomicBool = atomic::AtomicBool::new(false);
 
 pub fn controller_stats() -> &'static Mutex<ControllerStats> {
     &CONTROLLER_STATS
@@ -35,38 +54,48 @@ const STATS_CHANNEL_SIZE: usize = 100_000;
 
 pub(crate) fn init() {
     WAIT_HANDLES.lock().replace(<_>::default());
-    let (tx, rx) = mpsc::sync_channel::<(String, u16)>(STATS_CHANNEL_SIZE);
+    let (tx, rx) = mpsc::sync_channel::<(String, TickReport)>(STATS_CHANNEL_SIZE);
     STATS_TX.set(Mutex::new(tx)).unwrap();
     spawn_service("stats", move || {
-        while let Ok((name, jitter)) = rx.recv() {
+        while let Ok((name, report)) = rx.recv() {
             if let Some(entry) = CONTROLLER_STATS.lock().thread_stats.get_mut(&name) {
-                entry.report_jitter(jitter);
+                entry.report(report);
             }
         }
     });
 }
 
-/// # Panics
-///
-/// Will panic if set twice
+/// Registers a function which is called when inputs and programs are stopped, before the final
+/// output sync. Can be called multiple times, see [`crate::hooks`] for more options
 pub fn on_shutdown<F>(f: F)
 where
     F: Fn() + Send + Sync + 'static,
 {
-    assert!(
-        SHUTDOWN_FN.set(Box::new(f)).is_ok(),
-        "Unable to set shutdown function. Has it been already set?"
-    );
+    hooks::on_stopping(0, f);
+}
+
+/// Registers a function which is called when the watchdog drives the PLC into the safe state
+///
+/// The function should set all outputs in the context to safe values, the output threads keep
+/// running and write them to the field (if the fault has happened before the inputs were synced,
+/// the outputs are written only on shutdown). Can be called multiple times, see [`crate::hooks`] for
+/// more options
+pub fn on_safe_state<F>(f: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    hooks::on_fault(0, f);
 }
 
 pub(crate) fn shutdown() {
     set_status(Status::Stopping);
     if let Some(wait_handles) = WAIT_HANDLES.lock().take() {
-        if let Some(f) = SHUTDOWN_FN.get() {
+        if hooks::has(hooks::Kind::Stopping) {
             for handle in wait_handles {
                 let _ = handle.join();
             }
-            f();
+            crate::chain::wait_stopped();
+            hooks::run(hooks::Kind::Stopping);
         }
     } else {
         warn!("no wait handles, is shutdown called twice?");
@@ -75,18 +104,19 @@ pub(crate) fn shutdown() {
 }
 
 pub(crate) trait ConvX {
-    fn as_u16_max(&self) -> u16;
+    fn as_u32_max(&self) -> u32;
 }
 
 macro_rules! impl_convx {
     ($t: ty) => {
         impl ConvX for $t {
-            fn as_u16_max(&self) -> u16 {
+            #[allow(clippy::cast_possible_truncation)]
+            fn as_u32_max(&self) -> u32 {
                 let val = *self;
-                if val > <$t>::from(u16::MAX) {
-                    u16::MAX
+                if val > <$t>::from(u32::MAX) {
+                    u32::MAX
                 } else {
-                    val as u16
+                    val as u32
                 }
             }
         }
@@ -95,6 +125,7 @@ macro_rules! impl_convx {
 
 impl_convx!(u32);
 impl_convx!(u64);
+impl_convx!(u128);
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, EnumStr)]
 #[repr(i16)]
@@ -104,6 +135,7 @@ pub enum Status {
     Starting = 1,     // plc is starting
     Syncing = 2,      // inputs can run
     Preparing = 3,    // programs can run
+    Fault = 50,       // a task has been failed, programs are halted
     Active = 100,     // outputs can run
     Stopping = -1,    // plc started shutdown, inputs and programs must quit
     StopSyncing = -2, // final data sync
@@ -135,13 +167,14 @@ pub fn thread_name() -> String {
     }
 }
 
-#[derive(Eq, PartialEq, Copy, Clone, EnumStr)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug, EnumStr)]
 #[enumstr(rename_all = "lowercase")]
 pub enum Kind {
     Input,
     Output,
     Program,
     Service,
+    Chain,
 }
 
 impl Kind {
@@ -151,13 +184,14 @@ impl Kind {
             Kind::Output => "O",
             Kind::Program => "P",
             Kind::Service => "S",
+            Kind::Chain => "C",
         }
     }
 }
 
 pub enum Period {
     Interval(Duration),
-    Trigger(triggered::Listener),
+    Trigger(crate::trigger::Listener),
 }
 
 pub(crate) fn set_preparing_if_no_inputs() {
@@ -174,7 +208,9 @@ pub(crate) fn set_active_if_no_inputs_and_programs() {
 }
 
 pub fn stop_if_no_output_or_sfn() {
-    if CONTROLLER_STATS.lock().output_threads_stopped.is_empty() || SHUTDOWN_FN.get().is_none() {
+    if CONTROLLER_STATS.lock().output_threads_stopped.is_empty()
+        || !hooks::has(hooks::Kind::Stopping)
+    {
         set_status(Status::Stopped);
     }
 }
@@ -193,95 +229,187 @@ pub struct ControllerStats {
 #[derive(Default, Debug)]
 pub(crate) struct ThreadStats {
     iters: u32,
-    jitter: Option<JitterStats>,
+    overruns: u32,
+    jitter: Option<TimeStats>,
+    exec: Option<TimeStats>,
+    jitter_hist: Histogram,
+    exec_hist: Histogram,
 }
 
 impl ThreadStats {
-    #[allow(clippy::cast_possible_truncation)]
     pub(crate) fn info(&self) -> Option<ThreadInfo> {
-        self.jitter.as_ref().map(|jitter| ThreadInfo {
-            iters: self.iters,
-            jitter_min: jitter.min,
-            jitter_max: jitter.max,
-            jitter_last: jitter.last,
-            jitter_avg: (jitter.total / self.iters).as_u16_max(),
+        self.jitter.as_ref().map(|jitter| {
+            let mut info = ThreadInfo {
+                iters: self.iters,
+                jitter_min: jitter.min,
+                jitter_max: jitter.max,
+                jitter_last: jitter.last,
+                jitter_avg: jitter.avg(self.iters),
+                overruns: self.overruns,
+                ..ThreadInfo::default()
+            };
+            if let Some(ref exec) = self.exec {
+                info.exec_min = exec.min;
+                info.exec_max = exec.max;
+                info.exec_last = exec.last;
+                info.exec_avg = exec.avg(self.iters);
+            }
+            info
         })
     }
+    pub(crate) fn histogram(&self) -> ThreadHistogram {
+        ThreadHistogram {
+            jitter: self.jitter_hist.info(),
+            exec: self.exec_hist.info(),
+        }
+    }
 }
 
+/// Task (thread) statistics. All times are in microseconds
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct ThreadInfo {
     pub iters: u32,
-    pub jitter_min: u16,
-    pub jitter_max: u16,
-    pub jitter_last: u16,
-    pub jitter_avg: u16,
+    pub jitter_min: u32,
+    pub jitter_max: u32,
+    pub jitter_last: u32,
+    pub jitter_avg: u32,
+    #[serde(default)]
+    pub exec_min: u32,
+    #[serde(default)]
+    pub exec_max: u32,
+    #[serde(default)]
+    pub exec_last: u32,
+    #[serde(default)]
+    pub exec_avg: u32,
+    /// number of missed loop deadlines
+    #[serde(default)]
+    pub overruns: u32,
+    #[serde(default)]
+    pub paused: bool,
+    /// the current loop interval, zero for tasks with no fixed interval
+    #[serde(default)]
+    pub interval: u64,
+    #[serde(default)]
+    pub panics: u32,
+    #[serde(default)]
+    pub last_panic: Option<String>,
+    /// the task has been stopped by the fault panic action
+    #[serde(default)]
+    pub faulted: bool,
+}
+
+impl ThreadInfo {
+    fn apply_control(&mut self, control: &TaskControl) {
+        self.paused = control.is_paused();
+        self.interval = control
+            .interval()
+            .map_or(0, |v| u64::try_from(v.as_micros()).unwrap_or(u64::MAX));
+    }
+}
+
+/// Task (thread) jitter and execution time histograms
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ThreadHistogram {
+    pub jitter: HistogramInfo,
+    pub exec: HistogramInfo,
+}
+
+/// A single loop iteration report
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct TickReport {
+    pub(crate) jitter: u32,
+    pub(crate) exec: u32,
+    pub(crate) overrun: bool,
 }
 
 #[derive(Default, Debug, Serialize)]
-struct JitterStats {
-    min: u16,
-    max: u16,
-    last: u16,
-    total: u32,
+struct TimeStats {
+    min: u32,
+    max: u32,
+    last: u32,
+    total: u64,
 }
 
-impl JitterStats {
+impl TimeStats {
     #[inline]
-    fn new(jitter: u16) -> Self {
+    fn new(value: u32) -> Self {
         Self {
-            min: jitter,
-            max: jitter,
-            last: jitter,
-            total: u32::from(jitter),
+            min: value,
+            max: value,
+            last: value,
+            total: u64::from(value),
+        }
+    }
+    #[inline]
+    fn report(&mut self, value: u32, reset: bool) {
+        if self.min > value {
+            self.min = value;
+        }
+        if self.max < value {
+            self.max = value;
+        }
+        self.last = value;
+        if reset {
+            self.total = u64::from(value);
+        } else {
+            self.total = self.total.saturating_add(u64::from(value));
+        }
+    }
+    #[inline]
+    fn avg(&self, iters: u32) -> u32 {
+        if iters == 0 {
+            0
+        } else {
+            (self.total / u64::from(iters)).as_u32_max()
         }
     }
 }
 
+#[inline]
+fn report_time(stats: &mut Option<TimeStats>, value: u32, reset: bool) {
+    if let Some(ref mut t_stats) = stats {
+        t_stats.report(value, reset);
+    } else {
+        stats.replace(TimeStats::new(value));
+    }
+}
+
 impl ThreadStats {
     #[inline]
-    fn report_jitter(&mut self, jitter: u16) {
-        let was_reset = if self.iters == u32::MAX {
+    fn report(&mut self, report: TickReport) {
+        // totals are u64, only the iteration counter may wrap
+        let reset = if self.iters == u32::MAX {
             self.iters = 1;
             true
         } else {
             self.iters += 1;
             false
         };
-        if let Some(ref mut j_stats) = self.jitter {
-            if j_stats.min > jitter {
-                j_stats.min = jitter;
-            }
-            if j_stats.max < jitter {
-                j_stats.max = jitter;
-            }
-            j_stats.last = jitter;
-            let j32 = u32::from(jitter);
-            if was_reset {
-                j_stats.total = j32;
-            } else if j_stats.total > u32::MAX - j32 {
-                self.iters = 1;
-                j_stats.total = j32;
-            } else {
-                j_stats.total += j32;
-            }
-        } else {
-            self.jitter.replace(JitterStats::new(jitter));
+        report_time(&mut self.jitter, report.jitter, reset);
+        report_time(&mut self.exec, report.exec, reset);
+        self.jitter_hist.record(report.jitter);
+        self.exec_hist.record(report.exec);
+        if report.overrun {
+            self.overruns = self.overruns.saturating_add(1);
         }
     }
     pub(crate) fn reset(&mut self) {
         self.iters = 0;
+        self.overruns = 0;
         self.jitter.take();
+        self.exec.take();
+        self.jitter_hist.reset();
+        self.exec_hist.reset();
     }
 }
 
 #[inline]
-pub(crate) fn report_jitter(jitter: u16) {
+pub(crate) fn report_tick(report: TickReport) {
     if STATS_TX
         .get()
         .unwrap()
         .lock()
-        .try_send((thread_name(), jitter))
+        .try_send((thread_name(), report))
         .is_err()
     {
         error!("CRITICAL: stats channel full");
@@ -331,6 +459,20 @@ impl ControllerStats {
         self.programs_ready = false;
         Ok(())
     }
+    fn register_chain_thread(&mut self, name: &str, inputs: bool, programs: bool, outputs: bool) {
+        if inputs {
+            self.input_threads_ready.insert(name.to_owned(), false);
+            self.inputs_ready = false;
+        }
+        if programs {
+            self.program_threads_ready.insert(name.to_owned(), false);
+            self.programs_ready = false;
+        }
+        if outputs {
+            self.output_threads_stopped.insert(name.to_owned(), false);
+            self.outputs_stopped = false;
+        }
+    }
     fn register_service_thread(&mut self, name: &str) -> EResult<()> {
         self.register_thread_stats(name)
     }
@@ -407,7 +549,12 @@ impl ControllerStats {
     }
     pub fn thread_info(&self, name: &str) -> Option<ThreadInfo> {
         if let Some(thread_stats) = self.thread_stats.get(name) {
-            thread_stats.info()
+            thread_stats.info().map(|mut info| {
+                if let Some(control) = TASK_CONTROLS.lock().get(name) {
+                    info.apply_control(control);
+                }
+                info
+            })
         } else {
             None
         }
@@ -417,9 +564,16 @@ impl ControllerStats {
 #[inline]
 fn set_status(status: Status) {
     let _lock = STATUS_MUTEX.lock();
-    STATUS.store(status as i16, atomic::Ordering::Relaxed);
+    // the fault status is latched until the PLC is stopped
+    if status >= Status::Inactive && self::status() == Status::Fault {
+        return;
+    }
+    let prev = STATUS.swap(status as i16, atomic::Ordering::Relaxed);
     info!("controller status: {}", status);
     STATUS_CHANGED.notify_all();
+    if status == Status::Active && prev != Status::Active as i16 {
+        hooks::run_async(hooks::Kind::Active);
+    }
 }
 
 #[inline]
@@ -448,6 +602,7 @@ impl From<i16> for Status {
             x if x == Status::Starting as i16 => Status::Starting,
             x if x == Status::Syncing as i16 => Status::Syncing,
             x if x == Status::Preparing as i16 => Status::Preparing,
+            x if x == Status::Fault as i16 => Status::Fault,
             x if x == Status::Active as i16 => Status::Active,
             x if x == Status::Stopping as i16 => Status::Stopping,
             x if x == Status::StopSyncing as i16 => Status::StopSyncing,
@@ -478,10 +633,21 @@ fn mark_program_thread_ready() {
 }
 
 #[inline]
-fn mark_output_thread_stopped() {
+pub(crate) fn mark_output_thread_stopped() {
     CONTROLLER_STATS.lock().mark_output_thread_stopped();
 }
 
+/// Registers a chain thread as an input, program and/or output one for the controller status
+/// tracking
+pub(crate) fn register_chain_thread(name: &str, inputs: bool, programs: bool, outputs: bool) {
+    CONTROLLER_STATS.lock().register_chain_thread(
+        &format!("{}{}", Kind::Chain.thread_prefix(), name),
+        inputs,
+        programs,
+        outputs,
+    );
+}
+
 #[inline]
 pub(crate) fn mark_thread_ready(kind: Kind) {
     match kind {
@@ -502,9 +668,19 @@ fn can_run_programs() -> bool {
 }
 
 #[inline]
-fn can_run_outputs() -> bool {
+pub(crate) fn programs_enabled() -> bool {
     let status = status();
-    status >= Status::Preparing || status <= Status::Stopping
+    status >= Status::Preparing && status != Status::Fault
+}
+
+#[inline]
+pub(crate) fn can_run_outputs() -> bool {
+    let status = status();
+    if status == Status::Fault {
+        !FAULT_BEFORE_SYNC.load(atomic::Ordering::Relaxed)
+    } else {
+        status >= Status::Preparing || status <= Status::Stopping
+    }
 }
 
 pub(crate) fn wait_can_run_input() {
@@ -582,7 +758,7 @@ where
                 .lock()
                 .register_output_thread(&name)
                 .unwrap(),
-            Kind::Service => CONTROLLER_STATS
+            Kind::Service | Kind::Chain => CONTROLLER_STATS
                 .lock()
                 .register_service_thread(&name)
                 .unwrap(),
@@ -594,6 +770,12 @@ where
                     .unwrap_or_else(|e| panic!("UNABLE TO SET THREAD {} AFFINITY: {}", name, e))
             })
             .ok();
+        let rt = ThreadRt::new(&name, affinity);
+        let prefault_stack = if kind == Kind::Service {
+            None
+        } else {
+            RT_PROFILE.get().and_then(|p| p.prefault_stack)
+        };
         let mut builder = thread::Builder::new();
         if let Some(ss) = crate::STACK_SIZE.get() {
             builder = builder.stack_size(*ss);
@@ -601,32 +783,21 @@ where
         let handle = builder
             .name(name)
             .spawn(move || {
-                if let Some(affinity) = affinity {
-                    let name = thread_name();
-                    info!(
-                        "setting {} affinity to CPU {}, priority: {}",
-                        name, affinity.cpu_id, affinity.sched_priority
-                    );
-                    core_affinity::set_for_current(core_affinity::CoreId {
-                        id: affinity.cpu_id,
-                    });
-                    let res = unsafe {
-                        libc::sched_setscheduler(
-                            0,
-                            libc::SCHED_RR,
-                            &libc::sched_param {
-                                sched_priority: affinity.sched_priority,
-                            },
-                        )
-                    };
-                    assert!(
-                        res == 0,
-                        "UNABLE TO SET THREAD {} AFFINITY, error code: {}",
-                        name,
-                        res
-                    );
+                TASK_KIND.with(|k| k.set(Some(kind)));
+                rt.apply();
+                if let Some(size) = prefault_stack {
+                    debug!("prefaulting {} bytes of {} stack", size, thread_name());
+                    prefault_stack_pages(size / STACK_PAGE_SIZE);
+                }
+                // the abort action exits in the panic hook, so only panics of the restart and
+                // fault actions are caught here. Such a panic is outside of the iteration guard
+                // and the task can not be continued
+                if panic::catch_unwind(panic::AssertUnwindSafe(f)).is_err() {
+                    fault_task(None);
+                    if kind == Kind::Output {
+                        mark_output_thread_stopped();
+                    }
                 }
-                f();
             })
             .unwrap();
         if kind == Kind::Input || kind == Kind::Program {
@@ -635,9 +806,185 @@ where
     }
 }
 
+const STACK_PAGE_SIZE: usize = 4096;
+
+// touches the given number of stack pages, so they are mapped before the task starts
+#[inline(never)]
+fn prefault_stack_pages(pages: usize) {
+    let mut page = [0u8; STACK_PAGE_SIZE];
+    std::hint::black_box(&mut page);
+    if pages > 1 {
+        prefault_stack_pages(pages - 1);
+    }
+}
+
+/// Thread scheduling policy
+#[derive(Deserialize, Debug, Default, Copy, Clone, Eq, PartialEq, EnumStr)]
+#[serde(rename_all = "lowercase")]
+#[enumstr(rename_all = "lowercase")]
+pub enum SchedPolicy {
+    Fifo,
+    #[default]
+    Rr,
+    Other,
+}
+
+impl SchedPolicy {
+    fn as_libc(self) -> libc::c_int {
+        match self {
+            SchedPolicy::Fifo => libc::SCHED_FIFO,
+            SchedPolicy::Rr => libc::SCHED_RR,
+            SchedPolicy::Other => libc::SCHED_OTHER,
+        }
+    }
+    fn check_priority(self, priority: libc::c_int) -> EResult<()> {
+        let valid = if self == SchedPolicy::Other {
+            priority == 0
+        } else {
+            (1..=99).contains(&priority)
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::invalid_params(format!(
+                "invalid scheduler priority for {}: {}",
+                self, priority
+            )))
+        }
+    }
+}
+
+/// Real-time settings of a task, generated from the "core.tasks" section of plc.yml
+#[derive(Debug, Copy, Clone)]
+pub struct TaskRt {
+    /// full thread name, including the kind prefix (e.g. "Pmain")
+    pub name: &'static str,
+    pub cpu: Option<usize>,
+    pub policy: Option<SchedPolicy>,
+    pub priority: Option<libc::c_int>,
+    pub nice: Option<libc::c_int>,
+}
+
+/// Real-time profile of the PLC, generated from the "core" section of plc.yml
+#[derive(Debug, Copy, Clone, Default)]
+pub struct RtProfile {
+    /// lock all current and future process memory
+    pub mlockall: bool,
+    /// bytes of stack, prefaulted by I/O, program and chain tasks before start
+    pub prefault_stack: Option<usize>,
+    pub tasks: &'static [TaskRt],
+}
+
+/// use init_plc!() macro to set the real-time profile
+///
+/// Task settings can be overridden with PLC_THREAD_AFFINITY_NAME=CPU,PRIORITY[,POLICY]
+/// environment variables
+///
+/// # Panics
+///
+/// Will panic if set twice or if mlockall is requested but memory can not be locked
+pub fn set_rt_profile(profile: RtProfile) {
+    if profile.mlockall {
+        info!("locking process memory");
+        let res = unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) };
+        assert!(
+            res == 0,
+            "UNABLE TO LOCK PROCESS MEMORY: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    assert!(RT_PROFILE.set(profile).is_ok(), "RT profile is already set");
+}
+
+#[derive(Default)]
+struct ThreadRt {
+    cpu: Option<usize>,
+    sched: Option<(SchedPolicy, libc::c_int)>,
+    nice: Option<libc::c_int>,
+}
+
+impl ThreadRt {
+    // environment variables have got priority over the RT profile
+    fn new(name: &str, affinity: Option<Affinity>) -> Self {
+        let mut rt = RT_PROFILE
+            .get()
+            .and_then(|p| p.tasks.iter().find(|t| t.name == name))
+            .map_or_else(Self::default, |t| {
+                if let Some(cpu) = t.cpu {
+                    assert!(
+                        cpu < cpus(),
+                        "UNABLE TO SET THREAD {} AFFINITY: CPU not found: {}",
+                        name,
+                        cpu
+                    );
+                }
+                let sched = match (t.policy, t.priority) {
+                    (None, None) => None,
+                    (policy, priority) => Some((policy.unwrap_or_default(), priority.unwrap_or(0))),
+                };
+                Self {
+                    cpu: t.cpu,
+                    sched,
+                    nice: t.nice,
+                }
+            });
+        if let Some(aff) = affinity {
+            rt.cpu = Some(aff.cpu_id);
+            rt.sched = Some((aff.policy, aff.sched_priority));
+        }
+        rt
+    }
+    fn apply(&self) {
+        let name = thread_name();
+        if let Some(cpu) = self.cpu {
+            info!("setting {} affinity to CPU {}", name, cpu);
+            core_affinity::set_for_current(core_affinity::CoreId { id: cpu });
+        }
+        if let Some((policy, priority)) = self.sched {
+            info!(
+                "setting {} scheduler policy to {}, priority: {}",
+                name, policy, priority
+            );
+            let res = unsafe {
+                libc::sched_setscheduler(
+                    0,
+                    policy.as_libc(),
+                    &libc::sched_param {
+                        sched_priority: priority,
+                    },
+                )
+            };
+            assert!(
+                res == 0,
+                "UNABLE TO SET THREAD {} SCHEDULER: {}",
+                name,
+                std::io::Error::last_os_error()
+            );
+        }
+        if let Some(nice) = self.nice {
+            info!("setting {} nice to {}", name, nice);
+            // on Linux PRIO_PROCESS with 0 is applied to the calling thread only
+            let res = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) };
+            assert!(
+                res == 0,
+                "UNABLE TO SET THREAD {} NICE: {}",
+                name,
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+}
+
 pub struct Affinity {
     pub cpu_id: usize,
     pub sched_priority: libc::c_int,
+    pub policy: SchedPolicy,
+}
+
+impl fmt::Display for Affinity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{}", self.cpu_id, self.sched_priority, self.policy)
+    }
 }
 
 impl FromStr for Affinity {
@@ -654,6 +1001,12 @@ impl FromStr for Affinity {
             .ok_or_else(|| Error::invalid_params("no priority specified"))?
             .parse()
             .map_err(|e| Error::invalid_params(format!("invalid task priority: {e}")))?;
+        let policy: SchedPolicy = if let Some(p) = sp.next() {
+            p.parse()
+                .map_err(|e| Error::invalid_params(format!("invalid task policy: {e}")))?
+        } else {
+            SchedPolicy::default()
+        };
         if let Some(s) = sp.next() {
             return Err(Error::invalid_params(format!(
                 "extra affinity params not supported: {}",
@@ -663,19 +1016,17 @@ impl FromStr for Affinity {
         if cpu_id >= cpus() {
             return Err(Error::invalid_params(format!("CPU not found: {}", cpu_id)));
         }
-        if !(1..=99).contains(&sched_priority) {
-            return Err(Error::invalid_params(format!(
-                "invalid scheduler priority: {}",
-                sched_priority
-            )));
-        }
+        policy.check_priority(sched_priority)?;
         Ok(Self {
             cpu_id,
             sched_priority,
+            policy,
         })
     }
 }
 
+/// Forced values are applied after each iteration, custom inputs may call
+/// [`crate::force::apply_locked`] under their context lock to never expose unforced values
 #[inline]
 pub fn spawn_input_loop<F>(name: &str, interval: Duration, shift: Duration, f: F)
 where
@@ -692,16 +1043,24 @@ where
     spawn_loop(name, interval, shift, Kind::Output, f);
 }
 
-pub fn spawn_loop<F>(name: &str, interval: Duration, shift: Duration, kind: Kind, mut f: F)
+pub fn spawn_loop<F>(name: &str, interval: Duration, shift: Duration, kind: Kind, f: F)
 where
     F: FnMut() + Send + 'static,
 {
+    let mut f = match crate::chain::take_step(kind, name, f) {
+        Ok(()) => return,
+        Err(f) => f,
+    };
     if kind == Kind::Output {
         spawn(name, Kind::Output, move || {
             let mut int = Loop::prepare_reported(interval, shift);
+            let mut guard = Guard::default();
             loop {
                 let last_sync = output_last_sync();
-                f();
+                if last_sync || !int.is_paused() {
+                    crate::force::apply();
+                    guard.run(&mut f);
+                }
                 if last_sync {
                     break;
                 }
@@ -713,9 +1072,15 @@ where
     } else {
         spawn(name, kind, move || {
             let mut int = Loop::prepare_reported(interval, shift);
+            let mut guard = Guard::default();
             loop {
                 log_running();
-                f();
+                if !int.is_paused() {
+                    guard.run(&mut f);
+                    if kind == Kind::Input {
+                        crate::force::apply_after_input();
+                    }
+                }
                 if need_stop(kind) {
                     break;
                 }
@@ -740,7 +1105,7 @@ fn log_finished() {
 fn need_stop(kind: Kind) -> bool {
     match kind {
         Kind::Input | Kind::Program => status() <= Status::Stopping,
-        Kind::Output => status() <= Status::StopSyncing,
+        Kind::Output | Kind::Chain => status() <= Status::StopSyncing,
         Kind::Service => false,
     }
 }
@@ -779,13 +1144,19 @@ pub fn spawn_program_loop<F>(name: &str, prog: F, interval: Duration, shift: Dur
 where
     F: Fn() + Send + 'static,
 {
+    let prog = match crate::chain::take_step(Kind::Program, name, prog) {
+        Ok(()) => return,
+        Err(f) => f,
+    };
     spawn(name, Kind::Program, move || {
         let mut int = Loop::prepare_reported(interval, shift);
+        let mut guard = Guard::default();
         loop {
             log_running();
             {
-                if status() >= Status::Preparing {
-                    prog();
+                if programs_enabled() && !int.is_paused() {
+                    crate::force::apply();
+                    guard.run(&prog);
                 }
             }
             if need_stop(Kind::Program) {
@@ -797,6 +1168,62 @@ where
     });
 }
 
+/// Spawns a program either as a fixed-interval loop or as a triggered one
+///
+/// Triggered programs are executed when any of the listened triggers is fired. The delay
+/// between the fire and the program start is reported as the task jitter. Triggered programs
+/// have no fixed interval and are not monitored by the watchdog
+///
+/// # Panics
+///
+/// The function will panic if
+///
+/// - the thread with such name is already registered
+///
+/// - the thread name is more than 14 characters
+///
+/// - the OS is unable to spawn the thread
+pub fn spawn_program<F>(name: &str, prog: F, period: Period)
+where
+    F: Fn() + Send + 'static,
+{
+    match period {
+        Period::Interval(interval) => {
+            spawn_program_loop(name, prog, interval, Duration::default());
+        }
+        Period::Trigger(listener) => {
+            debug!("program {} triggers: {}", name, listener.names().join(", "));
+            spawn(name, Kind::Program, move || {
+                wait_can_run_program();
+                // a triggered program can not be waited for the first run
+                mark_thread_ready(Kind::Program);
+                let control = register_task_control(None);
+                let mut guard = Guard::default();
+                loop {
+                    if let Some(fired) = listener.wait(WAIT_STEP) {
+                        if programs_enabled() && !control.is_paused() {
+                            log_running();
+                            let t = Instant::now();
+                            crate::force::apply();
+                            guard.run(&prog);
+                            report_tick(TickReport {
+                                jitter: t.saturating_duration_since(fired).as_micros().as_u32_max(),
+                                exec: t.elapsed().as_micros().as_u32_max(),
+                                overrun: false,
+                            });
+                        }
+                    }
+                    if need_stop(Kind::Program) {
+                        break;
+                    }
+                }
+                unregister_task_control(&control);
+                log_finished();
+            });
+        }
+    }
+}
+
 pub fn spawn_stats_log(int: Duration) {
     spawn_service("stlog", move || {
         let mut stats_interval = Loop::prepare0(int);
@@ -813,8 +1240,18 @@ pub fn spawn_stats_log(int: Duration) {
 fn log_thread_stats(name: &str, t_stats: &ThreadStats) {
     if let Some(info) = t_stats.info() {
         info!(
-            "thread {} iters {}, jitter min: {}, max: {}, last: {}, avg: {}",
-            name, info.iters, info.jitter_min, info.jitter_max, info.jitter_last, info.jitter_avg
+            "thread {} iters {}, jitter min: {}, max: {}, last: {}, avg: {}, exec min: {}, max: {}, last: {}, avg: {}, overruns: {}",
+            name,
+            info.iters,
+            info.jitter_min,
+            info.jitter_max,
+            info.jitter_last,
+            info.jitter_avg,
+            info.exec_min,
+            info.exec_max,
+            info.exec_last,
+            info.exec_avg,
+            info.overruns
         );
     }
 }
@@ -826,3 +1263,381 @@ pub(crate) fn reset_thread_stats() {
         .values_mut()
         .for_each(ThreadStats::reset);
 }
+
+/// Action taken by the watchdog when a task misses its loop intervals
+#[derive(Deserialize, Debug, Default, Copy, Clone, Eq, PartialEq, EnumStr)]
+#[serde(rename_all = "lowercase")]
+#[enumstr(rename_all = "lowercase")]
+pub enum WatchdogAction {
+    /// log an error
+    #[default]
+    Log,
+    /// switch the controller into FAULT status, programs are halted
+    Fault,
+    /// switch the controller into FAULT status and call the safe state function
+    Safe,
+    /// abort the process
+    Abort,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Watchdog {
+    /// the number of missed intervals after which the action is taken
+    pub missed: u32,
+    pub action: WatchdogAction,
+}
+
+/// use init_plc!() macro to set the watchdog
+///
+/// # Panics
+///
+/// Will panic if set twice
+pub fn set_watchdog(watchdog: Option<Watchdog>) {
+    if let Some(wd) = watchdog {
+        assert!(WATCHDOG.set(wd).is_ok(), "Watchdog is already set");
+    }
+}
+
+#[inline]
+fn monotonic_nanos() -> u64 {
+    u64::try_from(MONOTONIC_BASE.elapsed().as_nanos()).unwrap_or(u64::MAX)
+}
+
+pub(crate) struct Heartbeat {
+    interval: atomic::AtomicU64,
+    last: atomic::AtomicU64,
+    missed: atomic::AtomicBool,
+}
+
+impl Heartbeat {
+    #[inline]
+    pub(crate) fn beat(&self) {
+        self.last
+            .store(monotonic_nanos(), atomic::Ordering::Relaxed);
+    }
+    #[inline]
+    pub(crate) fn set_interval(&self, interval: Duration) {
+        self.interval.store(
+            u64::try_from(interval.as_nanos()).unwrap_or(u64::MAX),
+            atomic::Ordering::Relaxed,
+        );
+    }
+}
+
+pub(crate) fn register_heartbeat(interval: Duration) -> Arc<Heartbeat> {
+    let hb = Arc::new(Heartbeat {
+        interval: atomic::AtomicU64::new(u64::try_from(interval.as_nanos()).unwrap_or(u64::MAX)),
+        last: atomic::AtomicU64::new(monotonic_nanos()),
+        missed: atomic::AtomicBool::new(false),
+    });
+    HEARTBEATS.lock().insert(thread_name(), hb.clone());
+    hb
+}
+
+pub(crate) fn unregister_heartbeat(hb: &Arc<Heartbeat>) {
+    let mut heartbeats = HEARTBEATS.lock();
+    let name = thread_name();
+    if heartbeats.get(&name).is_some_and(|v| Arc::ptr_eq(v, hb)) {
+        heartbeats.remove(&name);
+    }
+}
+
+pub(crate) fn check_health() {
+    if let Some(watchdog) = WATCHDOG.get() {
+        check_heartbeats(watchdog);
+    }
+}
+
+fn check_heartbeats(watchdog: &Watchdog) {
+    let status = status();
+    if status < Status::Syncing {
+        return;
+    }
+    let now = monotonic_nanos();
+    let mut failed = Vec::new();
+    for (name, hb) in &*HEARTBEATS.lock() {
+        let elapsed = now.saturating_sub(hb.last.load(atomic::Ordering::Relaxed));
+        if elapsed
+            > hb.interval
+                .load(atomic::Ordering::Relaxed)
+                .saturating_mul(u64::from(watchdog.missed))
+        {
+            if !hb.missed.swap(true, atomic::Ordering::Relaxed) {
+                failed.push((name.clone(), Duration::from_nanos(elapsed)));
+            }
+        } else if hb.missed.swap(false, atomic::Ordering::Relaxed) {
+            info!("watchdog: task {} is alive again", name);
+        }
+    }
+    for (name, elapsed) in failed {
+        error!(
+            "watchdog: task {} missed {} intervals, no ticks for {:?}, action: {}",
+            name, watchdog.missed, elapsed, watchdog.action
+        );
+        match watchdog.action {
+            WatchdogAction::Log => {}
+            WatchdogAction::Fault => set_fault(),
+            WatchdogAction::Safe => {
+                set_fault();
+                if !hooks::run(hooks::Kind::Fault) {
+                    warn!("watchdog: no safe state function set");
+                }
+            }
+            WatchdogAction::Abort => {
+                error!("watchdog: aborting the process");
+                process::abort();
+            }
+        }
+    }
+}
+
+/// Action taken when a task panics
+#[derive(Deserialize, Debug, Default, Copy, Clone, Eq, PartialEq, EnumStr)]
+#[serde(rename_all = "lowercase")]
+#[enumstr(rename_all = "lowercase")]
+pub enum PanicAction {
+    /// print the panic message and exit the process
+    #[default]
+    Abort,
+    /// abandon the current iteration and continue with the next one
+    Restart,
+    /// stop the task, switch the controller into FAULT status and call fault hooks, other tasks
+    /// continue running
+    Fault,
+}
+
+/// Panic actions per task kind, service tasks always abort
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PanicPolicy {
+    pub input: PanicAction,
+    pub program: PanicAction,
+    pub output: PanicAction,
+    pub chain: PanicAction,
+}
+
+impl PanicPolicy {
+    fn action(&self, kind: Kind) -> PanicAction {
+        match kind {
+            Kind::Input => self.input,
+            Kind::Program => self.program,
+            Kind::Output => self.output,
+            Kind::Chain => self.chain,
+            Kind::Service => PanicAction::Abort,
+        }
+    }
+}
+
+#[derive(Default)]
+struct PanicStats {
+    count: u32,
+    last_message: Option<String>,
+    faulted: bool,
+}
+
+/// use init_plc!() macro to set the panic policy
+///
+/// # Panics
+///
+/// Will panic if set twice
+pub fn set_panic_policy(policy: PanicPolicy) {
+    assert!(
+        PANIC_POLICY.set(policy).is_ok(),
+        "Panic policy is already set"
+    );
+}
+
+fn current_panic_action() -> PanicAction {
+    TASK_KIND
+        .with(Cell::get)
+        .map_or(PanicAction::Abort, |kind| {
+            PANIC_POLICY
+                .get()
+                .map_or(PanicAction::Abort, |p| p.action(kind))
+        })
+}
+
+/// Called by the panic hook. Records the panic of the current task and returns the action
+pub(crate) fn report_panic(message: &str) -> PanicAction {
+    let action = current_panic_action();
+    if TASK_KIND.with(Cell::get).is_some() {
+        let mut panics = PANICS.lock();
+        let entry = panics.entry(thread_name()).or_default();
+        entry.count = entry.count.saturating_add(1);
+        entry.last_message.replace(message.to_owned());
+    }
+    action
+}
+
+/// Runs task iterations, catching panics according to the panic policy. A faulted task (or
+/// a chain step) is not run anymore
+#[derive(Default)]
+pub(crate) struct Guard {
+    step: Option<String>,
+    faulted: bool,
+}
+
+impl Guard {
+    /// A guard of a chain step
+    pub(crate) fn step(kind: Kind, name: &str) -> Self {
+        Self {
+            step: Some(format!("{kind} {name}")),
+            faulted: false,
+        }
+    }
+    #[inline]
+    pub(crate) fn run<F>(&mut self, f: F)
+    where
+        F: FnOnce(),
+    {
+        if self.faulted {
+            return;
+        }
+        if panic::catch_unwind(panic::AssertUnwindSafe(f)).is_err()
+            && current_panic_action() == PanicAction::Fault
+        {
+            self.faulted = true;
+            fault_task(self.step.as_deref());
+        }
+    }
+}
+
+/// Marks the current task faulted, switches the controller into FAULT status and calls the
+/// fault hooks
+pub(crate) fn fault_task(step: Option<&str>) {
+    let name = thread_name();
+    if let Some(step) = step {
+        error!("task {} ({}) is faulted", name, step);
+    } else {
+        error!("task {} is faulted", name);
+    }
+    PANICS.lock().entry(name).or_default().faulted = true;
+    let was_fault = status() == Status::Fault;
+    set_fault();
+    if !was_fault && !hooks::run(hooks::Kind::Fault) {
+        warn!("no safe state function set");
+    }
+}
+
+fn set_fault() {
+    let status = status();
+    if status >= Status::Inactive && status != Status::Fault {
+        if status < Status::Preparing {
+            FAULT_BEFORE_SYNC.store(true, atomic::Ordering::Relaxed);
+        }
+        set_status(Status::Fault);
+    }
+}
+
+/// Runtime task control (pause/resume, interval change)
+pub(crate) struct TaskControl {
+    paused: atomic::AtomicBool,
+    // zero for tasks with no fixed interval
+    interval: atomic::AtomicU64,
+}
+
+impl TaskControl {
+    #[inline]
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(atomic::Ordering::Relaxed)
+    }
+    #[inline]
+    pub(crate) fn interval(&self) -> Option<Duration> {
+        let interval = self.interval.load(atomic::Ordering::Relaxed);
+        if interval == 0 {
+            None
+        } else {
+            Some(Duration::from_nanos(interval))
+        }
+    }
+}
+
+/// Registers the current thread control, the interval is None for tasks with no fixed interval
+pub(crate) fn register_task_control(interval: Option<Duration>) -> Arc<TaskControl> {
+    let control = Arc::new(TaskControl {
+        paused: atomic::AtomicBool::new(false),
+        interval: atomic::AtomicU64::new(
+            interval.map_or(0, |v| u64::try_from(v.as_nanos()).unwrap_or(u64::MAX)),
+        ),
+    });
+    TASK_CONTROLS.lock().insert(thread_name(), control.clone());
+    control
+}
+
+pub(crate) fn unregister_task_control(control: &Arc<TaskControl>) {
+    let mut controls = TASK_CONTROLS.lock();
+    let name = thread_name();
+    if controls.get(&name).is_some_and(|v| Arc::ptr_eq(v, control)) {
+        controls.remove(&name);
+    }
+}
+
+fn task_control(name: &str) -> EResult<Arc<TaskControl>> {
+    TASK_CONTROLS.lock().get(name).cloned().ok_or_else(|| {
+        Error::not_found(format!("task {} not found or can not be controlled", name))
+    })
+}
+
+/// Pauses a task (by the full thread name, e.g. "Pprogram1")
+///
+/// Paused tasks keep ticking but do not call their functions, except the final output sync
+pub fn pause_task(name: &str) -> EResult<()> {
+    task_control(name)?
+        .paused
+        .store(true, atomic::Ordering::Relaxed);
+    warn!("task {} paused", name);
+    Ok(())
+}
+
+/// Resumes a paused task
+pub fn resume_task(name: &str) -> EResult<()> {
+    task_control(name)?
+        .paused
+        .store(false, atomic::Ordering::Relaxed);
+    warn!("task {} resumed", name);
+    Ok(())
+}
+
+/// Changes the loop interval of a task, the new interval is applied at the next tick
+pub fn set_task_interval(name: &str, interval: Duration) -> EResult<()> {
+    let control = task_control(name)?;
+    if control.interval().is_none() {
+        return Err(Error::unsupported(format!(
+            "task {} has no fixed interval",
+            name
+        )));
+    }
+    let interval_ns = u64::try_from(interval.as_nanos())
+        .ok()
+        .filter(|v| *v > 0)
+        .ok_or_else(|| Error::invalid_params(format!("invalid interval: {:?}", interval)))?;
+    control
+        .interval
+        .store(interval_ns, atomic::Ordering::Relaxed);
+    warn!("task {} interval set to {:?}", name, interval);
+    Ok(())
+}
+
+/// Returns statistics of all registered tasks, including their runtime control state
+pub(crate) fn thread_infos() -> BTreeMap<String, Option<ThreadInfo>> {
+    let stats = CONTROLLER_STATS.lock();
+    let controls = TASK_CONTROLS.lock();
+    let panics = PANICS.lock();
+    stats
+        .thread_stats
+        .iter()
+        .map(|(name, st)| {
+            let info = st.info().map(|mut info| {
+                if let Some(control) = controls.get(name) {
+                    info.apply_control(control);
+                }
+                if let Some(p) = panics.get(name) {
+                    info.panics = p.count;
+                    info.last_panic.clone_from(&p.last_message);
+                    info.faulted = p.faulted;
+                }
+                info
+            });
+            (name.clone(), info)
+        })
+        .collect()
+}
@@ -0,0 +1,250 @@
+use crate::clock;
+use crate::trigger::Listener;
+use parking_lot::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// harnesses share the global context and triggers, so they are serialized
+static HARNESS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Program definition, generated by `#[plc_program]` as `NAME_program()`
+pub struct Program {
+    pub name: &'static str,
+    pub f: fn(),
+    pub period: ProgramPeriod,
+}
+
+pub enum ProgramPeriod {
+    Loop { interval: Duration, shift: Duration },
+    Trigger(&'static [&'static str]),
+}
+
+enum Schedule {
+    Loop { interval: Duration, next: Instant },
+    Trigger(Listener),
+}
+
+struct Entry {
+    name: &'static str,
+    f: fn(),
+    schedule: Schedule,
+}
+
+/// Drives PLC programs cycle by cycle against a virtual clock, without spawning tasks
+///
+/// Inputs are injected and outputs are checked directly with `plc_context_mut!()` and
+/// `plc_context!()`. While the harness exists, [`crate::clock::now()`] returns the virtual
+/// time in the current thread. Harnesses are serialized, as they share the global context.
+///
+/// ```ignore
+/// #[test]
+/// fn test_tempmon() {
+///     let mut h = Harness::new();
+///     h.add(tempmon_program());
+///     plc_context_mut!().temperature = 31.0;
+///     h.run_for(Duration::from_secs(1));
+///     assert!(plc_context!().fan);
+/// }
+/// ```
+pub struct Harness {
+    start: Instant,
+    now: Instant,
+    programs: Vec<Entry>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        let lock = HARNESS_LOCK.lock();
+        let start = Instant::now();
+        clock::set_virtual(Some(start));
+        Self {
+            start,
+            now: start,
+            programs: <_>::default(),
+            _lock: lock,
+        }
+    }
+    /// Adds a program. Loop programs are scheduled from the current virtual time plus their
+    /// shift
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the loop interval of the program is zero
+    pub fn add(&mut self, program: Program) -> &mut Self {
+        let schedule = match program.period {
+            ProgramPeriod::Loop { interval, shift } => {
+                assert!(
+                    !interval.is_zero(),
+                    "program {}: the loop interval can not be zero",
+                    program.name
+                );
+                Schedule::Loop {
+                    interval,
+                    next: self.now + shift,
+                }
+            }
+            ProgramPeriod::Trigger(names) => Schedule::Trigger(Listener::new(names)),
+        };
+        self.programs.push(Entry {
+            name: program.name,
+            f: program.f,
+            schedule,
+        });
+        self
+    }
+    /// Current virtual time
+    #[inline]
+    pub fn now(&self) -> Instant {
+        self.now
+    }
+    /// Virtual time elapsed since the harness has been created
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.now - self.start
+    }
+    fn set_now(&mut self, t: Instant) {
+        self.now = t;
+        clock::set_virtual(Some(t));
+    }
+    fn next_run(&self) -> Option<Instant> {
+        self.programs
+            .iter()
+            .filter_map(|p| match p.schedule {
+                Schedule::Loop { next, .. } => Some(next),
+                Schedule::Trigger(_) => None,
+            })
+            .min()
+    }
+    fn run_triggered(&mut self) {
+        for p in &self.programs {
+            if let Schedule::Trigger(ref listener) = p.schedule {
+                if listener.wait(Duration::ZERO).is_some() {
+                    log::trace!("harness: running triggered program {}", p.name);
+                    (p.f)();
+                }
+            }
+        }
+    }
+    /// Runs pending triggered programs, then advances the virtual clock to the next scheduled
+    /// loop and runs all loop programs due, in the order of adding. Returns the elapsed virtual
+    /// time
+    pub fn step(&mut self) -> Duration {
+        self.run_triggered();
+        if let Some(t) = self.next_run() {
+            if t > self.now {
+                self.set_now(t);
+            }
+            let now = self.now;
+            for p in &mut self.programs {
+                if let Schedule::Loop {
+                    interval,
+                    ref mut next,
+                } = p.schedule
+                {
+                    if *next <= now {
+                        log::trace!("harness: running program {}", p.name);
+                        (p.f)();
+                        *next += interval;
+                    }
+                }
+            }
+            self.run_triggered();
+        }
+        self.elapsed()
+    }
+    /// Runs the given number of steps
+    pub fn cycles(&mut self, n: usize) -> Duration {
+        for _ in 0..n {
+            self.step();
+        }
+        self.elapsed()
+    }
+    /// Runs all programs scheduled within the duration and advances the virtual clock by it
+    pub fn run_for(&mut self, d: Duration) -> Duration {
+        let end = self.now + d;
+        while self.next_run().is_some_and(|t| t <= end) {
+            self.step();
+        }
+        self.set_now(end);
+        self.run_triggered();
+        self.elapsed()
+    }
+    /// Advances the virtual clock without running loop programs
+    pub fn advance(&mut self, d: Duration) {
+        let t = self.now + d;
+        self.set_now(t);
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        clock::set_virtual(None);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    static LOOPS: AtomicU32 = AtomicU32::new(0);
+    static EVENTS: AtomicU32 = AtomicU32::new(0);
+
+    fn p_loop() {
+        LOOPS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn p_event() {
+        EVENTS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_harness() {
+        let mut h = Harness::new();
+        h.add(Program {
+            name: "p_loop",
+            f: p_loop,
+            period: ProgramPeriod::Loop {
+                interval: Duration::from_millis(100),
+                shift: Duration::ZERO,
+            },
+        })
+        .add(Program {
+            name: "p_event",
+            f: p_event,
+            period: ProgramPeriod::Trigger(&["test.harness"]),
+        });
+        assert!(clock::is_virtual());
+        let t = clock::now();
+        h.run_for(Duration::from_secs(1));
+        assert_eq!(LOOPS.load(Ordering::SeqCst), 11);
+        assert_eq!(clock::now() - t, Duration::from_secs(1));
+        assert_eq!(EVENTS.load(Ordering::SeqCst), 0);
+        crate::trigger::fire("test.harness");
+        crate::trigger::fire("test.harness");
+        h.step();
+        assert_eq!(EVENTS.load(Ordering::SeqCst), 1);
+        assert_eq!(h.elapsed(), Duration::from_millis(1100));
+        drop(h);
+        assert!(!clock::is_virtual());
+    }
+
+    #[test]
+    #[should_panic(expected = "the loop interval can not be zero")]
+    fn test_zero_interval() {
+        Harness::new().add(Program {
+            name: "p_loop",
+            f: p_loop,
+            period: ProgramPeriod::Loop {
+                interval: Duration::ZERO,
+                shift: Duration::ZERO,
+            },
+        });
+    }
+}
@@ -0,0 +1,368 @@
+use crate::interval::Loop;
+use crate::tasks;
+use eva_common::value::Value;
+use eva_common::{EResult, Error};
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+static TRENDS: Lazy<Mutex<BTreeMap<&'static str, TrendEntry>>> = Lazy::new(<_>::default);
+
+/// Trend recorder, samples context fields into a ring buffer and optional rotating CSV files
+///
+/// Trends are declared in the "trends" section of plc.yml
+pub struct Trend {
+    pub id: &'static str,
+    pub interval: Duration,
+    pub paths: &'static [&'static str],
+    /// ring buffer size (samples)
+    pub size: usize,
+    pub csv: Option<TrendCsv>,
+}
+
+/// CSV files are written to var_dir as NAME.trend.ID.csv and rotated when the size is exceeded
+pub struct TrendCsv {
+    /// max file size in bytes
+    pub max_size: u64,
+    /// number of rotated files to keep
+    pub keep: usize,
+}
+
+struct TrendEntry {
+    trend: &'static Trend,
+    buf: Arc<Mutex<VecDeque<Sample>>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Sample {
+    /// timestamp
+    pub t: f64,
+    /// values in the order of the trend paths
+    pub values: Vec<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TrendInfo {
+    pub id: String,
+    /// sampling interval in seconds
+    pub interval: f64,
+    pub paths: Vec<String>,
+    pub size: usize,
+    pub samples: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TrendData {
+    pub paths: Vec<String>,
+    pub samples: Vec<Sample>,
+}
+
+/// use init_plc!() macro to register trends
+///
+/// # Panics
+///
+/// Will panic if a trend is already registered
+pub fn register(trends: &'static [Trend]) {
+    let mut registered = TRENDS.lock();
+    for trend in trends {
+        assert!(
+            registered
+                .insert(
+                    trend.id,
+                    TrendEntry {
+                        trend,
+                        buf: Arc::new(Mutex::new(VecDeque::with_capacity(trend.size))),
+                    },
+                )
+                .is_none(),
+            "trend {} is already registered",
+            trend.id
+        );
+    }
+}
+
+/// Spawns recorders of all registered trends, called by the controller after I/O is launched
+pub(crate) fn spawn_all() {
+    for entry in TRENDS.lock().values() {
+        let trend = entry.trend;
+        let buf = entry.buf.clone();
+        info!(
+            "trend {}: {} every {:?}",
+            trend.id,
+            trend.paths.join(", "),
+            trend.interval
+        );
+        tasks::spawn_service(&format!("tr.{}", trend.id), move || {
+            let mut csv = trend.csv.as_ref().map(|c| {
+                let mut path = crate::var_dir();
+                path.push(format!("{}.trend.{}.csv", crate::name(), trend.id));
+                CsvWriter::new(trend, c, path)
+            });
+            let mut int = Loop::prepare0(trend.interval);
+            let mut failed = false;
+            loop {
+                int.tick();
+                match crate::context::get_many(trend.paths.iter().copied()) {
+                    Ok(values) => {
+                        failed = false;
+                        let sample = Sample {
+                            t: timestamp(),
+                            values,
+                        };
+                        if let Some(ref mut c) = csv {
+                            c.write(&sample);
+                        }
+                        push_sample(&mut buf.lock(), trend.size, sample);
+                    }
+                    Err(e) => {
+                        if !failed {
+                            error!("trend {} sampling error: {}", trend.id, e);
+                            failed = true;
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Appends a sample to the ring buffer, the oldest samples are evicted when the buffer is full
+fn push_sample(buf: &mut VecDeque<Sample>, size: usize, sample: Sample) {
+    if buf.len() >= size {
+        buf.pop_front();
+    }
+    buf.push_back(sample);
+}
+
+fn timestamp() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+pub fn list() -> Vec<TrendInfo> {
+    TRENDS
+        .lock()
+        .values()
+        .map(|entry| TrendInfo {
+            id: entry.trend.id.to_owned(),
+            interval: entry.trend.interval.as_secs_f64(),
+            paths: entry.trend.paths.iter().map(|&v| v.to_owned()).collect(),
+            size: entry.trend.size,
+            samples: entry.buf.lock().len(),
+        })
+        .collect()
+}
+
+/// Returns buffered trend samples, optionally only the ones after the timestamp and/or the last
+/// N samples
+pub fn get(id: &str, since: Option<f64>, limit: Option<usize>) -> EResult<TrendData> {
+    let (trend, buf) = {
+        let trends = TRENDS.lock();
+        let entry = trends
+            .get(id)
+            .ok_or_else(|| Error::not_found(format!("trend {} not found", id)))?;
+        (entry.trend, entry.buf.clone())
+    };
+    let buf = buf.lock();
+    let start = since.map_or(0, |t| buf.partition_point(|s| s.t <= t));
+    let start = limit.map_or(start, |l| start.max(buf.len().saturating_sub(l)));
+    Ok(TrendData {
+        paths: trend.paths.iter().map(|&v| v.to_owned()).collect(),
+        samples: buf.range(start..).cloned().collect(),
+    })
+}
+
+struct CsvWriter {
+    trend: &'static Trend,
+    max_size: u64,
+    keep: usize,
+    path: PathBuf,
+    file: Option<fs::File>,
+    size: u64,
+}
+
+impl CsvWriter {
+    fn new(trend: &'static Trend, config: &TrendCsv, path: PathBuf) -> Self {
+        Self {
+            trend,
+            max_size: config.max_size,
+            keep: config.keep,
+            path,
+            file: None,
+            size: 0,
+        }
+    }
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        self.path.with_extension(format!("{}.csv", n))
+    }
+    fn rotate(&mut self) {
+        self.file.take();
+        if self.keep == 0 {
+            let _ = fs::remove_file(&self.path);
+            return;
+        }
+        let _ = fs::remove_file(self.rotated_path(self.keep));
+        for n in (1..self.keep).rev() {
+            let _ = fs::rename(self.rotated_path(n), self.rotated_path(n + 1));
+        }
+        if let Err(e) = fs::rename(&self.path, self.rotated_path(1)) {
+            warn!("trend {} CSV rotation error: {}", self.trend.id, e);
+        }
+    }
+    fn open(&mut self) -> std::io::Result<()> {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = file.metadata()?.len();
+        if self.size == 0 {
+            let mut header = "t".to_owned();
+            for path in self.trend.paths {
+                header.push(',');
+                header.push_str(&csv_escape(path));
+            }
+            header.push('\n');
+            file.write_all(header.as_bytes())?;
+            self.size = header.len() as u64;
+        }
+        self.file.replace(file);
+        Ok(())
+    }
+    fn write(&mut self, sample: &Sample) {
+        if let Err(e) = self.try_write(sample) {
+            error!("trend {} CSV write error: {}", self.trend.id, e);
+            self.file.take();
+        }
+    }
+    fn try_write(&mut self, sample: &Sample) -> std::io::Result<()> {
+        if self.file.is_some() && self.size >= self.max_size {
+            self.rotate();
+        }
+        if self.file.is_none() {
+            self.open()?;
+        }
+        let mut line = format!("{:.6}", sample.t);
+        for value in &sample.values {
+            line.push(',');
+            line.push_str(&csv_escape(&value.to_string()));
+        }
+        line.push('\n');
+        if let Some(ref mut file) = self.file {
+            file.write_all(line.as_bytes())?;
+        }
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// Escapes a CSV field, used by the trend files and the CLI
+pub fn csv_escape(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample(t: f64) -> Sample {
+        Sample {
+            t,
+            values: vec![Value::F64(t)],
+        }
+    }
+
+    static TREND: Trend = Trend {
+        id: "test",
+        interval: Duration::from_secs(1),
+        paths: &["temp", "fan"],
+        size: 3,
+        csv: None,
+    };
+
+    #[test]
+    fn test_ring_buffer() {
+        let mut buf = VecDeque::new();
+        for t in 1..=5 {
+            push_sample(&mut buf, 3, sample(f64::from(t)));
+        }
+        assert_eq!(buf.iter().map(|s| s.t).collect::<Vec<_>>(), [3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn test_get() {
+        register(std::slice::from_ref(&TREND));
+        {
+            let trends = TRENDS.lock();
+            let mut buf = trends.get("test").unwrap().buf.lock();
+            for t in 1..=5 {
+                push_sample(&mut buf, TREND.size, sample(f64::from(t)));
+            }
+        }
+        let times = |since, limit| {
+            get("test", since, limit)
+                .unwrap()
+                .samples
+                .iter()
+                .map(|s| s.t)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(times(None, None), [3.0, 4.0, 5.0]);
+        assert_eq!(times(Some(3.0), None), [4.0, 5.0]);
+        assert_eq!(times(Some(3.5), None), [4.0, 5.0]);
+        assert!(times(Some(5.0), None).is_empty());
+        assert_eq!(times(None, Some(2)), [4.0, 5.0]);
+        assert_eq!(times(None, Some(10)), [3.0, 4.0, 5.0]);
+        assert!(times(None, Some(0)).is_empty());
+        assert_eq!(times(Some(3.0), Some(1)), [5.0]);
+        assert_eq!(get("test", None, None).unwrap().paths, ["temp", "fan"]);
+        assert!(get("missing", None, None).is_err());
+    }
+
+    #[test]
+    fn test_csv_escape() {
+        assert_eq!(csv_escape("temp"), "temp");
+        assert_eq!(csv_escape("a,b"), "\"a,b\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_escape("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_escape(""), "");
+    }
+
+    #[test]
+    fn test_csv_rotation() {
+        let dir = std::env::temp_dir().join(format!("rplc_test_trend_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("plc.trend.test.csv");
+        let config = TrendCsv {
+            max_size: 1,
+            keep: 2,
+        };
+        let mut csv = CsvWriter::new(&TREND, &config, path.clone());
+        assert_eq!(csv.rotated_path(1), dir.join("plc.trend.test.1.csv"));
+        assert_eq!(csv.rotated_path(2), dir.join("plc.trend.test.2.csv"));
+        // every write exceeds the max size, so each following one rotates the file
+        for t in 1..=4 {
+            csv.try_write(&sample(f64::from(t))).unwrap();
+        }
+        let read = |p: &std::path::Path| fs::read_to_string(p).unwrap();
+        assert_eq!(read(&path), "t,temp,fan\n4.000000,4\n");
+        assert_eq!(read(&csv.rotated_path(1)), "t,temp,fan\n3.000000,3\n");
+        assert_eq!(read(&csv.rotated_path(2)), "t,temp,fan\n2.000000,2\n");
+        assert!(!csv.rotated_path(3).exists());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,97 @@
+use once_cell::sync::Lazy;
+use parking_lot::{Condvar, Mutex};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+static LISTENERS: Lazy<Mutex<BTreeMap<String, Vec<Arc<Signal>>>>> = Lazy::new(<_>::default);
+
+#[derive(Default)]
+struct Signal {
+    // holds the time of the first unprocessed fire
+    fired: Mutex<Option<Instant>>,
+    cv: Condvar,
+}
+
+impl Signal {
+    fn set(&self, t: Instant) {
+        let mut fired = self.fired.lock();
+        if fired.is_none() {
+            fired.replace(t);
+        }
+        self.cv.notify_one();
+    }
+}
+
+/// Fires a named trigger, waking up all its listeners
+///
+/// If a listener is busy, fires are coalesced and the listener is woken up once.
+///
+/// The generated I/O code fires the following triggers:
+///
+/// - "io.ID" after an input worker of the I/O ID has successfully updated the context
+///
+/// - "ctx.PATH" when an input mapping with "trigger: true" has changed the context field
+pub fn fire(name: &str) {
+    if let Some(signals) = LISTENERS.lock().get(name) {
+        let now = Instant::now();
+        for signal in signals {
+            signal.set(now);
+        }
+    }
+}
+
+/// Returns true if the trigger has listeners
+pub fn has_listeners(name: &str) -> bool {
+    LISTENERS.lock().contains_key(name)
+}
+
+/// Listens to one or several named triggers
+pub struct Listener {
+    names: Vec<String>,
+    signal: Arc<Signal>,
+}
+
+impl Listener {
+    pub fn new(names: &[&str]) -> Self {
+        let signal = Arc::new(Signal::default());
+        let mut listeners = LISTENERS.lock();
+        for name in names {
+            listeners
+                .entry((*name).to_owned())
+                .or_default()
+                .push(signal.clone());
+        }
+        Self {
+            names: names.iter().map(|v| (*v).to_owned()).collect(),
+            signal,
+        }
+    }
+    #[inline]
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+    /// Waits until any of the triggers is fired. Returns the time of the (first) fire or None
+    /// on timeout
+    pub fn wait(&self, timeout: Duration) -> Option<Instant> {
+        let mut fired = self.signal.fired.lock();
+        if fired.is_none() {
+            let _ = self.signal.cv.wait_for(&mut fired, timeout);
+        }
+        fired.take()
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let mut listeners = LISTENERS.lock();
+        for name in &self.names {
+            if let Some(signals) = listeners.get_mut(name) {
+                signals.retain(|v| !Arc::ptr_eq(v, &self.signal));
+                if signals.is_empty() {
+                    listeners.remove(name);
+                }
+            }
+        }
+    }
+}